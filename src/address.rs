@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{Ipv6Addr, SocketAddr, SocketAddrV6, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
+use crossbeam_channel::{Receiver, Sender};
+
+// Ошибка разрешения имени. Сборщик заворачивает её в io::Error, чтобы отличить от ошибки соединения
+#[derive(Debug)]
+pub struct DnsError(pub String);
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DNS: {}", self.0)
+    }
+}
+
+impl std::error::Error for DnsError {}
+
+pub fn dns_error(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::other(DnsError(message.into()))
+}
+
+pub fn is_dns_error(err: &std::io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<DnsError>())
+}
+
+// Куда подключаться: готовый сокет-адрес или имя хоста, которое ещё нужно разрешить
+pub enum Target {
+    Socket(SocketAddr),
+    Host(String, u16),
+}
+
+// Разбирает host:port, a.b.c.d:port и [ipv6%zone]:port. Строка в конфиге хранится как ввели,
+// разбор выполняется заново перед каждым подключением
+pub fn parse(text: &str) -> Result<Target, String> {
+    if let Some(rest) = text.strip_prefix('[') {
+        let (host, port) = rest
+            .split_once(']')
+            .ok_or_else(|| "Нет закрывающей скобки ]".to_string())?;
+        let port = port
+            .strip_prefix(':')
+            .ok_or_else(|| "Не указан порт ([ipv6]:port)".to_string())?;
+        let port = parse_port(port)?;
+        let (ip, zone) = match host.split_once('%') {
+            Some((ip, zone)) => (ip, Some(zone)),
+            None => (host, None),
+        };
+        let ip: Ipv6Addr = ip.parse().map_err(|_| format!("Неверный IPv6-адрес: {}", ip))?;
+        let scope_id = zone.map(parse_zone).transpose()?.unwrap_or(0);
+        return Ok(Target::Socket(SocketAddr::V6(SocketAddrV6::new(ip, port, 0, scope_id))));
+    }
+
+    let (host, port) = text
+        .rsplit_once(':')
+        .ok_or_else(|| "Не указан порт (host:port)".to_string())?;
+    if host.contains(':') {
+        return Err("IPv6-адрес нужно взять в скобки: [fe80::1]:9000".to_string());
+    }
+    if host.is_empty() {
+        return Err("Не указан хост".to_string());
+    }
+    let port = parse_port(port)?;
+    match host.parse() {
+        Ok(ip) => Ok(Target::Socket(SocketAddr::new(ip, port))),
+        Err(_) => Ok(Target::Host(host.to_string(), port)),
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, String> {
+    port.parse().map_err(|_| format!("Неверный порт: {}", port))
+}
+
+// Зона — номер интерфейса или его имя (eth0)
+fn parse_zone(zone: &str) -> Result<u32, String> {
+    if let Ok(index) = zone.parse() {
+        return Ok(index);
+    }
+    interface_index(zone).ok_or_else(|| format!("Неизвестный интерфейс: {}", zone))
+}
+
+#[cfg(unix)]
+fn interface_index(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: name — корректная C-строка, функция только читает её
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    (index != 0).then_some(index)
+}
+
+#[cfg(not(unix))]
+fn interface_index(_name: &str) -> Option<u32> {
+    None
+}
+
+// Результат проверки адреса: None — резолвер ещё не ответил
+pub type CheckResult = Option<Result<(), String>>;
+
+// Сколько верить ответу резолвера. Ошибка бывает временной (DNS не ответил, сеть поднимается),
+// поэтому перепроверяется чаще удачного ответа
+const OK_TTL:    Duration = Duration::from_secs(300);
+const ERROR_TTL: Duration = Duration::from_secs(15);
+
+struct Entry {
+    result:  CheckResult,
+    // Когда пришёл result
+    checked: Instant,
+    // Адрес стоит в очереди резолвера; до ответа показывается прежний результат
+    pending: bool,
+}
+
+impl Entry {
+    fn expired(&self, now: Instant) -> bool {
+        let ttl = match &self.result {
+            None => return false,
+            Some(Ok(())) => OK_TTL,
+            Some(Err(_)) => ERROR_TTL,
+        };
+        now.saturating_duration_since(self.checked) > ttl
+    }
+}
+
+// Адреса разрешаются по очереди в одном фоновом потоке, чтобы медленный DNS не подвешивал кадр.
+// Результаты кешируются по строке адреса и перепроверяются по истечении OK_TTL / ERROR_TTL
+pub struct AddressChecks {
+    results:  HashMap<String, Entry>,
+    requests: Sender<String>,
+    rx:       Receiver<(String, Result<(), String>)>,
+}
+
+impl AddressChecks {
+    pub fn new() -> Self {
+        let (requests, queue) = crossbeam_channel::unbounded::<String>();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        // Поток живёт, пока жив AddressChecks: закрытая очередь завершает цикл
+        thread::spawn(move || {
+            for address in queue {
+                let result = resolve(&address);
+                if tx.send((address, result)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { results: HashMap::new(), requests, rx }
+    }
+
+    // Забирает готовые ответы резолвера
+    pub fn poll(&mut self) {
+        while let Ok((address, result)) = self.rx.try_recv() {
+            self.results.insert(address, Entry { result: Some(result), checked: Instant::now(), pending: false });
+        }
+    }
+
+    // Возвращает известный результат; при первом обращении и по истечении срока ставит адрес в очередь
+    pub fn check(&mut self, address: &str) -> &CheckResult {
+        let entry = self
+            .results
+            .entry(address.to_string())
+            .or_insert_with(|| Entry { result: None, checked: Instant::now(), pending: false });
+        if !entry.pending && (entry.result.is_none() || entry.expired(Instant::now())) {
+            entry.pending = self.requests.send(address.to_string()).is_ok();
+        }
+        &entry.result
+    }
+
+    // Проверить адрес заново, не дожидаясь срока: Старт не должен упираться в старую временную ошибку
+    pub fn recheck(&mut self, address: &str) {
+        match self.results.get_mut(address) {
+            Some(entry) if !entry.pending => entry.pending = self.requests.send(address.to_string()).is_ok(),
+            Some(_) => {}
+            None => {
+                self.check(address);
+            }
+        }
+    }
+
+    // Первая известная ошибка среди адресов
+    pub fn first_error<'a>(&self, addresses: impl IntoIterator<Item = &'a str>) -> Option<(&'a str, &str)> {
+        addresses.into_iter().find_map(|address| match self.results.get(address).map(|entry| &entry.result) {
+            Some(Some(Err(error))) => Some((address, error.as_str())),
+            _ => None,
+        })
+    }
+}
+
+fn resolve(address: &str) -> Result<(), String> {
+    let (host, port) = match parse(address)? {
+        Target::Socket(_) => return Ok(()),
+        Target::Host(host, port) => (host, port),
+    };
+    let mut addrs = (host.as_str(), port).to_socket_addrs().map_err(|e| e.to_string())?;
+    match addrs.next() {
+        Some(_) => Ok(()),
+        None => Err("Имя не разрешается ни в один адрес".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ждёт ответа резолвера на адрес; литералы и ошибки разбора приходят без обращения к DNS
+    fn wait(checks: &mut AddressChecks, address: &str) -> Result<(), String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            checks.poll();
+            if let Some(result) = checks.check(address) {
+                return result.clone();
+            }
+            assert!(Instant::now() < deadline, "resolver did not answer {}", address);
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn wait_fresh(checks: &mut AddressChecks, address: &str) -> Result<(), String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while checks.results[address].pending {
+            assert!(Instant::now() < deadline, "resolver did not answer {}", address);
+            thread::sleep(Duration::from_millis(5));
+            checks.poll();
+        }
+        checks.results[address].result.clone().expect("ответ получен")
+    }
+
+    #[test]
+    fn checks_go_through_one_worker_and_are_cached() {
+        let mut checks = AddressChecks::new();
+        assert_eq!(checks.check("127.0.0.1:9000"), &None);
+        assert!(checks.results["127.0.0.1:9000"].pending);
+        assert_eq!(wait(&mut checks, "127.0.0.1:9000"), Ok(()));
+        assert!(wait(&mut checks, "127.0.0.1").is_err());
+
+        // Свежий ответ повторно не запрашивается
+        checks.check("127.0.0.1:9000");
+        assert!(!checks.results["127.0.0.1:9000"].pending);
+        assert_eq!(checks.first_error(["127.0.0.1:9000", "127.0.0.1"]).map(|(a, _)| a), Some("127.0.0.1"));
+    }
+
+    #[test]
+    fn failures_expire_before_successes() {
+        let now = Instant::now();
+        let ok = Entry { result: Some(Ok(())), checked: now, pending: false };
+        let failed = Entry { result: Some(Err("DNS".to_string())), checked: now, pending: false };
+        let later = now + ERROR_TTL + Duration::from_secs(1);
+        assert!(failed.expired(later));
+        assert!(!ok.expired(later));
+        assert!(ok.expired(now + OK_TTL + Duration::from_secs(1)));
+        assert!(!Entry { result: None, checked: now, pending: true }.expired(later));
+    }
+
+    #[test]
+    fn recheck_requeues_a_failed_address() {
+        let mut checks = AddressChecks::new();
+        assert!(wait(&mut checks, "host").is_err());
+        checks.recheck("host");
+        assert!(checks.results["host"].pending);
+        // Прежняя ошибка видна, пока не придёт новый ответ
+        assert!(checks.first_error(["host"]).is_some());
+        assert!(wait_fresh(&mut checks, "host").is_err());
+    }
+
+    fn socket(text: &str) -> SocketAddr {
+        match parse(text) {
+            Ok(Target::Socket(addr)) => addr,
+            Ok(Target::Host(host, port)) => panic!("{} parsed as host {}:{}", text, host, port),
+            Err(e) => panic!("{}: {}", text, e),
+        }
+    }
+
+    #[test]
+    fn parses_ip_literals() {
+        assert_eq!(socket("192.168.1.10:502"), "192.168.1.10:502".parse().unwrap());
+        let v6 = socket("[fe80::1]:9000");
+        assert_eq!(v6, "[fe80::1]:9000".parse().unwrap());
+        let SocketAddr::V6(zoned) = socket("[fe80::1%3]:9000") else { panic!("not IPv6") };
+        assert_eq!((zoned.ip().to_string(), zoned.port(), zoned.scope_id()), ("fe80::1".to_string(), 9000, 3));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn zone_can_name_an_interface() {
+        let SocketAddr::V6(zoned) = socket("[fe80::1%lo]:9000") else { panic!("not IPv6") };
+        assert_eq!(Some(zoned.scope_id()), interface_index("lo"));
+        assert!(parse("[fe80::1%no-such-if0]:9000").is_err());
+    }
+
+    #[test]
+    fn hostnames_are_resolved_later() {
+        match parse("sensor-1.lab:9000") {
+            Ok(Target::Host(host, port)) => assert_eq!((host.as_str(), port), ("sensor-1.lab", 9000)),
+            _ => panic!("hostname not kept for resolution"),
+        }
+    }
+
+    #[test]
+    fn rejects_incomplete_addresses() {
+        for text in ["10.0.0.1", "sensor", "[fe80::1]", "[fe80::1]9000", "[fe80::1:9000", ":9000", "host:", "host:70000", "[zz::1]:9000"] {
+            assert!(parse(text).is_err(), "{} accepted", text);
+        }
+        // Без скобок порт IPv6 не отделить — подсказываем, как записать
+        assert!(parse("fe80::1:9000").err().unwrap().contains("[fe80::1]:9000"));
+    }
+}
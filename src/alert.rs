@@ -0,0 +1,81 @@
+use crate::{channel::{self, ChannelDef, Column}, ServerInfo};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Below,
+    Above,
+}
+
+impl Bound {
+    // Машиночитаемый код для экспорта
+    pub fn code(&self) -> &'static str {
+        match self {
+            Bound::Below => "below",
+            Bound::Above => "above",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Bound::Below => "ниже min",
+            Bound::Above => "выше max",
+        }
+    }
+}
+
+// Выход канала за порог. Запись не удаляется при возврате в диапазон, а получает время разрешения.
+// Время — миллисекунды от начала сбора, как у ComputationResults
+#[derive(Clone, Debug)]
+pub struct AlertEvent {
+    pub timestamp:    u64,
+    pub channel:      String,
+    pub value:        f64,
+    pub bound:        Bound,
+    pub limit:        f64,
+    pub resolved:     Option<u64>,
+    // Оператор видел событие; отметка есть только в копии GUI
+    pub acknowledged: bool,
+}
+
+// Нарушенный порог и его значение. None в min/max — проверка по этой границе выключена
+fn violation(def: &ChannelDef, value: f64) -> Option<(Bound, f64)> {
+    if let Some(min) = def.min.filter(|&min| value < min) {
+        return Some((Bound::Below, min));
+    }
+    def.max.filter(|&max| value > max).map(|max| (Bound::Above, max))
+}
+
+// Пропуск отсчёта не меняет состояние тревоги: по нему нельзя сказать, вернулся ли канал в диапазон
+pub fn check(alerts: &mut Vec<AlertEvent>, columns: &[Column], servers: &[ServerInfo], timestamp: u64, flow: &[Option<f64>]) {
+    for (column, value) in columns.iter().zip(flow) {
+        // У удалённого сервера нет порогов, его колонка не проверяется
+        let (Some(value), Some((server, def))) = (*value, channel::resolve(servers, column)) else { continue };
+        let label = channel::label(server, def);
+        let violation = violation(def, value);
+        let active = alerts.iter().rposition(|a| a.channel == label && a.resolved.is_none());
+
+        if let Some(i) = active {
+            // Переход через весь диапазон за один тик — старая тревога разрешается, открывается новая
+            if violation.is_some_and(|(bound, _)| bound == alerts[i].bound) {
+                continue;
+            }
+            alerts[i].resolved = Some(timestamp);
+        }
+        if let Some((bound, limit)) = violation {
+            alerts.push(AlertEvent { timestamp, channel: label, value, bound, limit, resolved: None, acknowledged: false });
+        }
+    }
+}
+
+pub fn unacknowledged(alerts: &[AlertEvent]) -> usize {
+    alerts.iter().filter(|a| !a.acknowledged).count()
+}
+
+// Неразрешённые тревоги по каналам сервера
+pub fn active_for<'a>(alerts: &'a [AlertEvent], server: &ServerInfo) -> Vec<&'a AlertEvent> {
+    let labels: Vec<String> = server.channels.iter().map(|def| channel::label(server, def)).collect();
+    alerts
+        .iter()
+        .filter(|a| a.resolved.is_none() && labels.contains(&a.channel))
+        .collect()
+}
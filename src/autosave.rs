@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use crate::{session::Session, AutosaveSettings, ServerData};
+
+const SNAPSHOT_FILE: &str = "autosave.json";
+const PREVIOUS_FILE: &str = "autosave.prev.json";
+
+// Снимки сессии во время сбора, в формате сессии JSON. Новый снимок пишется во временный файл
+// и переименовывается, прежний сохраняется как autosave.prev.json
+#[derive(Default)]
+pub struct Autosaver {
+    next_due: Option<Instant>,
+    writing:  Option<JoinHandle<()>>,
+}
+
+impl Autosaver {
+    // Вызывается каждый тик сбора. Копия данных снимается на тике, запись и сериализация — в пуле
+    // блокирующих задач, так что диск не задерживает опрос
+    pub fn tick(&mut self, data: &ServerData, settings: &AutosaveSettings, now: Instant) {
+        if !settings.enabled || data.computed_results.is_empty() {
+            return;
+        }
+        let interval = Duration::from_secs(settings.interval_min.max(1) * 60);
+        let due = *self.next_due.get_or_insert(now + interval);
+        // Предыдущая запись ещё идёт — ждём следующего тика, а не копим задачи
+        if now < due || self.writing.as_ref().is_some_and(|task| !task.is_finished()) {
+            return;
+        }
+        self.next_due = Some(now + interval);
+
+        let session = Session::from_data(data);
+        let dir = PathBuf::from(&settings.dir);
+        self.writing = Some(tokio::task::spawn_blocking(move || {
+            if let Err(e) = write_snapshot(&dir, &session) {
+                tracing::error!(dir = %dir.display(), error = %e, "autosave failed");
+            }
+        }));
+    }
+
+    // Новая сессия отсчитывает интервал заново
+    pub fn reset(&mut self) {
+        self.next_due = None;
+    }
+}
+
+fn write_snapshot(dir: &Path, session: &Session) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(SNAPSHOT_FILE);
+    let tmp = dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+    fs::write(&tmp, serde_json::to_string(session)?)?;
+    if path.exists() {
+        fs::rename(&path, dir.join(PREVIOUS_FILE))?;
+    }
+    fs::rename(&tmp, &path)
+}
+
+// Снимок, оставшийся от прошлого запуска: сессию не выгрузили, значит, её можно восстановить
+pub fn find(dir: &str) -> Option<PathBuf> {
+    let path = Path::new(dir).join(SNAPSHOT_FILE);
+    path.exists().then_some(path)
+}
+
+// Данные выгружены или восстановление отклонено — снимки больше не нужны
+pub fn discard(dir: &str) {
+    for file in [SNAPSHOT_FILE, PREVIOUS_FILE] {
+        match fs::remove_file(Path::new(dir).join(file)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => tracing::warn!(dir, error = %e, "autosave cleanup failed"),
+            _ => {}
+        }
+    }
+}
+
+pub fn default_dir() -> String {
+    crate::config::config_dir()
+        .map(|dir| dir.join("autosave"))
+        .unwrap_or_else(|| PathBuf::from("autosave"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{handle_command, session, CollectorCommand, ComputationResults, Marker};
+
+    // Отметки, поставленные в GUI, должны попасть в снимок сборщика и вернуться при восстановлении
+    #[tokio::test]
+    async fn markers_survive_recovery() {
+        let dir = std::env::temp_dir().join(format!("enlil-autosave-{}", std::process::id()));
+        let settings = AutosaveSettings { enabled: true, interval_min: 1, dir: dir.to_string_lossy().into_owned() };
+        let mut data = ServerData { start_time: Some(1_700_000_000_000), ..Default::default() };
+        for timestamp in [0, 1000, 2000] {
+            data.computed_results.push(ComputationResults {
+                timestamp,
+                flow:        vec![Some(1.0)],
+                sampled:     1,
+                channels:    1,
+                after_pause: false,
+                quality:     Vec::new(),
+            });
+        }
+        let markers = vec![
+            Marker { timestamp: 500, label: "клапан открыт".to_string() },
+            Marker { timestamp: 1500, label: "поджиг".to_string() },
+        ];
+        handle_command(&mut data, CollectorCommand::Markers(markers));
+
+        let mut autosaver = Autosaver::default();
+        let now = Instant::now();
+        autosaver.tick(&data, &settings, now);
+        autosaver.tick(&data, &settings, now + Duration::from_secs(61));
+        autosaver.writing.take().expect("снимок запущен").await.unwrap();
+
+        let path = find(&settings.dir).expect("снимок записан");
+        let restored = session::load(&path).unwrap().into_data();
+        discard(&settings.dir);
+        let _ = fs::remove_dir(&dir);
+
+        let restored: Vec<(u64, &str)> = restored.markers.iter().map(|m| (m.timestamp, m.label.as_str())).collect();
+        assert_eq!(restored, [(500, "клапан открыт"), (1500, "поджиг")]);
+    }
+}
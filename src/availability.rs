@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use crate::{channel::Column, ComputationResults, SampleQuality, ServerInfo};
+
+// Простой сервера: отсчёты от первого тика без ответа до первого тика с ответом, мс от начала сбора
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Outage {
+    pub start: u64,
+    pub end:   u64,
+}
+
+impl Outage {
+    pub fn duration(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+// Доступность сервера за сессию для отчёта об испытании
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ServerAvailability {
+    pub server:   String,
+    pub address:  String,
+    pub outages:  Vec<Outage>,
+    // Суммарный и самый долгий простой, мс
+    pub downtime: u64,
+    pub longest:  u64,
+}
+
+// Что отсчёт говорит о сервере: молчал, ответил или не опрашивался (выключен, ещё не добавлен)
+#[derive(PartialEq)]
+enum Observed {
+    Offline,
+    Online,
+    Unknown,
+}
+
+fn observe(result: &ComputationResults, indices: &[usize]) -> Observed {
+    let flags: Vec<SampleQuality> = indices.iter().filter_map(|&i| result.quality.get(i).copied()).collect();
+    let offline = |flag: &SampleQuality| matches!(flag, SampleQuality::Timeout | SampleQuality::Error | SampleQuality::Skipped);
+    if flags.iter().any(|flag| matches!(flag, SampleQuality::Good | SampleQuality::Retried | SampleQuality::Parse)) {
+        Observed::Online
+    } else if !flags.is_empty() && flags.iter().all(offline) {
+        Observed::Offline
+    } else {
+        Observed::Unknown
+    }
+}
+
+// Интервалы простоя по флагам качества отсчётов. Простой длится до отсчёта, на котором сервер
+// снова ответил или перестал опрашиваться; не закрытый к концу сессии закрывается последним отсчётом.
+// Сессии без флагов качества дают пустой отчёт
+pub fn report(results: &[ComputationResults], columns: &[Column], servers: &[ServerInfo]) -> Vec<ServerAvailability> {
+    let last = results.last().map_or(0, |result| result.timestamp);
+    servers
+        .iter()
+        .map(|server| {
+            let indices: Vec<usize> = columns
+                .iter()
+                .enumerate()
+                .filter(|(_, column)| column.server == server.id)
+                .map(|(i, _)| i)
+                .collect();
+            let mut outages = Vec::new();
+            let mut open = None;
+            for result in results {
+                match (observe(result, &indices), open) {
+                    (Observed::Offline, None) => open = Some(result.timestamp),
+                    (Observed::Online | Observed::Unknown, Some(start)) => {
+                        outages.push(Outage { start, end: result.timestamp });
+                        open = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(start) = open {
+                outages.push(Outage { start, end: last });
+            }
+            ServerAvailability {
+                server:   server.name.clone(),
+                address:  server.address.clone(),
+                downtime: outages.iter().map(Outage::duration).sum(),
+                longest:  outages.iter().map(Outage::duration).max().unwrap_or(0),
+                outages,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,260 @@
+// Имитатор прибора для разработки без стенда: слушает один или несколько портов
+// и на команду опроса отвечает строкой синтетических значений через пробел, как настоящий прибор.
+//
+//   cargo run --bin mock_server -- --port 9000 --port 9001 --wave sine --channels 2
+//   cargo run --bin mock_server -- --port 9000 --wave script --script "1,2,err,delay:1500,garbage,3"
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use clap::{Parser, ValueEnum};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+    time,
+};
+
+// Команды, на которые отвечает прибор; остальные получают ERR
+const COMMANDS: [&str; 2] = ["rffff0", "getData"];
+// Сколько ждать команду от клиента после подключения
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Parser)]
+#[command(about = "Имитатор прибора для enlil")]
+pub struct Args {
+    /// Порт прибора, можно указать несколько раз
+    #[arg(long = "port", default_value = "9000")]
+    ports:      Vec<u16>,
+    #[arg(long, default_value = "127.0.0.1")]
+    host:       String,
+    #[arg(long, value_enum, default_value = "sine")]
+    wave:       Wave,
+    /// Сколько значений в одном ответе
+    #[arg(long, default_value_t = 1)]
+    channels:   usize,
+    #[arg(long, default_value_t = 10.0)]
+    amplitude:  f64,
+    #[arg(long, default_value_t = 0.0)]
+    offset:     f64,
+    /// Период синуса и пилы, с
+    #[arg(long, default_value_t = 10.0)]
+    period:     f64,
+    /// Сценарий для --wave script через запятую: число, err (разрыв без ответа),
+    /// garbage (нечисловой ответ), delay:MS (ответить с задержкой). Повторяется по кругу
+    #[arg(long, default_value = "")]
+    script:     String,
+    /// Доля запросов, на которые соединение рвётся без ответа, 0..1
+    #[arg(long, default_value_t = 0.0)]
+    error_rate: f64,
+    /// Задержка перед каждым ответом, мс
+    #[arg(long, default_value_t = 0)]
+    delay_ms:   u64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Wave {
+    Sine,
+    Ramp,
+    Noise,
+    Script,
+}
+
+// Шаг сценария
+#[derive(Clone)]
+pub enum Step {
+    Value(f64),
+    Error,
+    Garbage,
+    Delay(u64),
+}
+
+// Что сделать с очередным запросом
+enum Reply {
+    Values(Vec<f64>),
+    Garbage,
+    Drop,
+}
+
+struct Instrument {
+    args:     Arc<Args>,
+    script:   Arc<Vec<Step>>,
+    // Номер прибора: сдвигает фазу, чтобы линии разных портов не совпадали
+    number:   usize,
+    requests: AtomicU64,
+    started:  Instant,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let script = match parse_script(&args.script) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("Script error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if matches!(args.wave, Wave::Script) && script.is_empty() {
+        eprintln!("--wave script требует --script");
+        std::process::exit(2);
+    }
+
+    let instruments = match start(args, script).await {
+        Ok(instruments) => instruments,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    for (number, (addr, _)) in instruments.iter().enumerate() {
+        println!("Прибор {} слушает {}", number + 1, addr);
+    }
+    futures::future::join_all(instruments.into_iter().map(|(_, task)| task)).await;
+}
+
+// По прибору на каждый порт из args, порт 0 — свободный от системы. Тесты enlil поднимают
+// приборы этой же функцией и останавливают их через JoinHandle
+pub async fn start(args: Args, script: Vec<Step>) -> io::Result<Vec<(SocketAddr, JoinHandle<()>)>> {
+    let args = Arc::new(args);
+    let script = Arc::new(script);
+    let mut instruments = Vec::new();
+    for (number, port) in args.ports.iter().enumerate() {
+        let listener = TcpListener::bind((args.host.as_str(), *port)).await.map_err(|e| {
+            io::Error::new(e.kind(), format!("Listen error ({}:{}): {}", args.host, port, e))
+        })?;
+        let addr = listener.local_addr()?;
+        let instrument = Arc::new(Instrument {
+            args: args.clone(),
+            script: script.clone(),
+            number,
+            requests: AtomicU64::new(0),
+            started: Instant::now(),
+        });
+        instruments.push((addr, tokio::spawn(serve(listener, instrument))));
+    }
+    Ok(instruments)
+}
+
+pub fn parse_script(text: &str) -> Result<Vec<Step>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| match item {
+            "err" => Ok(Step::Error),
+            "garbage" => Ok(Step::Garbage),
+            _ => match item.strip_prefix("delay:") {
+                Some(ms) => ms.parse().map(Step::Delay).map_err(|_| format!("неверная задержка: {}", item)),
+                None => item.parse().map(Step::Value).map_err(|_| format!("неизвестный шаг: {}", item)),
+            },
+        })
+        .collect()
+}
+
+async fn serve(listener: TcpListener, instrument: Arc<Instrument>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(handle(stream, peer, instrument.clone()));
+            }
+            Err(e) => eprintln!("Accept error: {}", e),
+        }
+    }
+}
+
+// Один запрос на соединение: команда, ответ, закрытие — так опрашивает enlil
+async fn handle(mut stream: TcpStream, peer: SocketAddr, instrument: Arc<Instrument>) {
+    let mut buf = [0u8; 256];
+    let len = match time::timeout(READ_TIMEOUT, stream.read(&mut buf)).await {
+        Ok(Ok(len)) => len,
+        Ok(Err(e)) => {
+            eprintln!("Read error ({}): {}", peer, e);
+            return;
+        }
+        Err(_) => return,
+    };
+    let command = String::from_utf8_lossy(&buf[..len]).trim().to_string();
+    if !COMMANDS.contains(&command.as_str()) {
+        let _ = stream.write_all(format!("ERR unknown command {:?}\n", command).as_bytes()).await;
+        return;
+    }
+
+    let (reply, delay) = instrument.next_reply();
+    time::sleep(delay).await;
+    let response = match reply {
+        Reply::Values(values) => {
+            let fields: Vec<String> = values.iter().map(|v| format!("{:.6}", v)).collect();
+            fields.join(" ") + "\n"
+        }
+        Reply::Garbage => "#?!\n".to_string(),
+        // Соединение закрывается без ответа
+        Reply::Drop => return,
+    };
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        eprintln!("Write error ({}): {}", peer, e);
+    }
+    let _ = stream.shutdown().await;
+}
+
+impl Instrument {
+    fn next_reply(&self) -> (Reply, Duration) {
+        let args = &self.args;
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        let mut delay = Duration::from_millis(args.delay_ms);
+        if args.error_rate > 0.0 && random_unit(request) < args.error_rate {
+            return (Reply::Drop, delay);
+        }
+
+        let reply = match args.wave {
+            Wave::Script => match &self.script[request as usize % self.script.len()] {
+                Step::Value(value) => Reply::Values(vec![*value; args.channels]),
+                Step::Error => Reply::Drop,
+                Step::Garbage => Reply::Garbage,
+                Step::Delay(ms) => {
+                    delay += Duration::from_millis(*ms);
+                    // Задержанный ответ несёт последнее числовое значение сценария перед шагом
+                    let value = self.script[..=request as usize % self.script.len()]
+                        .iter()
+                        .rev()
+                        .find_map(|step| if let Step::Value(v) = step { Some(*v) } else { None })
+                        .unwrap_or(args.offset);
+                    Reply::Values(vec![value; args.channels])
+                }
+            },
+            wave => {
+                let t = self.started.elapsed().as_secs_f64();
+                Reply::Values((0..args.channels).map(|channel| self.sample(wave, t, channel, request)).collect())
+            }
+        };
+        (reply, delay)
+    }
+
+    fn sample(&self, wave: Wave, t: f64, channel: usize, request: u64) -> f64 {
+        let args = &self.args;
+        // Каналы и приборы сдвинуты по фазе на восьмую часть периода
+        let shift = (self.number + channel) as f64 / 8.0;
+        let phase = (t / args.period + shift).fract();
+        let unit = match wave {
+            Wave::Sine => (phase * std::f64::consts::TAU).sin(),
+            Wave::Ramp => phase * 2.0 - 1.0,
+            Wave::Noise => random_unit(request.wrapping_mul(31).wrapping_add(channel as u64)) * 2.0 - 1.0,
+            Wave::Script => 0.0,
+        };
+        args.offset + args.amplitude * unit
+    }
+}
+
+// Псевдослучайное число в [0, 1): xorshift от номера запроса и времени запуска процесса
+fn random_unit(seed: u64) -> f64 {
+    let start = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let mut x = seed ^ start.rotate_left(17) ^ 0x9E37_79B9_7F4A_7C15;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
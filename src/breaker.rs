@@ -0,0 +1,135 @@
+use std::{
+    fmt,
+    io,
+    time::{Duration, Instant},
+};
+use crate::BreakerSettings;
+
+// Автомат на сервер: после failures ошибок подряд сервер не опрашивается cooldown.
+// По истечении паузы идёт одна пробная попытка: успех замыкает цепь, ошибка размыкает
+// её снова на вдвое большую паузу, но не дольше max_cooldown_secs
+#[derive(Clone, Copy, Default)]
+pub struct Breaker {
+    pub failures:   u32,
+    // Текущая пауза, ноль — цепь ещё не размыкалась
+    pub cooldown:   Duration,
+    pub open_until: Option<Instant>,
+}
+
+impl Breaker {
+    // Разомкнута ли цепь сейчас: опрос пропускается
+    pub fn is_open(&self, now: Instant) -> bool {
+        self.open_until.is_some_and(|until| until > now)
+    }
+
+    // Сколько осталось до пробной попытки
+    pub fn retry_in(&self, now: Instant) -> Option<Duration> {
+        self.open_until.filter(|until| *until > now).map(|until| until - now)
+    }
+
+    // Состояние после опроса
+    pub fn after(self, ok: bool, settings: &BreakerSettings, now: Instant) -> Self {
+        if ok || !settings.enabled {
+            return Self::default();
+        }
+        let failures = self.failures + 1;
+        if failures < settings.failures {
+            return Self { failures, ..self };
+        }
+        let base = Duration::from_secs(settings.cooldown_secs);
+        let max = Duration::from_secs(settings.max_cooldown_secs).max(base);
+        let cooldown = if self.cooldown.is_zero() { base } else { (self.cooldown * 2).min(max) };
+        Self { failures, cooldown, open_until: Some(now + cooldown) }
+    }
+}
+
+// Ответ пропущенного опроса: отсчёт на этом тике пустой, статус сервера не меняется
+#[derive(Debug)]
+pub struct Open;
+
+impl fmt::Display for Open {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Опрос пропущен: сервер не отвечает, ждём паузу")
+    }
+}
+
+impl std::error::Error for Open {}
+
+pub fn open_error() -> io::Error {
+    io::Error::other(Open)
+}
+
+pub fn is_open_error(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<Open>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(failures: u32, cooldown_secs: u64, max_cooldown_secs: u64) -> BreakerSettings {
+        BreakerSettings { enabled: true, failures, cooldown_secs, max_cooldown_secs }
+    }
+
+    // Серия неудачных опросов, каждый — в момент now
+    fn fail(mut breaker: Breaker, times: u32, settings: &BreakerSettings, now: Instant) -> Breaker {
+        for _ in 0..times {
+            breaker = breaker.after(false, settings, now);
+        }
+        breaker
+    }
+
+    #[test]
+    fn opens_after_threshold() {
+        let settings = settings(3, 5, 120);
+        let now = Instant::now();
+        let breaker = fail(Breaker::default(), 2, &settings, now);
+        assert_eq!(breaker.failures, 2);
+        assert!(!breaker.is_open(now));
+
+        let breaker = breaker.after(false, &settings, now);
+        assert!(breaker.is_open(now));
+        assert_eq!(breaker.cooldown, Duration::from_secs(5));
+        assert_eq!(breaker.retry_in(now + Duration::from_secs(2)), Some(Duration::from_secs(3)));
+        // Пауза истекла — пробная попытка
+        assert!(!breaker.is_open(now + Duration::from_secs(5)));
+        assert_eq!(breaker.retry_in(now + Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn failed_probes_double_the_cooldown_up_to_max() {
+        let doubling = settings(1, 5, 30);
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        let mut cooldowns = Vec::new();
+        for _ in 0..5 {
+            breaker = breaker.after(false, &doubling, now);
+            cooldowns.push(breaker.cooldown.as_secs());
+        }
+        assert_eq!(cooldowns, [5, 10, 20, 30, 30]);
+        assert_eq!(breaker.open_until, Some(now + Duration::from_secs(30)));
+
+        // Максимум меньше начальной паузы не укорачивает её
+        let breaker = fail(Breaker::default(), 2, &settings(1, 10, 3), now);
+        assert_eq!(breaker.cooldown, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn success_closes_and_resets() {
+        let settings = settings(2, 5, 120);
+        let now = Instant::now();
+        let breaker = fail(Breaker::default(), 4, &settings, now).after(true, &settings, now);
+        assert_eq!((breaker.failures, breaker.cooldown, breaker.open_until), (0, Duration::ZERO, None));
+        // После сброса снова нужна полная серия ошибок
+        assert!(!fail(breaker, 1, &settings, now).is_open(now));
+    }
+
+    #[test]
+    fn disabled_breaker_never_opens() {
+        let settings = BreakerSettings { enabled: false, ..settings(1, 5, 120) };
+        let now = Instant::now();
+        let breaker = fail(Breaker::default(), 10, &settings, now);
+        assert!(!breaker.is_open(now));
+        assert_eq!(breaker.failures, 0);
+    }
+}
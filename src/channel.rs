@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use crate::{parse_value, ServerInfo};
+
+// Канал — одно значение из ответа сервера. Ответ делится на поля по пробелам,
+// index выбирает поле, затем применяется линейное преобразование scale * x + offset
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChannelDef {
+    // Пустое имя — канал подписывается именем сервера
+    #[serde(default)]
+    pub name:    String,
+    #[serde(default)]
+    pub index:   usize,
+    #[serde(default = "default_scale")]
+    pub scale:   f64,
+    #[serde(default)]
+    pub offset:  f64,
+    // Скрытый канал продолжает опрашиваться и писаться в результаты
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    // Пороги тревоги (см. alert.rs), None — граница не проверяется
+    #[serde(default)]
+    pub min:     Option<f64>,
+    #[serde(default)]
+    pub max:     Option<f64>,
+    // Единица для подписи оси Y, например «K» или «кг/с»
+    #[serde(default)]
+    pub unit:    String,
+    // Канал рисуется по правой оси Y со своими пределами
+    #[serde(default)]
+    pub right_axis: bool,
+}
+
+impl Default for ChannelDef {
+    fn default() -> Self {
+        Self {
+            name:    String::new(),
+            index:   0,
+            scale:   1.0,
+            offset:  0.0,
+            visible: true,
+            min:     None,
+            max:     None,
+            unit:    String::new(),
+            right_axis: false,
+        }
+    }
+}
+
+pub fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+pub fn default_channels() -> Vec<ChannelDef> {
+    vec![ChannelDef::default()]
+}
+
+// Значения каналов сервера в инженерных единицах: сначала преобразование канала,
+// затем калибровка сервера. Отсутствующее или нечисловое поле даёт пропуск
+pub fn parse(response: &str, server: &ServerInfo) -> Vec<Option<f64>> {
+    let fields: Vec<&str> = response.split_whitespace().collect();
+    server.channels
+        .iter()
+        .map(|def| {
+            fields
+                .get(def.index)
+                .and_then(|field| parse_value(field))
+                .map(|value| (value * def.scale + def.offset) * server.scale + server.offset)
+        })
+        .collect()
+}
+
+// Подпись канала в легенде и заголовках экспорта
+pub fn label(server: &ServerInfo, def: &ChannelDef) -> String {
+    if def.name.is_empty() {
+        server.name.clone()
+    } else {
+        format!("{}.{}", server.name, def.name)
+    }
+}
+
+// Колонка ComputationResults::flow. Канал адресуется устойчивым id сервера и номером канала,
+// а не местом в списке: добавление и удаление серверов посреди сессии не сдвигает уже
+// собранные значения. Колонки удалённого сервера остаются до конца сессии
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    pub server:  u32,
+    pub channel: usize,
+    // Подпись на момент появления колонки, нужна, когда сервера уже нет
+    pub label:   String,
+}
+
+// Раскладка новой сессии: по серверам, внутри сервера — по списку каналов
+pub fn layout(servers: &[ServerInfo]) -> Vec<Column> {
+    let mut columns = Vec::new();
+    extend_layout(&mut columns, servers);
+    columns
+}
+
+// Дописывает в конец колонки каналов, которых ещё нет. Существующие колонки не трогаются
+pub fn extend_layout(columns: &mut Vec<Column>, servers: &[ServerInfo]) {
+    for server in servers {
+        for (channel, def) in server.channels.iter().enumerate() {
+            if !columns.iter().any(|c| c.server == server.id && c.channel == channel) {
+                columns.push(Column { server: server.id, channel, label: label(server, def) });
+            }
+        }
+    }
+}
+
+// Канал колонки в текущем списке, None — сервер удалён или канал убран
+pub fn resolve<'a>(servers: &'a [ServerInfo], column: &Column) -> Option<(&'a ServerInfo, &'a ChannelDef)> {
+    let server = servers.iter().find(|s| s.id == column.server)?;
+    server.channels.get(column.channel).map(|def| (server, def))
+}
+
+// Каналы всех колонок по порядку flow
+pub fn resolved<'a>(
+    columns: &'a [Column],
+    servers: &'a [ServerInfo],
+) -> impl Iterator<Item = Option<(&'a ServerInfo, &'a ChannelDef)>> + 'a {
+    columns.iter().map(move |column| resolve(servers, column))
+}
+
+// Текущая подпись канала, у удалённого — сохранённая в колонке
+pub fn column_labels(columns: &[Column], servers: &[ServerInfo]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| resolve(servers, column).map_or_else(|| column.label.clone(), |(server, def)| label(server, def)))
+        .collect()
+}
+
+// Выдаёт id серверам без него (старые конфигурации) и повторам. 0 не используется
+pub fn assign_ids(servers: &mut [ServerInfo]) {
+    let mut next = servers.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+    let mut seen = std::collections::HashSet::new();
+    for server in servers {
+        if server.id == 0 || !seen.insert(server.id) {
+            server.id = next;
+            seen.insert(next);
+            next += 1;
+        }
+    }
+}
+
+// Id нового сервера. Учитываются и колонки: у удалённого сервера они остаются до конца сессии,
+// и сервер с его id продолжил бы чужую историю
+pub fn next_id(servers: &[ServerInfo], columns: &[Column]) -> u32 {
+    let servers = servers.iter().map(|s| s.id);
+    let columns = columns.iter().map(|c| c.server);
+    servers.chain(columns).max().unwrap_or(0) + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(id: u32, name: &str) -> ServerInfo {
+        let mut server = ServerInfo::new(name, "127.0.0.1:9000");
+        server.id = id;
+        server
+    }
+
+    #[test]
+    fn assign_ids_fills_missing_and_duplicates() {
+        let mut servers = vec![server(0, "a"), server(3, "b"), server(3, "c"), server(0, "d")];
+        assign_ids(&mut servers);
+        let ids: Vec<u32> = servers.iter().map(|s| s.id).collect();
+        assert_eq!(ids, [4, 3, 5, 6]);
+    }
+
+    #[test]
+    fn next_id_skips_ids_still_held_by_columns() {
+        let mut servers = vec![server(1, "a"), server(2, "b")];
+        let columns = layout(&servers);
+        servers.pop();
+        // Раньше max(id) + 1 по списку давал 2 — id удалённого сервера с его колонками
+        assert_eq!(next_id(&servers, &columns), 3);
+        assert_eq!(next_id(&[], &[]), 1);
+    }
+
+    // Удалить последний сервер, добавить новый: новый не должен продолжить колонку удалённого
+    #[test]
+    fn new_server_does_not_inherit_removed_history() {
+        let mut servers = vec![server(1, "a"), server(2, "b")];
+        let mut columns = layout(&servers);
+        servers.pop();
+        let id = next_id(&servers, &columns);
+        servers.push(server(id, "c"));
+        extend_layout(&mut columns, &servers);
+
+        assert_eq!(columns.len(), 3);
+        assert_eq!(column_labels(&columns, &servers), ["a", "b", "c"]);
+        assert!(resolve(&servers, &columns[1]).is_none());
+        assert_eq!(resolve(&servers, &columns[2]).map(|(s, _)| s.name.as_str()), Some("c"));
+    }
+
+    #[test]
+    fn extend_layout_keeps_existing_columns() {
+        let mut servers = vec![server(1, "a")];
+        let mut columns = layout(&servers);
+        servers.insert(0, server(2, "b"));
+        servers[1].channels.push(ChannelDef { name: "t".to_string(), index: 1, ..Default::default() });
+        extend_layout(&mut columns, &servers);
+        let keys: Vec<(u32, usize)> = columns.iter().map(|c| (c.server, c.channel)).collect();
+        assert_eq!(keys, [(1, 0), (2, 0), (1, 1)]);
+        assert_eq!(column_labels(&columns, &servers), ["a", "b", "a.t"]);
+    }
+
+    #[test]
+    fn resolve_missing_channel() {
+        let servers = vec![server(1, "a")];
+        let column = Column { server: 1, channel: 1, label: "a.old".to_string() };
+        assert!(resolve(&servers, &column).is_none());
+        assert_eq!(column_labels(&[column], &servers), ["a.old"]);
+    }
+
+    #[test]
+    fn parse_applies_channel_then_server_calibration() {
+        let mut server = server(1, "a");
+        server.channels = vec![
+            ChannelDef { index: 1, scale: 2.0, offset: 1.0, ..Default::default() },
+            ChannelDef { index: 5, ..Default::default() },
+        ];
+        server.scale = 10.0;
+        server.offset = -3.0;
+        assert_eq!(parse("7 4.5", &server), [Some((4.5 * 2.0 + 1.0) * 10.0 - 3.0), None]);
+    }
+}
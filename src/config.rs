@@ -0,0 +1,69 @@
+use std::{
+    fs,
+    io,
+    path::PathBuf,
+};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use crate::{
+    AutoStopSettings, AutosaveSettings, BreakerSettings, FeedSettings, LiveLogSettings, LiveTailSettings, MetricsSettings, RetryPolicy, ServerInfo,
+    ShortcutSettings,
+};
+
+const CONFIG_FILE: &str = "config.json";
+
+// Всё, что переживает перезапуск приложения
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub servers:   Vec<ServerInfo>,
+    pub retry:     RetryPolicy,
+    pub live_tail: LiveTailSettings,
+    pub metrics:   MetricsSettings,
+    pub feed:      FeedSettings,
+    pub autosave:  AutosaveSettings,
+    pub live_log:  LiveLogSettings,
+    pub auto_stop: AutoStopSettings,
+    pub breaker:   BreakerSettings,
+    // Сколько серверов опрашивается одновременно, 0 — без ограничения
+    pub max_connections: usize,
+    // Каталог последнего экспорта, с него открывается диалог сохранения
+    pub export_dir: Option<PathBuf>,
+    // Последний загруженный профиль стенда (см. profile.rs), только для подписи в панели
+    pub profile:    Option<String>,
+    pub shortcuts:  ShortcutSettings,
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "enlil").map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+// Отсутствующий или испорченный файл не должен ронять приложение: пишем ошибку и возвращаем None
+pub fn load() -> Option<Config> {
+    let path = config_path()?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::error!(path = %path.display(), error = %e, "config read failed");
+            return None;
+        }
+    };
+
+    serde_json::from_str(&text)
+        .map_err(|e| tracing::error!(path = %path.display(), error = %e, "config parse failed"))
+        .ok()
+}
+
+pub fn save(config: &Config) -> io::Result<()> {
+    let path = config_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(config)?)
+}
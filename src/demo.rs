@@ -0,0 +1,66 @@
+use std::{
+    io::{self, ErrorKind},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use crate::{fetcher::Fetcher, ServerInfo};
+
+// Доля опросов, которые в демо-режиме «теряются» по таймауту
+const DROPOUT_RATE: f64 = 0.02;
+// Шум относительно амплитуды синуса
+const NOISE: f64 = 0.05;
+
+// Демо-режим: вместо опроса приборов — синусы с шумом и редкими пропусками.
+// Ответ собирается в том же текстовом виде, что у прибора, поэтому каналы,
+// калибровка, пороги и экспорт работают как с настоящими серверами
+pub struct Demo {
+    started: Instant,
+    seed:    AtomicU64,
+}
+
+impl Demo {
+    pub fn new() -> Self {
+        Self { started: Instant::now(), seed: AtomicU64::new(0x2545_F491_4F6C_DD1D) }
+    }
+
+    // xorshift, общий для всех серверов; точная воспроизводимость здесь не нужна
+    fn random(&self) -> f64 {
+        let mut x = self.seed.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.seed.store(x, Ordering::Relaxed);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn response(&self, server: &ServerInfo) -> io::Result<String> {
+        if self.random() < DROPOUT_RATE {
+            return Err(io::Error::new(ErrorKind::TimedOut, "Демо: имитация пропуска"));
+        }
+        // Период и фаза зависят от имени, чтобы линии разных серверов различались
+        let hash = server.name.bytes().fold(17u64, |h, b| h.wrapping_mul(31).wrapping_add(b as u64));
+        let period = 5.0 + (hash % 11) as f64;
+        let t = self.started.elapsed().as_secs_f64();
+        let fields = server.channels.iter().map(|def| def.index + 1).max().unwrap_or(1);
+        let values: Vec<String> = (0..fields)
+            .map(|field| {
+                let phase = (hash % 360) as f64 / 360.0 + field as f64 / 4.0;
+                let amplitude = 10.0 * (field + 1) as f64;
+                let wave = (std::f64::consts::TAU * (t / period + phase)).sin();
+                let noise = (self.random() * 2.0 - 1.0) * NOISE;
+                format!("{:.4}", amplitude * (wave + noise) + 50.0)
+            })
+            .collect();
+        Ok(values.join(" "))
+    }
+}
+
+impl Fetcher for Demo {
+    async fn fetch(&self, server: &ServerInfo, _timeout: Duration) -> io::Result<String> {
+        // Значение производного канала по-прежнему считает evaluate_derived
+        if server.source.is_derived() {
+            return Ok(String::new());
+        }
+        self.response(server)
+    }
+}
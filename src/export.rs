@@ -0,0 +1,594 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+use crate::{
+    alert::AlertEvent,
+    availability::ServerAvailability,
+    channel::{self, Column},
+    fft::Spectrum,
+    session::{Metadata, Session},
+    ComputationResults,
+    Marker,
+    ServerInfo,
+};
+
+// Заголовки колонок строятся по каналам серверов на момент экспорта
+fn header_row(labels: &[String]) -> Vec<String> {
+    std::iter::once("time".to_string())
+        .chain(labels.iter().cloned())
+        .chain(["sampled".to_string(), "channels".to_string()])
+        .collect()
+}
+
+// У сессий до появления качества ячейки остаются пустыми
+fn quality_header(labels: &[String]) -> Vec<String> {
+    std::iter::once("time".to_string()).chain(labels.iter().cloned()).collect()
+}
+
+// Время в файлах — секунды от начала сбора с дробной частью до миллисекунд
+pub fn seconds(millis: u64) -> f64 {
+    millis as f64 / 1000.0
+}
+
+const CALIBRATION_HEADER: [&str; 8] = [
+    "channel", "server", "address", "field", "channel_scale", "channel_offset", "server_scale", "server_offset",
+];
+
+// Калибровка, с которой записаны значения: по строке на канал в порядке колонок данных.
+// У колонки удалённого сервера остаётся только подпись
+fn calibration_rows(columns: &[Column], servers: &[ServerInfo]) -> Vec<[String; 8]> {
+    columns
+        .iter()
+        .map(|column| match channel::resolve(servers, column) {
+            Some((server, def)) => [
+                channel::label(server, def),
+                server.name.clone(),
+                server.address.clone(),
+                def.index.to_string(),
+                def.scale.to_string(),
+                def.offset.to_string(),
+                server.scale.to_string(),
+                server.offset.to_string(),
+            ],
+            None => {
+                let mut row: [String; 8] = Default::default();
+                row[0] = column.label.clone();
+                row
+            }
+        })
+        .collect()
+}
+
+// Метаданные — пары «ключ, значение», затем таблица серверов
+fn metadata_rows(metadata: &Metadata, start_time: Option<u64>, servers: &[ServerInfo]) -> Vec<Vec<String>> {
+    let start = start_time
+        .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64))
+        .map(|time| time.with_timezone(&chrono::Local).to_rfc3339())
+        .unwrap_or_default();
+    let mut rows = vec![
+        vec!["app_version".to_string(), metadata.app_version.clone()],
+        vec!["start_time".to_string(), start],
+        vec!["poll_interval_ms".to_string(), metadata.poll_interval_ms.to_string()],
+        vec!["notes".to_string(), metadata.notes.clone()],
+        vec!["demo".to_string(), metadata.demo.to_string()],
+        Vec::new(),
+        ["server", "address", "source", "scale", "offset"].map(String::from).to_vec(),
+    ];
+    rows.extend(servers.iter().map(|server| vec![
+        server.name.clone(),
+        server.address.clone(),
+        server.source.label().to_string(),
+        server.scale.to_string(),
+        server.offset.to_string(),
+    ]));
+    rows
+}
+
+const EVENT_HEADER: [&str; 6] = ["time", "channel", "value", "bound", "limit", "resolved"];
+
+// Пустое resolved — к моменту экспорта канал так и не вернулся в диапазон
+fn event_row(event: &AlertEvent) -> [String; 6] {
+    [
+        seconds(event.timestamp).to_string(),
+        event.channel.clone(),
+        event.value.to_string(),
+        event.bound.code().to_string(),
+        event.limit.to_string(),
+        event.resolved.map(|t| seconds(t).to_string()).unwrap_or_default(),
+    ]
+}
+
+// Первая колонка — время отметки, названа marker, чтобы секцию CSV нельзя было спутать с событиями
+const MARKER_HEADER: [&str; 2] = ["marker", "label"];
+
+const AVAILABILITY_HEADER: [&str; 5] = ["server", "address", "outages", "downtime", "longest"];
+const OUTAGE_HEADER: [&str; 4] = ["server", "start", "end", "duration"];
+
+// Сводка по серверам, пустая строка, затем все интервалы простоя. Время — секунды
+fn availability_rows(report: &[ServerAvailability]) -> Vec<Vec<String>> {
+    let mut rows = vec![AVAILABILITY_HEADER.map(String::from).to_vec()];
+    rows.extend(report.iter().map(|server| vec![
+        server.server.clone(),
+        server.address.clone(),
+        server.outages.len().to_string(),
+        seconds(server.downtime).to_string(),
+        seconds(server.longest).to_string(),
+    ]));
+    rows.push(Vec::new());
+    rows.push(OUTAGE_HEADER.map(String::from).to_vec());
+    for server in report {
+        rows.extend(server.outages.iter().map(|outage| vec![
+            server.server.clone(),
+            seconds(outage.start).to_string(),
+            seconds(outage.end).to_string(),
+            seconds(outage.duration()).to_string(),
+        ]));
+    }
+    rows
+}
+
+// Excel =====================================================================
+
+// Как часто save_to_excel сообщает о прогрессе
+const PROGRESS_STEP: usize = 1000;
+
+// Жирный закреплённый заголовок в первой строке, ширина колонок — по заголовку
+fn write_header(sheet: &mut umya_spreadsheet::Worksheet, titles: &[String]) {
+    for (col, title) in titles.iter().enumerate() {
+        let col = col as u32 + 1;
+        sheet.get_cell_mut((col, 1)).set_value(title.clone());
+        sheet.get_style_mut((col, 1)).get_font_mut().set_bold(true);
+        let width = (title.chars().count() + 2).max(10) as f64;
+        sheet.get_column_dimension_by_number_mut(&col).set_width(width);
+    }
+
+    let mut pane = umya_spreadsheet::Pane::default();
+    pane.set_vertical_split(1.0)
+        .set_active_pane(umya_spreadsheet::PaneValues::BottomLeft)
+        .set_state(umya_spreadsheet::PaneStateValues::Frozen);
+    let views = sheet.get_sheet_views_mut().get_sheet_view_list_mut();
+    if views.is_empty() {
+        views.push(umya_spreadsheet::SheetView::default());
+    }
+    for view in views.iter_mut() {
+        view.set_pane(pane.clone());
+    }
+}
+
+// Ограничения Excel на имя листа
+const SHEET_NAME_LIMIT: usize = 31;
+const SHEET_NAME_INVALID: [char; 7] = [':', '\\', '/', '?', '*', '[', ']'];
+// Общий лист всех каналов: Data в обычном режиме, Summary рядом с листами серверов
+const DATA_SHEETS: [&str; 2] = ["Data", "Summary"];
+const SERVICE_SHEETS: [&str; 6] = ["Quality", "Availability", "Events", "Markers", "Calibration", "Metadata"];
+
+// Имена листов серверов: недопустимые символы заменяются, длина обрезается,
+// повторы (без учёта регистра, как в Excel) получают суффикс « (2)», « (3)» по порядку
+fn sheet_names(servers: &[ServerInfo]) -> Vec<String> {
+    let mut used: Vec<String> = DATA_SHEETS.iter().chain(&SERVICE_SHEETS).map(|s| s.to_lowercase()).collect();
+    servers
+        .iter()
+        .map(|server| {
+            let cleaned: String = server.name
+                .chars()
+                .map(|c| if SHEET_NAME_INVALID.contains(&c) || c.is_control() { '_' } else { c })
+                .collect();
+            let base = match cleaned.trim().trim_matches('\'') {
+                "" => "server",
+                base => base,
+            };
+            (1..)
+                .map(|n| {
+                    let suffix = if n == 1 { String::new() } else { format!(" ({})", n) };
+                    let room = SHEET_NAME_LIMIT - suffix.chars().count();
+                    base.chars().take(room).collect::<String>().trim_end().to_string() + &suffix
+                })
+                .find(|name| !used.contains(&name.to_lowercase()))
+                .inspect(|name| used.push(name.to_lowercase()))
+                .expect("бесконечный перебор суффиксов")
+        })
+        .collect()
+}
+
+fn cancelled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "Экспорт отменён")
+}
+
+// per_server — дополнительно по листу на сервер (время и его каналы), общий лист тогда зовётся Summary.
+// progress получает (записано строк, всего строк); false — отмена, файл не создаётся
+pub fn save_to_excel(
+    session:    &Session,
+    alerts:     &[AlertEvent],
+    path:       &Path,
+    per_server: bool,
+    mut progress: impl FnMut(usize, usize) -> bool,
+) -> io::Result<()> {
+    let (results, servers) = (&session.results, &session.servers);
+    // Общий лист, лист качества и листы серверов
+    let passes = if per_server { 2 + servers.len() } else { 2 };
+    let total = results.len() * passes;
+    let mut book = umya_spreadsheet::new_file_empty_worksheet();
+    let sheet = book.new_sheet(DATA_SHEETS[per_server as usize]).map_err(io::Error::other)?;
+    // Подписи и порядок колонок — по раскладке сессии на момент экспорта
+    let labels = channel::column_labels(&session.columns, servers);
+
+    write_header(sheet, &header_row(&labels));
+
+    for (row, result) in results.iter().enumerate() {
+        if row % PROGRESS_STEP == 0 && !progress(row, total) {
+            return Err(cancelled());
+        }
+        let row = row as u32 + 2;
+        sheet.get_cell_mut((1, row)).set_value_number(seconds(result.timestamp));
+
+        // Пропуск отсчёта или канал, добавленный посреди сбора, — ячейка остаётся пустой
+        for (i, value) in result.flow.iter().take(labels.len()).enumerate() {
+            if let Some(value) = value {
+                sheet.get_cell_mut((i as u32 + 2, row)).set_value_number(*value);
+            }
+        }
+
+        let col = labels.len() as u32 + 2;
+        sheet.get_cell_mut((col, row)).set_value_number(result.sampled as f64);
+        sheet.get_cell_mut((col + 1, row)).set_value_number(result.channels as f64);
+    }
+
+    // Качество каждой ячейки Data кодом SampleQuality::code, та же форма листа без sampled/channels
+    let quality = book.new_sheet("Quality").map_err(io::Error::other)?;
+    write_header(quality, &quality_header(&labels));
+    for (row, result) in results.iter().enumerate() {
+        if row % PROGRESS_STEP == 0 && !progress(results.len() + row, total) {
+            return Err(cancelled());
+        }
+        let row = row as u32 + 2;
+        quality.get_cell_mut((1, row)).set_value_number(seconds(result.timestamp));
+        for (i, flag) in result.quality.iter().take(labels.len()).enumerate() {
+            quality.get_cell_mut((i as u32 + 2, row)).set_value(flag.code());
+        }
+    }
+
+    if per_server {
+        for (pass, (server, name)) in servers.iter().zip(sheet_names(servers)).enumerate() {
+            let sheet = book.new_sheet(name).map_err(io::Error::other)?;
+            let titles: Vec<String> = std::iter::once("time".to_string())
+                .chain(server.channels.iter().map(|def| channel::label(server, def)))
+                .collect();
+            write_header(sheet, &titles);
+            // Колонки flow этого сервера по номеру канала
+            let indices: Vec<Option<usize>> = (0..server.channels.len())
+                .map(|channel| session.columns.iter().position(|c| c.server == server.id && c.channel == channel))
+                .collect();
+
+            let done = results.len() * (pass + 2);
+            for (row, result) in results.iter().enumerate() {
+                if row % PROGRESS_STEP == 0 && !progress(done + row, total) {
+                    return Err(cancelled());
+                }
+                let row = row as u32 + 2;
+                sheet.get_cell_mut((1, row)).set_value_number(seconds(result.timestamp));
+                for (i, index) in indices.iter().enumerate() {
+                    if let Some(value) = index.and_then(|index| result.flow.get(index).copied().flatten()) {
+                        sheet.get_cell_mut((i as u32 + 2, row)).set_value_number(value);
+                    }
+                }
+            }
+        }
+    }
+
+    // Две таблицы на листе, заголовки обеих жирные
+    let availability = book.new_sheet("Availability").map_err(io::Error::other)?;
+    let titles = [0, session.availability.len() + 2];
+    for (row, cells) in availability_rows(&session.availability).into_iter().enumerate() {
+        let title = titles.contains(&row);
+        for (col, cell) in cells.into_iter().enumerate() {
+            let coordinate = (col as u32 + 1, row as u32 + 1);
+            availability.get_cell_mut(coordinate).set_value(cell);
+            if title {
+                availability.get_style_mut(coordinate).get_font_mut().set_bold(true);
+            }
+        }
+    }
+    availability.get_column_dimension_by_number_mut(&1).set_width(18.0);
+    availability.get_column_dimension_by_number_mut(&2).set_width(22.0);
+
+    // События порогов — отдельным листом, чтобы лист Data оставался прямоугольной таблицей
+    let events = book.new_sheet("Events").map_err(io::Error::other)?;
+    write_header(events, &EVENT_HEADER.map(String::from));
+    for (row, event) in alerts.iter().enumerate() {
+        let row = row as u32 + 2;
+        events.get_cell_mut((1, row)).set_value_number(seconds(event.timestamp));
+        events.get_cell_mut((2, row)).set_value(event.channel.clone());
+        events.get_cell_mut((3, row)).set_value_number(event.value);
+        events.get_cell_mut((4, row)).set_value(event.bound.code());
+        events.get_cell_mut((5, row)).set_value_number(event.limit);
+        if let Some(resolved) = event.resolved {
+            events.get_cell_mut((6, row)).set_value_number(seconds(resolved));
+        }
+    }
+
+    let markers = book.new_sheet("Markers").map_err(io::Error::other)?;
+    write_header(markers, &["time", "label"].map(String::from));
+    for (row, marker) in session.markers.iter().enumerate() {
+        let row = row as u32 + 2;
+        markers.get_cell_mut((1, row)).set_value_number(seconds(marker.timestamp));
+        markers.get_cell_mut((2, row)).set_value(marker.label.clone());
+    }
+    markers.get_column_dimension_by_number_mut(&2).set_width(40.0);
+
+    let calibration = book.new_sheet("Calibration").map_err(io::Error::other)?;
+    write_header(calibration, &CALIBRATION_HEADER.map(String::from));
+    for (row, cells) in calibration_rows(&session.columns, servers).into_iter().enumerate() {
+        for (col, cell) in cells.into_iter().enumerate() {
+            calibration.get_cell_mut((col as u32 + 1, row as u32 + 2)).set_value(cell);
+        }
+    }
+
+    let info = book.new_sheet("Metadata").map_err(io::Error::other)?;
+    for (row, cells) in metadata_rows(&session.metadata, session.start_time, servers).into_iter().enumerate() {
+        for (col, cell) in cells.into_iter().enumerate() {
+            info.get_cell_mut((col as u32 + 1, row as u32 + 1)).set_value(cell);
+        }
+    }
+    info.get_column_dimension_by_number_mut(&1).set_width(18.0);
+    info.get_column_dimension_by_number_mut(&2).set_width(40.0);
+
+    progress(total, total);
+    umya_spreadsheet::writer::xlsx::write(&book, path).map_err(|e| io::Error::other(e.to_string()))
+}
+
+// Обратное чтение файла save_to_excel. Адреса и каналы восстанавливаются не полностью:
+// каждая колонка становится сервером с одним каналом и пустым адресом. Из метаданных читаются заметки и начало сбора
+pub fn load_excel(path: &Path) -> io::Result<Session> {
+    let book = umya_spreadsheet::reader::xlsx::read(path).map_err(|e| io::Error::other(e.to_string()))?;
+    let sheet = DATA_SHEETS
+        .iter()
+        .find_map(|name| book.get_sheet_by_name(name))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Нет листа Data"))?;
+
+    let (columns, rows) = sheet.get_highest_column_and_row();
+    // time, серверы..., sampled, channels
+    let server_count = columns.checked_sub(3)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Неверный заголовок листа Data"))?;
+    let mut servers: Vec<ServerInfo> = (0..server_count)
+        .map(|i| ServerInfo::new(&sheet.get_value((i + 2, 1)), ""))
+        .collect();
+    channel::assign_ids(&mut servers);
+
+    let number = |col: u32, row: u32| sheet.get_value((col, row)).trim().parse::<f64>().ok();
+    let mut results = Vec::new();
+    for row in 2..=rows {
+        let Some(timestamp) = number(1, row) else { continue };
+        results.push(ComputationResults {
+            // Секунды, у старых файлов целые
+            timestamp:   (timestamp * 1000.0).round() as u64,
+            // Пустая ячейка — пропуск отсчёта
+            flow:        (0..server_count).map(|i| number(i + 2, row)).collect(),
+            sampled:     number(server_count + 2, row).unwrap_or(0.0) as usize,
+            channels:    number(server_count + 3, row).unwrap_or(0.0) as usize,
+            after_pause: false,
+            quality:     Vec::new(),
+        });
+    }
+
+    // Пары «ключ, значение» в начале листа Metadata, до пустой строки
+    let mut metadata = Metadata::default();
+    // Начало сбора нужно для времени по часам на графике открытой сессии
+    let mut start_time = None;
+    if let Some(info) = book.get_sheet_by_name("Metadata") {
+        for row in 1.. {
+            let key = info.get_value((1, row));
+            let value = info.get_value((2, row));
+            match key.as_str() {
+                "" => break,
+                "app_version" => metadata.app_version = value,
+                "start_time" => start_time = chrono::DateTime::parse_from_rfc3339(&value).ok().map(|t| t.timestamp_millis() as u64),
+                "poll_interval_ms" => metadata.poll_interval_ms = value.parse().unwrap_or_default(),
+                "notes" => metadata.notes = value,
+                "demo" => metadata.demo = value == "true",
+                _ => {}
+            }
+        }
+    }
+
+    Ok(Session {
+        format_version: crate::session::FORMAT_VERSION,
+        start_time,
+        columns:        channel::layout(&servers),
+        servers,
+        results,
+        metadata,
+        availability:   Vec::new(),
+        markers:        Vec::new(),
+    })
+}
+
+// CSV =======================================================================
+
+// После данных, каждая через пустую строку и со своим заголовком, идут секции
+// качества отсчётов, калибровки, событий порогов и отметок оператора
+pub fn export_csv(
+    results: &[ComputationResults],
+    columns: &[Column],
+    servers: &[ServerInfo],
+    alerts:  &[AlertEvent],
+    markers: &[Marker],
+    path:    &Path,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    let labels = channel::column_labels(columns, servers);
+    let header: Vec<String> = header_row(&labels).iter().map(|title| csv_escape(title)).collect();
+    writeln!(out, "{}", header.join(","))?;
+
+    for result in results {
+        let mut row = vec![seconds(result.timestamp).to_string()];
+        row.extend((0..labels.len()).map(|i| {
+            result.flow.get(i).copied().flatten().map(|v| v.to_string()).unwrap_or_default()
+        }));
+        row.push(result.sampled.to_string());
+        row.push(result.channels.to_string());
+        writeln!(out, "{}", row.join(","))?;
+    }
+
+    // Секция качества: та же сетка, что у данных, в ячейках коды SampleQuality.
+    // Первая колонка называется quality, чтобы секцию нельзя было спутать с заголовком данных
+    writeln!(out)?;
+    let header: Vec<String> = std::iter::once("quality".to_string())
+        .chain(labels.iter().map(|label| csv_escape(label)))
+        .collect();
+    writeln!(out, "{}", header.join(","))?;
+    for result in results {
+        let mut row = vec![seconds(result.timestamp).to_string()];
+        row.extend((0..labels.len()).map(|i| result.quality.get(i).map_or("", |flag| flag.code()).to_string()));
+        writeln!(out, "{}", row.join(","))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "{}", CALIBRATION_HEADER.join(","))?;
+    for cells in calibration_rows(columns, servers) {
+        let row: Vec<String> = cells.iter().map(|cell| csv_escape(cell)).collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+
+    if !alerts.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{}", EVENT_HEADER.join(","))?;
+        for event in alerts {
+            let row: Vec<String> = event_row(event).iter().map(|cell| csv_escape(cell)).collect();
+            writeln!(out, "{}", row.join(","))?;
+        }
+    }
+
+    if !markers.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{}", MARKER_HEADER.join(","))?;
+        for marker in markers {
+            writeln!(out, "{},{}", seconds(marker.timestamp), csv_escape(&marker.label))?;
+        }
+    }
+
+    out.flush()
+}
+
+// Поля с запятыми, кавычками или переводами строк берутся в кавычки, кавычки удваиваются
+pub fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Спектр ====================================================================
+
+pub fn export_spectrum_csv(spectrum: &Spectrum, path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "frequency_hz,amplitude")?;
+    for (f, a) in spectrum.frequencies.iter().zip(&spectrum.amplitudes) {
+        writeln!(out, "{},{}", f, a)?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alert::Bound, ServerData};
+
+    // Два сервера, три отсчёта, у m2 пропуск во втором
+    fn small_session() -> ServerData {
+        let mut servers = vec![ServerInfo::new("m1", "127.0.0.1:9001"), ServerInfo::new("m2", "127.0.0.1:9002")];
+        channel::assign_ids(&mut servers);
+        let result = |timestamp, flow: [Option<f64>; 2]| ComputationResults {
+            timestamp,
+            sampled:  flow.iter().flatten().count(),
+            channels: 2,
+            flow:     flow.to_vec(),
+            ..Default::default()
+        };
+        ServerData {
+            columns:          channel::layout(&servers),
+            servers,
+            start_time:       Some(1_715_689_800_000),
+            computed_results: vec![
+                result(0, [Some(1.5), Some(20.0)]),
+                result(1000, [Some(2.5), None]),
+                result(2250, [Some(3.5), Some(22.0)]),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("enlil-{}-{}.xlsx", name, std::process::id()))
+    }
+
+    #[test]
+    fn excel_cells_are_typed() {
+        let data = small_session();
+        let alert = AlertEvent {
+            timestamp: 1000, channel: "m1".to_string(), value: 2.5, bound: Bound::Above, limit: 2.0, resolved: None, acknowledged: false,
+        };
+        let path = temp_path("typed");
+        save_to_excel(&Session::from_data(&data), &[alert], &path, false, |_, _| true).unwrap();
+        let book = umya_spreadsheet::reader::xlsx::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let sheet = book.get_sheet_by_name("Data").unwrap();
+        let data_type = |col: u32, row: u32| sheet.get_cell((col, row)).map_or("", |cell| cell.get_data_type()).to_string();
+        // Заголовок — жирный текст в закреплённой строке
+        for col in 1..=5 {
+            assert_eq!(data_type(col, 1), "s");
+            assert!(*sheet.get_style((col, 1)).get_font().unwrap().get_bold());
+        }
+        assert_eq!(sheet.get_value((1, 1)), "time");
+        let pane = sheet.get_sheets_views().get_sheet_view_list()[0].get_pane().unwrap();
+        assert_eq!(*pane.get_vertical_split(), 1.0);
+        assert!(matches!(pane.get_state(), umya_spreadsheet::PaneStateValues::Frozen));
+        assert!(*sheet.get_column_dimension_by_number(&1).unwrap().get_width() >= 10.0);
+
+        // Время, значения и полнота — числа, пропуск — пустая ячейка
+        for row in 2..=4 {
+            for col in 1..=5 {
+                let expected = if (col, row) == (3, 3) { "" } else { "n" };
+                assert_eq!(data_type(col, row), expected, "cell ({}, {})", col, row);
+            }
+        }
+        assert_eq!(sheet.get_cell((1, 4)).unwrap().get_value_number(), Some(2.25));
+        assert_eq!(sheet.get_cell((2, 3)).unwrap().get_value_number(), Some(2.5));
+
+        let events = book.get_sheet_by_name("Events").unwrap();
+        let types: Vec<_> = (1..=5).map(|col| events.get_cell((col, 2)).unwrap().get_data_type().to_string()).collect();
+        assert_eq!(types, ["n", "s", "n", "s", "n"]);
+        assert!(events.get_cell((6, 2)).is_none_or(|cell| cell.get_value().is_empty()));
+    }
+
+    #[test]
+    fn excel_round_trip_keeps_values_and_gaps() {
+        let data = small_session();
+        let path = temp_path("round-trip");
+        save_to_excel(&Session::from_data(&data), &[], &path, false, |_, _| true).unwrap();
+        let session = load_excel(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let names: Vec<_> = session.servers.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["m1", "m2"]);
+        assert_eq!(session.start_time, data.start_time);
+        let rows: Vec<_> = session.results.iter().map(|r| (r.timestamp, r.flow.clone(), r.sampled)).collect();
+        assert_eq!(rows, [
+            (0, vec![Some(1.5), Some(20.0)], 2),
+            (1000, vec![Some(2.5), None], 1),
+            (2250, vec![Some(3.5), Some(22.0)], 2),
+        ]);
+    }
+
+    #[test]
+    fn cancelled_export_writes_nothing() {
+        let path = temp_path("cancelled");
+        let err = save_to_excel(&Session::from_data(&small_session()), &[], &path, false, |_, _| false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, fmt, io, iter::Peekable, str::Chars};
+
+// Выражение производного канала: числа, имена каналов, + - * /, унарный минус и скобки.
+// Имя канала пишется как в легенде: m1 или m1.a
+#[derive(Debug)]
+pub enum Expr {
+    Number(f64),
+    Channel(String),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug)]
+pub struct ExprError(pub String);
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+fn error<T>(message: impl Into<String>) -> Result<T, ExprError> {
+    Err(ExprError(message.into()))
+}
+
+pub fn is_expr_error(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<ExprError>())
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let literal = take_while(&mut chars, |c| c.is_ascii_digit() || c == '.');
+                match literal.parse() {
+                    Ok(value) => tokens.push(Token::Number(value)),
+                    Err(_) => return error(format!("неверное число {}", literal)),
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let name = take_while(&mut chars, |c| c.is_alphanumeric() || c == '_' || c == '.');
+                tokens.push(Token::Name(name));
+            }
+            '+' | '-' | '*' | '/' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => return error(format!("неожиданный символ '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn take_while(chars: &mut Peekable<Chars>, accept: impl Fn(char) -> bool) -> String {
+    let mut text = String::new();
+    while let Some(&c) = chars.peek().filter(|&&c| accept(c)) {
+        text.push(c);
+        chars.next();
+    }
+    text
+}
+
+pub fn parse(text: &str) -> Result<Expr, ExprError> {
+    let tokens = tokenize(text)?;
+    if tokens.is_empty() {
+        return error("пустое выражение");
+    }
+    let mut parser = Parser { tokens: tokens.into_iter().peekable() };
+    let expr = parser.sum()?;
+    match parser.tokens.next() {
+        None => Ok(expr),
+        Some(_) => error("лишний текст после выражения"),
+    }
+}
+
+// Рекурсивный спуск: сумма из произведений, произведение из унарных
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.product()?;
+        while let Some(op) = self.next_op(&['+', '-']) {
+            left = Expr::Binary(Box::new(left), op, Box::new(self.product()?));
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.next_op(&['*', '/']) {
+            left = Expr::Binary(Box::new(left), op, Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.tokens.next_if_eq(&Token::Op('-')).is_some() {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        match self.tokens.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Name(name)) => Ok(Expr::Channel(name)),
+            Some(Token::Open) => {
+                let inner = self.sum()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => error("ожидалась )"),
+                }
+            }
+            Some(Token::Close) => error("лишняя )"),
+            Some(Token::Op(c)) => error(format!("ожидалось значение перед '{}'", c)),
+            None => error("выражение оборвано"),
+        }
+    }
+
+    fn next_op(&mut self, ops: &[char]) -> Option<Op> {
+        let Some(Token::Op(c)) = self.tokens.peek() else { return None };
+        let op = match c {
+            c if !ops.contains(c) => return None,
+            '+' => Op::Add,
+            '-' => Op::Sub,
+            '*' => Op::Mul,
+            _ => Op::Div,
+        };
+        self.tokens.next();
+        Some(op)
+    }
+}
+
+impl Expr {
+    // values: подпись канала -> значение на этом тике (None — канал не дал отсчёта)
+    pub fn eval(&self, values: &HashMap<String, Option<f64>>) -> Result<f64, ExprError> {
+        let value = match self {
+            Expr::Number(value) => *value,
+            Expr::Channel(name) => match values.get(name) {
+                None => return error(format!("неизвестный канал {}", name)),
+                Some(None) => return error(format!("нет значения {}", name)),
+                Some(Some(value)) => *value,
+            },
+            Expr::Neg(inner) => -inner.eval(values)?,
+            Expr::Binary(left, op, right) => {
+                let (left, right) = (left.eval(values)?, right.eval(values)?);
+                match op {
+                    Op::Add => left + right,
+                    Op::Sub => left - right,
+                    Op::Mul => left * right,
+                    Op::Div => left / right,
+                }
+            }
+        };
+        // Деление на ноль и переполнение дают пропуск, а не inf в результатах
+        if !value.is_finite() {
+            return error("результат не число");
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,168 @@
+//! WebSocket-трансляция отсчётов для внешних панелей: `ws://<адрес>/live`.
+//!
+//! Каждый сохранённый отсчёт уходит всем клиентам текстовым сообщением JSON. Порядок
+//! `flow` совпадает с `names`; `timestamp` — миллисекунды от `start_time` (миллисекунды Unix):
+//!
+//! ```text
+//! {"start_time":1715689800000,"names":["m1","m2"],"timestamp":3000,"flow":[23.45,null],"sampled":1,"channels":2,"after_pause":false,"quality":["good","timeout"]}
+//! ```
+//!
+//! Сразу после подключения клиент получает последние `snapshot` отсчётов в том же формате.
+//! У каждого клиента своя ограниченная очередь: кто не успевает её разбирать, отключается,
+//! а сборщик никогда не ждёт сеть.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use serde::Serialize;
+use tokio::{net::TcpListener, sync::mpsc, task::JoinHandle};
+use crate::{channel::{self, Column}, ComputationResults, FeedSettings, ServerInfo};
+
+// Сколько новых отсчётов клиент может не забрать, прежде чем его отключат
+const CLIENT_QUEUE: usize = 32;
+
+#[derive(Serialize)]
+struct FeedSample<'a> {
+    start_time: u64,
+    names:      &'a [String],
+    #[serde(flatten)]
+    result:     &'a ComputationResults,
+}
+
+#[derive(Default)]
+struct Shared {
+    history: VecDeque<String>,
+    limit:   usize,
+    clients: Vec<mpsc::Sender<String>>,
+}
+
+impl Shared {
+    fn lock(shared: &Mutex<Shared>) -> std::sync::MutexGuard<'_, Shared> {
+        shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct Feed {
+    shared: Arc<Mutex<Shared>>,
+    // Адрес, на котором сейчас слушает HTTP-задача
+    server: Option<(String, JoinHandle<()>)>,
+}
+
+impl Feed {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::default(),
+            server: None,
+        }
+    }
+
+    // Сервер перезапускается только при смене адреса или выключении, история сохраняется
+    pub fn configure(&mut self, settings: &FeedSettings) {
+        {
+            let mut shared = Shared::lock(&self.shared);
+            shared.limit = settings.snapshot;
+            while shared.history.len() > shared.limit {
+                shared.history.pop_front();
+            }
+        }
+
+        let listen = settings.enabled.then_some(settings.listen.as_str());
+        if self.server.as_ref().map(|(addr, _)| addr.as_str()) == listen {
+            return;
+        }
+        if let Some((_, task)) = self.server.take() {
+            task.abort();
+            // Закрытые очереди завершают задачи клиентов
+            Shared::lock(&self.shared).clients.clear();
+        }
+        if let Some(listen) = listen {
+            let task = tokio::spawn(serve(listen.to_string(), self.shared.clone()));
+            self.server = Some((listen.to_string(), task));
+        }
+    }
+
+    pub fn publish(&self, columns: &[Column], servers: &[ServerInfo], start_time: u64, result: &ComputationResults) {
+        let names = channel::column_labels(columns, servers);
+        let sample = FeedSample { start_time, names: &names, result };
+        let message = match serde_json::to_string(&sample) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::error!(error = %e, "feed serialization failed");
+                return;
+            }
+        };
+
+        let mut shared = Shared::lock(&self.shared);
+        if shared.limit > 0 {
+            if shared.history.len() == shared.limit {
+                shared.history.pop_front();
+            }
+            shared.history.push_back(message.clone());
+        }
+        // Переполненная очередь — клиент отстал, закрытая — уже отключился
+        shared.clients.retain(|tx| tx.try_send(message.clone()).is_ok());
+    }
+
+    // Остановка сбора: новая сессия начнётся с пустой истории
+    pub fn clear(&self) {
+        Shared::lock(&self.shared).history.clear();
+    }
+}
+
+async fn serve(listen: String, shared: Arc<Mutex<Shared>>) {
+    let listener = match TcpListener::bind(&listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!(%listen, error = %e, "feed listen failed");
+            return;
+        }
+    };
+    let app = Router::new().route("/live", get(upgrade)).with_state(shared);
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!(%listen, error = %e, "feed server failed");
+    }
+}
+
+async fn upgrade(ws: WebSocketUpgrade, State(shared): State<Arc<Mutex<Shared>>>) -> Response {
+    ws.on_upgrade(move |socket| client(socket, shared))
+}
+
+async fn client(mut socket: WebSocket, shared: Arc<Mutex<Shared>>) {
+    let mut rx = {
+        // История и регистрация под одной блокировкой: между снимком и живыми отсчётами нет пропуска
+        let mut shared = Shared::lock(&shared);
+        let (tx, rx) = mpsc::channel(shared.history.len() + CLIENT_QUEUE);
+        for message in &shared.history {
+            let _ = tx.try_send(message.clone());
+        }
+        shared.clients.push(tx);
+        rx
+    };
+
+    loop {
+        tokio::select! {
+            message = rx.recv() => {
+                let Some(message) = message else { break };
+                if socket.send(Message::Text(message)).await.is_err() {
+                    break;
+                }
+            }
+            // Входящие сообщения не нужны, чтение лишь замечает закрытие со стороны клиента
+            incoming = socket.recv() => {
+                if let Some(Ok(Message::Close(_)) | Err(_)) | None = incoming {
+                    break;
+                }
+            }
+        }
+    }
+}
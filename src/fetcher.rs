@@ -0,0 +1,17 @@
+use std::{future::Future, io, time::Duration};
+use crate::{source::DataSource, ServerInfo};
+
+// Как сборщик получает ответ одного сервера. В приложении это всегда сеть,
+// подмена позволяет прогнать цикл сбора на заготовленных ответах без приборов
+pub trait Fetcher: Send + Sync + 'static {
+    fn fetch(&self, server: &ServerInfo, timeout: Duration) -> impl Future<Output = io::Result<String>> + Send;
+}
+
+// Опрос по протоколу из ServerInfo::source (см. source.rs)
+pub struct Network;
+
+impl Fetcher for Network {
+    fn fetch(&self, server: &ServerInfo, timeout: Duration) -> impl Future<Output = io::Result<String>> + Send {
+        server.fetch(timeout)
+    }
+}
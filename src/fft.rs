@@ -0,0 +1,93 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+
+const MIN_SAMPLES: usize = 8;
+
+pub struct Spectrum {
+    pub frequencies: Vec<f64>,
+    pub amplitudes:  Vec<f64>,
+    // Эффективная частота Найквиста после перевода на равномерную сетку
+    pub nyquist:     f64,
+    pub warnings:    Vec<String>,
+}
+
+// Амплитудный спектр по отсчётам (время, значение). Неравномерные отсчёты сначала
+// линейно интерполируются на равномерную сетку, затем применяется окно Ханна
+pub fn amplitude_spectrum(samples: &[(f64, f64)], remove_dc: bool) -> Result<Spectrum, String> {
+    let n = samples.len();
+    if n < MIN_SAMPLES {
+        return Err(format!("Слишком мало отсчётов: {} (нужно не меньше {})", n, MIN_SAMPLES));
+    }
+
+    let t0 = samples[0].0;
+    let span = samples[n - 1].0 - t0;
+    if span <= 0.0 {
+        return Err("Окно не содержит интервала времени".to_string());
+    }
+    let dt = span / (n - 1) as f64;
+
+    let mut warnings = Vec::new();
+    let max_gap = samples.windows(2).map(|w| w[1].0 - w[0].0).fold(0.0, f64::max);
+    if max_gap > 1.5 * dt {
+        warnings.push(format!(
+            "В окне есть пропуск {:.1} с при среднем шаге {:.2} с — значения интерполированы",
+            max_gap, dt,
+        ));
+    }
+
+    let uniform = resample(samples, t0, dt, n);
+    let mean = if remove_dc { uniform.iter().sum::<f64>() / n as f64 } else { 0.0 };
+    let window = hann(n);
+    let window_sum: f64 = window.iter().sum();
+
+    let mut buffer: Vec<Complex<f64>> = uniform
+        .iter()
+        .zip(&window)
+        .map(|(v, w)| Complex::new((v - mean) * w, 0.0))
+        .collect();
+    FftPlanner::new().plan_fft_forward(n).process(&mut buffer);
+
+    // Односторонний спектр: все бины, кроме нулевого и найквистовского, удваиваются
+    let half = n / 2 + 1;
+    let frequencies = (0..half).map(|k| k as f64 / (n as f64 * dt)).collect();
+    let amplitudes = buffer[..half]
+        .iter()
+        .enumerate()
+        .map(|(k, c)| {
+            let scale = if k == 0 || (n.is_multiple_of(2) && k == n / 2) { 1.0 } else { 2.0 };
+            scale * c.norm() / window_sum
+        })
+        .collect();
+
+    Ok(Spectrum { frequencies, amplitudes, nyquist: 0.5 / dt, warnings })
+}
+
+// Локальные максимумы без постоянной составляющей, по убыванию амплитуды
+pub fn top_peaks(spectrum: &Spectrum, count: usize) -> Vec<(f64, f64)> {
+    let a = &spectrum.amplitudes;
+    let mut peaks: Vec<(f64, f64)> = (1..a.len())
+        .filter(|&k| a[k] >= a[k - 1] && a.get(k + 1).is_none_or(|&next| a[k] > next))
+        .map(|k| (spectrum.frequencies[k], a[k]))
+        .collect();
+    peaks.sort_by(|x, y| y.1.total_cmp(&x.1));
+    peaks.truncate(count);
+    peaks
+}
+
+fn resample(samples: &[(f64, f64)], t0: f64, dt: f64, n: usize) -> Vec<f64> {
+    let mut j = 0;
+    (0..n).map(|i| {
+        let t = t0 + i as f64 * dt;
+        while j + 2 < samples.len() && samples[j + 1].0 < t {
+            j += 1;
+        }
+        let (ta, va) = samples[j];
+        let (tb, vb) = samples[j + 1];
+        if tb > ta { va + (vb - va) * (t - ta) / (tb - ta) } else { va }
+    }).collect()
+}
+
+fn hann(n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (n - 1) as f64).cos())
+        .collect()
+}
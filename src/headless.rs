@@ -0,0 +1,110 @@
+use std::{
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+use crossbeam_channel::Receiver;
+use tokio::{signal, time};
+use crate::{
+    apply_update, autosave, channel, current_timestamp, export, format_clock,
+    run_state::{RunCommand, RunControl},
+    session,
+    CollectorUpdate, ServerData, CSV_PATH, EXCEL_PATH, JSON_PATH,
+};
+
+// Как часто забираются обновления сборщика; сам тик задаёт сборщик
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Сбор без окна: старт сразу, строка состояния на каждый тик сборщика,
+// экспорт в xlsx, CSV и JSON сессии в out_dir по Ctrl+C или по истечении duration
+pub async fn run(
+    mut data: ServerData,
+    updates:  Receiver<CollectorUpdate>,
+    run:      Arc<RunControl>,
+    duration: Option<Duration>,
+    out_dir:  &Path,
+) {
+    if let Err(e) = run.try_transition(RunCommand::Start) {
+        tracing::error!(error = %e, "start failed");
+        return;
+    }
+
+    let deadline = async {
+        match duration {
+            Some(duration) => time::sleep(duration).await,
+            None => std::future::pending().await,
+        }
+    };
+    let ctrl_c = signal::ctrl_c();
+    tokio::pin!(deadline, ctrl_c);
+    let mut poll = time::interval(POLL_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => break,
+            _ = &mut deadline => break,
+            _ = poll.tick() => {}
+        }
+        drain_updates(&mut data, &updates, true);
+    }
+    // Отсчёт, сохранённый сборщиком перед самым выходом, тоже попадает в файлы
+    drain_updates(&mut data, &updates, false);
+
+    export_results(&data, out_dir);
+}
+
+fn drain_updates(data: &mut ServerData, updates: &Receiver<CollectorUpdate>, print: bool) {
+    for update in updates.try_iter() {
+        apply_update(data, &update);
+        if print && matches!(update, CollectorUpdate::Tick { .. }) {
+            println!("{}", status_line(data));
+        }
+    }
+}
+
+// 12:00:01 | отсчётов: 42 | m1=23.450 m2=TIMEOUT
+fn status_line(data: &ServerData) -> String {
+    let clock = format_clock(current_timestamp()).unwrap_or_default();
+    let flow = data.computed_results.last().map_or(&[][..], |result| result.flow.as_slice());
+    let channels: Vec<String> = channel::resolved(&data.columns, &data.servers)
+        .enumerate()
+        .filter_map(|(i, channel)| channel.map(|channel| (i, channel)))
+        .map(|(i, (server, def))| {
+            let value = match (server.failure, flow.get(i).copied().flatten()) {
+                (Some(failure), _) => failure.code().to_string(),
+                (None, Some(value)) => format!("{:.3}", value),
+                (None, None) => "—".to_string(),
+            };
+            format!("{}={}", channel::label(server, def), value)
+        })
+        .collect();
+    format!("{} | отсчётов: {} | {}", clock, data.computed_results.len(), channels.join(" "))
+}
+
+// Те же функции экспорта, что и у кнопок GUI
+fn export_results(data: &ServerData, out_dir: &Path) {
+    if data.computed_results.is_empty() {
+        println!("Нет отсчётов, экспорт пропущен");
+        return;
+    }
+    let session = session::Session::from_data(data);
+    let excel = out_dir.join(EXCEL_PATH);
+    match export::save_to_excel(&session, &data.alerts, &excel, false, |_, _| true) {
+        Ok(()) => println!("Сохранено: {}", excel.display()),
+        Err(e) => tracing::error!(path = %excel.display(), error = %e, "Excel export failed"),
+    }
+    let csv = out_dir.join(CSV_PATH);
+    match export::export_csv(&data.computed_results, &data.columns, &data.servers, &data.alerts, &data.markers, &csv) {
+        Ok(()) => {
+            println!("Сохранено: {}", csv.display());
+            autosave::discard(&data.autosave.dir);
+        }
+        Err(e) => tracing::error!(path = %csv.display(), error = %e, "CSV export failed"),
+    }
+    // Сессия целиком, её можно открыть в окне для просмотра
+    let json = out_dir.join(JSON_PATH);
+    match session::save_json(data, &json) {
+        Ok(()) => println!("Сохранено: {}", json.display()),
+        Err(e) => tracing::error!(path = %json.display(), error = %e, "session save failed"),
+    }
+}
@@ -0,0 +1,81 @@
+// Сколько последних тиков показывает полоска доступности сервера
+pub const DEPTH: u32 = 120;
+
+// История доступности сервера: последние DEPTH тиков битами (1 — корректный отсчёт)
+// и счётчики за сессию. Память не растёт с длиной сессии, доля считается по двум числам
+#[derive(Clone, Copy, Default)]
+pub struct Health {
+    bits:   u128,
+    len:    u32,
+    online: u64,
+    total:  u64,
+}
+
+impl Health {
+    pub fn push(&mut self, online: bool) {
+        self.bits = (self.bits << 1) | online as u128;
+        self.len = (self.len + 1).min(DEPTH);
+        self.total += 1;
+        self.online += online as u64;
+    }
+
+    // Доля тиков с корректным отсчётом за сессию, None — опросов ещё не было
+    pub fn uptime(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.online as f64 / self.total as f64)
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.total
+    }
+
+    // Состояния последних тиков, от старых к новым
+    pub fn recent(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).rev().map(move |i| (self.bits >> i) & 1 == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_history_has_no_uptime() {
+        let health = Health::default();
+        assert_eq!(health.uptime(), None);
+        assert_eq!(health.ticks(), 0);
+        assert_eq!(health.recent().count(), 0);
+    }
+
+    #[test]
+    fn recent_goes_from_old_to_new() {
+        let mut health = Health::default();
+        for online in [true, false, false, true] {
+            health.push(online);
+        }
+        assert_eq!(health.recent().collect::<Vec<_>>(), [true, false, false, true]);
+        assert_eq!(health.uptime(), Some(0.5));
+    }
+
+    // За глубиной полоски старые тики вытесняются, а счётчики сессии продолжают расти
+    #[test]
+    fn window_wraps_but_session_counts_everything() {
+        let mut health = Health::default();
+        // Первые десять тиков — отказы, затем DEPTH + 5 тиков чередуются, начиная с успешного
+        for _ in 0..10 {
+            health.push(false);
+        }
+        for i in 0..DEPTH + 5 {
+            health.push(i % 2 == 0);
+        }
+
+        let recent: Vec<_> = health.recent().collect();
+        assert_eq!(recent.len(), DEPTH as usize);
+        let expected: Vec<_> = (5..DEPTH + 5).map(|i| i % 2 == 0).collect();
+        assert_eq!(recent, expected);
+
+        let total = 10 + DEPTH as u64 + 5;
+        assert_eq!(health.ticks(), total);
+        let online = (DEPTH as u64 + 5).div_ceil(2);
+        assert_eq!(health.uptime(), Some(online as f64 / total as f64));
+    }
+}
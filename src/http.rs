@@ -0,0 +1,66 @@
+use std::{
+    fmt,
+    io,
+    sync::OnceLock,
+};
+
+// Ответ не HTTP 2xx, не JSON или без нужного поля. Заворачивается в io::Error,
+// чтобы HTTP-источник шёл через ту же классификацию сбоев, что и TCP
+#[derive(Debug)]
+pub struct HttpError(pub String);
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HTTP: {}", self.0)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+pub fn is_http_error(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<HttpError>())
+}
+
+fn http_error(message: impl Into<String>) -> io::Error {
+    io::Error::other(HttpError(message.into()))
+}
+
+// Один клиент на всё приложение: пул соединений переживает тики
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+// GET url и выбор поля JSON по указателю (RFC 6901, например /num1). Пустой указатель — весь документ.
+// Число и строка возвращаются как текст, массив — полями через пробел, чтобы работали каналы
+pub async fn fetch(url: &str, pointer: &str) -> io::Result<String> {
+    let response = client().get(url).send().await.map_err(map_reqwest_error)?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(http_error(format!("статус {}", status)));
+    }
+    let json: serde_json::Value = response.json().await.map_err(|e| http_error(format!("не JSON: {}", e)))?;
+    let value = json
+        .pointer(pointer)
+        .ok_or_else(|| http_error(format!("нет поля {}", pointer)))?;
+    Ok(value_text(value))
+}
+
+fn value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => items.iter().map(value_text).collect::<Vec<_>>().join(" "),
+        other => other.to_string(),
+    }
+}
+
+// Таймаут и ошибка подключения сводятся к тем же видам io::Error, что у TCP-источника
+fn map_reqwest_error(err: reqwest::Error) -> io::Error {
+    if err.is_timeout() {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    } else if err.is_connect() {
+        io::Error::new(io::ErrorKind::ConnectionRefused, err)
+    } else {
+        http_error(err.to_string())
+    }
+}
@@ -0,0 +1,46 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+use crate::{channel::{self, Column}, export, ComputationResults, ServerInfo};
+
+// Журнал отсчётов, дописываемый по мере сбора: строка на отсчёт, сброс на диск после каждой.
+// Формат — CSV: time (с от начала сбора), затем каналы по колонкам сессии. Колонки сервера,
+// добавленного посреди сессии, дописываются в конец строки без заголовка
+#[derive(Default)]
+pub struct LiveLog {
+    out: Option<BufWriter<File>>,
+}
+
+impl LiveLog {
+    // Файл открывается на первом отсчёте сессии и перезаписывается, заголовок пишется один раз
+    pub fn append(
+        &mut self,
+        path:    &str,
+        columns: &[Column],
+        servers: &[ServerInfo],
+        result:  &ComputationResults,
+    ) -> io::Result<()> {
+        if self.out.is_none() {
+            let mut out = BufWriter::new(File::create(Path::new(path))?);
+            let header: Vec<String> = std::iter::once("time".to_string())
+                .chain(channel::column_labels(columns, servers).iter().map(|label| export::csv_escape(label)))
+                .collect();
+            writeln!(out, "{}", header.join(","))?;
+            self.out = Some(out);
+        }
+        let Some(out) = self.out.as_mut() else { return Ok(()) };
+
+        let row: Vec<String> = std::iter::once(export::seconds(result.timestamp).to_string())
+            .chain(result.flow.iter().map(|value| value.map(|v| v.to_string()).unwrap_or_default()))
+            .collect();
+        writeln!(out, "{}", row.join(","))?;
+        out.flush()
+    }
+
+    // Конец сессии или ошибка записи: следующая сессия начнёт файл заново
+    pub fn close(&mut self) {
+        self.out = None;
+    }
+}
@@ -0,0 +1,148 @@
+//! Файл «живого хвоста» для внешних программ, которые умеют только перечитывать текстовый файл
+//! (например, старый дисплей в пультовой).
+//!
+//! Файл целиком перезаписывается на каждом тике атомарно: блок пишется во временный файл
+//! рядом с целевым, который затем переименовывается поверх старого. Читатель никогда
+//! не увидит частично записанный блок. Формат (UTF-8, строки через `\n`):
+//!
+//! ```text
+//! # enlil live tail v1
+//! # timestamp 1715689800
+//! m1\t23.450\t-\tOK
+//! m2\t\t-\tTIMEOUT
+//! ```
+//!
+//! Первая строка — версия формата, вторая — время записи в секундах Unix. Далее по строке
+//! на канал, поля разделены табуляцией (`\t` в примере):
+//!
+//! 1. имя канала (табуляции в имени заменяются пробелами);
+//! 2. последнее значение с тремя знаками после точки или пустое поле, если отсчёта нет;
+//! 3. единица измерения (`-`, если не задана);
+//! 4. статус: `OK` или код ошибки опроса (`REFUSED`, `TIMEOUT`, `UNREACHABLE`, ...).
+
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+    thread,
+};
+use crossbeam_channel::{Receiver, Sender};
+
+pub const FORMAT_HEADER: &str = "# enlil live tail v1";
+
+pub struct TailLine {
+    pub name:   String,
+    pub value:  Option<f64>,
+    pub unit:   String,
+    pub status: String,
+}
+
+pub struct TailBlock {
+    pub path:      PathBuf,
+    pub timestamp: u64,
+    pub lines:     Vec<TailLine>,
+}
+
+// Запись идёт в отдельном потоке, чтобы диск не задерживал тик опроса
+pub fn start_writer() -> Sender<TailBlock> {
+    let (tx, rx) = crossbeam_channel::bounded(4);
+    thread::spawn(move || writer_loop(rx));
+    tx
+}
+
+fn writer_loop(rx: Receiver<TailBlock>) {
+    for block in rx {
+        if let Err(e) = write_atomic(&block.path, &format_block(&block)) {
+            tracing::error!(path = %block.path.display(), error = %e, "live tail write failed");
+        }
+    }
+}
+
+pub fn format_block(block: &TailBlock) -> String {
+    let mut text = format!("{}\n# timestamp {}\n", FORMAT_HEADER, block.timestamp);
+    for line in &block.lines {
+        let value = line.value.map(|v| format!("{:.3}", v)).unwrap_or_default();
+        text.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            line.name.replace('\t', " "),
+            value,
+            line.unit,
+            line.status,
+        ));
+    }
+    text
+}
+
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Имя, значение, единица, статус
+    type Channel = (String, Option<f64>, String, String);
+
+    // Разбор по описанию формата в начале модуля — так файл читала бы внешняя программа
+    fn parse(text: &str) -> (u64, Vec<Channel>) {
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(FORMAT_HEADER));
+        let timestamp = lines.next().and_then(|l| l.strip_prefix("# timestamp ")).unwrap().parse().unwrap();
+        let channels = lines
+            .map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                assert_eq!(fields.len(), 4, "{:?}", line);
+                let value = (!fields[1].is_empty()).then(|| fields[1].parse().unwrap());
+                (fields[0].to_string(), value, fields[2].to_string(), fields[3].to_string())
+            })
+            .collect();
+        (timestamp, channels)
+    }
+
+    #[test]
+    fn written_file_parses_back() {
+        let dir = std::env::temp_dir().join(format!("enlil-tail-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tail.txt");
+        let line = |name: &str, value, status: &str| TailLine {
+            name: name.to_string(),
+            value,
+            unit: "-".to_string(),
+            status: status.to_string(),
+        };
+        let block = TailBlock {
+            path:      path.clone(),
+            timestamp: 1_715_689_800,
+            lines:     vec![line("m1", Some(23.4504), "OK"), line("m2\tb", None, "TIMEOUT")],
+        };
+        write_atomic(&path, &format_block(&block)).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        let (timestamp, channels) = parse(&text);
+        assert_eq!(timestamp, 1_715_689_800);
+        assert_eq!(channels, [
+            ("m1".to_string(), Some(23.45), "-".to_string(), "OK".to_string()),
+            ("m2 b".to_string(), None, "-".to_string(), "TIMEOUT".to_string()),
+        ]);
+        assert!(text.ends_with('\n'));
+    }
+
+    #[test]
+    fn rewrite_leaves_no_temp_file() {
+        let dir = std::env::temp_dir().join(format!("enlil-tail-rewrite-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tail.txt");
+        write_atomic(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().filter_map(Result::ok).map(|e| e.file_name()).collect();
+        let text = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(text, "new");
+        assert_eq!(files, ["tail.txt"]);
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    io,
+    path::PathBuf,
+    process::Command,
+};
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use crate::config;
+
+// Сколько суточных файлов журнала хранить
+const KEEP_FILES: usize = 7;
+
+// Каталог журнала рядом с конфигурацией: при запуске с ярлыка stderr никто не видит
+pub fn log_dir() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join("logs"))
+}
+
+// Журнал в stderr и в суточный файл. Уровень задаётся RUST_LOG, по умолчанию info.
+// Guard нужно держать до выхода, иначе хвост журнала не допишется в файл
+pub fn init() -> Option<WorkerGuard> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let appender = log_dir().and_then(|dir| {
+        // Без каталога appender не сможет перебрать старые файлы при ротации
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            eprintln!("Log directory error ({}): {}", dir.display(), e);
+            return None;
+        }
+        rolling::Builder::new()
+            .rotation(rolling::Rotation::DAILY)
+            .filename_prefix("enlil")
+            .filename_suffix("log")
+            .max_log_files(KEEP_FILES)
+            .build(dir)
+            .map_err(|e| eprintln!("Log file error: {}", e))
+            .ok()
+    });
+    let (file, guard) = match appender {
+        Some(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(fmt::layer().with_ansi(false).with_writer(writer)), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(io::stderr))
+        .with(file)
+        .init();
+    guard
+}
+
+// Открывает каталог журнала в файловом менеджере системы
+pub fn open_dir() -> io::Result<()> {
+    let dir = log_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+    std::fs::create_dir_all(&dir)?;
+    let opener = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener).arg(&dir).spawn().map(drop)
+}
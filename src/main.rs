@@ -1,20 +1,395 @@
+mod address;
+mod alert;
+mod autosave;
+mod availability;
+mod breaker;
+mod channel;
+mod config;
+mod demo;
+mod export;
+mod expr;
+mod feed;
+mod fetcher;
+mod fft;
+mod headless;
+mod health;
+mod http;
+mod live_log;
+mod live_tail;
+mod logging;
+mod metrics;
+mod modbus;
+mod profile;
+mod raw_log;
+mod run_state;
+mod serial;
+mod session;
+mod source;
+mod stream;
+mod tls;
+mod udp;
+
+// Имитатор прибора из src/bin: тесты сбора поднимают его в своём процессе, его main не нужен
+#[cfg(test)]
+#[path = "bin/mock_server.rs"]
+#[allow(dead_code)]
+mod mock_server;
+
 use std::{
-    time::{Duration, SystemTime, UNIX_EPOCH},
-    sync::{Arc, Mutex},
+    collections::{HashMap, VecDeque},
+    io::ErrorKind,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
+use clap::Parser;
+use crossbeam_channel::{Receiver, Sender};
 use eframe::egui;
-use egui_plot::{Legend, Line, Plot, PlotPoints};
+use serde::{Deserialize, Serialize};
+use address::AddressChecks;
+use channel::ChannelDef;
+use run_state::{RunCommand, RunControl, RunState};
+use source::DataSource;
+use egui_plot::{AxisHints, GridMark, HLine, HPlacement, Legend, Line, LineStyle, MarkerShape, Plot, PlotPoints, PlotPoint, Points, Polygon, Text, VLine};
 use tokio::{
-    net::TcpStream,
+    net::{self, TcpStream},
+    sync::{mpsc, watch, Semaphore},
     time,
-    io::{AsyncWriteExt,AsyncReadExt},
 };
 
+// Аргументы командной строки
+#[derive(Parser)]
+struct Cli {
+    /// Сбор без окна: старт сразу с серверами из конфигурации, экспорт по Ctrl+C
+    #[arg(long)]
+    headless: bool,
+    /// Остановить сбор и выгрузить результаты через столько секунд
+    #[arg(long, value_name = "SECS", requires = "headless")]
+    duration: Option<u64>,
+    /// Каталог для xlsx, CSV и JSON сессии после сбора без окна
+    #[arg(long, value_name = "DIR", default_value = ".", requires = "headless")]
+    out_dir:  PathBuf,
+}
+
 // Основное состояние приложения
 struct State {
-    shared_data:    Arc<Mutex<ServerData>>,
-    points_to_show: usize,
-    is_collecting:  Arc<Mutex<bool>>,
+    // Собственная копия данных GUI, догоняется обновлениями от сборщика
+    data:              ServerData,
+    updates:           Receiver<CollectorUpdate>,
+    commands:          mpsc::UnboundedSender<CollectorCommand>,
+    window:            TimeWindow,
+    y_axis:            YAxis,
+    // Пределы правой оси, по ней рисуются каналы с ChannelDef::right_axis
+    y_right:           YAxis,
+    // Ширина скользящего среднего в отсчётах, 1 — без сглаживания
+    smoothing:         usize,
+    layout:            PlotLayout,
+    legend:            LegendValues,
+    run:               Arc<RunControl>,
+    run_state:         watch::Receiver<RunState>,
+    run_error:         Option<String>,
+    show_completeness: bool,
+    show_latency:      bool,
+    // Перекрестие на графике: значения всех каналов в ближайшем к курсору отсчёте
+    crosshair:         bool,
+    selection:         RangeSelection,
+    // Обрезка начала сессии: время в поле панели и момент, ждущий подтверждения, мс
+    trim_at:           f64,
+    confirm_trim:      Option<u64>,
+    // Время на графике и в журналах — местное по часам, а не от начала сбора
+    absolute_time:     bool,
+    server_drafts:     ServerDrafts,
+    address_checks:    AddressChecks,
+    export_error:      Option<String>,
+    excel_export:      Option<ExcelExport>,
+    // Отдельный лист на каждый сервер в выгрузке Excel
+    excel_per_server:  bool,
+    stale_filter:      StaleFilter,
+    server_view:       ServerListView,
+    fft:               FftTool,
+    latency_budget:    LatencyBudget,
+    session_path:      String,
+    viewing:           Option<Viewing>,
+    // Снимок несохранённой сессии прошлого запуска, предлагается к восстановлению
+    recovery:          Option<PathBuf>,
+    close_guard:       CloseGuard,
+    // Сервер, удаление которого ждёт подтверждения (ServerInfo::id)
+    confirm_remove:    Option<u32>,
+    undo:              Option<RemovedServer>,
+    profiles:          ProfilePanel,
+    // Подпись следующей отметки; пустая — «Отметка N»
+    marker_label:      String,
+}
+
+// Панель профилей стенда (см. profile.rs). Список файлов перечитывается после каждой операции
+#[derive(Default)]
+struct ProfilePanel {
+    names:    Vec<String>,
+    selected: String,
+    // Имя для «Сохранить как» и переименования
+    name:     String,
+    status:   Option<String>,
+    // Переключение и удаление выполняются только после подтверждения
+    confirm:  Option<ProfileAction>,
+}
+
+enum ProfileAction {
+    Switch(String),
+    Delete(String),
+}
+
+// Последний удалённый сервер. Его колонки остаются в сессии (см. channel::Column),
+// поэтому возврат с тем же id восстанавливает и настройки, и собранные отсчёты
+struct RemovedServer {
+    server: ServerInfo,
+    index:  usize,
+    at:     Instant,
+}
+
+// Закрытие окна и остановка сбора при невыгруженных отсчётах сначала спрашивают пользователя
+#[derive(Default)]
+struct CloseGuard {
+    // Действие, ждущее ответа
+    prompt:    Option<Discarding>,
+    // Пользователь выбрал «Не сохранять», следующий запрос закрытия пропускается
+    confirmed: bool,
+}
+
+// Действия, после которых невыгруженные отсчёты пропадают
+#[derive(Clone, Copy, PartialEq)]
+enum Discarding {
+    Close,
+    // Остановка очищает сессию (см. CollectorUpdate::Cleared)
+    Stop,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AfterExport {
+    Stay,
+    Close,
+    Stop,
+}
+
+// Просмотр загруженной сессии: в data лежат данные из файла, а живая копия
+// продолжает догонять сборщик, чтобы после закрытия вернуться к ней
+struct Viewing {
+    path:            String,
+    live:            ServerData,
+    confirm_discard: bool,
+}
+
+// Выгрузка в Excel в фоновой задаче: книга на сотни тысяч строк строится секунды
+struct ExcelExport {
+    progress: Receiver<ExcelProgress>,
+    cancel:   Arc<AtomicBool>,
+    path:     PathBuf,
+    written:  usize,
+    total:    usize,
+    // Сколько отсчётов живой сессии попало в файл
+    samples:  usize,
+    // Что сделать после успешной записи
+    then:     AfterExport,
+    // Выгружалась живая сессия, а не открытый файл
+    live:     bool,
+}
+
+enum ExcelProgress {
+    Rows { written: usize, total: usize },
+    Done(std::io::Result<()>),
+}
+
+// Окно графика по времени: последние secs секунд или вся сессия.
+// Не зависит от интервала опроса, в отличие от числа точек
+#[derive(Clone, Serialize, Deserialize)]
+struct TimeWindow {
+    secs:     u64,
+    show_all: bool,
+}
+
+impl TimeWindow {
+    // На коротком окне подписи оси времени показывают миллисекунды
+    fn is_short(&self) -> bool {
+        !self.show_all && self.secs <= SHORT_WINDOW_SECS
+    }
+}
+
+// Ручные пределы оси Y. Пока autoscale включён или пределы неверны, ось подстраивается под данные
+#[derive(Clone, Serialize, Deserialize)]
+struct YAxis {
+    autoscale: bool,
+    min:       f64,
+    max:       f64,
+}
+
+impl Default for YAxis {
+    fn default() -> Self {
+        Self { autoscale: true, min: 0.0, max: 100.0 }
+    }
+}
+
+impl YAxis {
+    fn manual_range(&self) -> Option<(f64, f64)> {
+        (!self.autoscale && self.min < self.max).then_some((self.min, self.max))
+    }
+}
+
+// У egui_plot одна система координат, поэтому каналы правой оси переводятся в координаты левой,
+// а правая шкала — только подписи, пересчитанные обратно
+#[derive(Clone, Copy)]
+struct AxisMap {
+    left:  (f64, f64),
+    right: (f64, f64),
+}
+
+impl AxisMap {
+    fn scale(&self) -> f64 {
+        (self.left.1 - self.left.0) / (self.right.1 - self.right.0)
+    }
+
+    fn to_plot(self, value: f64) -> f64 {
+        self.left.0 + (value - self.right.0) * self.scale()
+    }
+
+    fn to_value(self, y: f64) -> f64 {
+        self.right.0 + (y - self.left.0) / self.scale()
+    }
+
+    // Знаков после запятой столько, чтобы соседние подписи различались
+    fn format(self, mark: GridMark) -> String {
+        let step = mark.step_size / self.scale();
+        let decimals = (-step.log10()).ceil().clamp(0.0, 6.0) as usize;
+        format!("{:.*}", decimals, self.to_value(mark.value))
+    }
+}
+
+// Допустимые задержки по звеньям цепочки прибор → экран, мс
+struct LatencyBudget {
+    network_ms:    u64,
+    processing_ms: u64,
+    display_ms:    u64,
+}
+
+// Окно быстрого спектрального анализа одного канала
+struct FftTool {
+    open:      bool,
+    channel:   usize,
+    remove_dc: bool,
+    peaks:     usize,
+    result:    Option<Result<fft::Spectrum, String>>,
+    status:    Option<String>,
+}
+
+// Фильтр списка серверов по давности последнего корректного отсчёта
+// и порог, после которого отсчёт подсвечивается как устаревший
+struct StaleFilter {
+    enabled:    bool,
+    min_age:    u64,
+    warn_after: u64,
+}
+
+// Выделенный диапазон времени, с от начала сбора: задаётся полями или перетаскиванием по графику с Shift
+#[derive(Default)]
+struct RangeSelection {
+    active:      bool,
+    from:        f64,
+    to:          f64,
+    // Где началось перетаскивание
+    drag_from:   Option<f64>,
+    // Выгрузка берёт только выделенное (см. session::slice)
+    export_only: bool,
+}
+
+impl RangeSelection {
+    // Диапазон для выгрузки в мс, границы по возрастанию
+    fn export_range(&self) -> Option<(u64, u64)> {
+        let (from, to) = (self.from.min(self.to).max(0.0), self.from.max(self.to).max(0.0));
+        (self.active && self.export_only).then_some(((from * 1000.0) as u64, (to * 1000.0) as u64))
+    }
+}
+
+// Все каналы на одном графике или сетка малых графиков, по одному на видимый канал
+#[derive(Clone, Copy, PartialEq, Default)]
+enum PlotLayout {
+    #[default]
+    Combined,
+    Grid,
+}
+
+// Последнее значение канала в подписи легенды: «имя — 12.34»
+struct LegendValues {
+    enabled:  bool,
+    decimals: usize,
+    // Подпись прошлого кадра → имя канала. egui_plot помнит скрытые линии по подписи,
+    // а она меняется с каждым отсчётом: по этой таблице скрытые переносятся на новые подписи
+    names:    HashMap<String, String>,
+}
+
+// Вид списка серверов: поиск по имени или адресу и компактные строки для больших стендов
+#[derive(Default)]
+struct ServerListView {
+    search:   String,
+    compact:  bool,
+    // Сервер, открытый на редактирование из компактного списка (ServerInfo::id)
+    expanded: Option<u32>,
+    // Измеренная высота полной записи по id: записи вне экрана заменяются отступом такой высоты
+    heights:  HashMap<u32, f32>,
+}
+
+// Общее для всех строк списка серверов
+struct ServerListCtx<'a> {
+    drafts:        &'a mut ServerDrafts,
+    checks:        &'a mut AddressChecks,
+    is_collecting: bool,
+    warn_after:    Duration,
+    // Средняя задержка выше — статус подсвечивается, порог «сеть» из бюджета задержек
+    latency_warn:  Duration,
+    // Начало сбора для времени по часам (см. format_time), None — время от начала
+    time_origin:   Option<u64>,
+}
+
+const TICK_INTERVAL:         Duration = Duration::from_secs(1);
+const SHORT_WINDOW_SECS:     u64 = 10;
+const RESPONSE_TIMEOUT:      Duration = Duration::from_secs(1);
+const STARTUP_PROBE_TIMEOUT: Duration = Duration::from_millis(300);
+// Сколько держится предложение вернуть удалённый сервер
+const UNDO_TIMEOUT:          Duration = Duration::from_secs(30);
+// Задержки опроса: сколько хранить для графика и по скольким считать среднее
+const LATENCY_HISTORY:       usize = 300;
+const LATENCY_AVERAGE:       usize = 10;
+// Высота полной записи сервера, пока она ни разу не показывалась
+const SERVER_ENTRY_HEIGHT:   f32 = 300.0;
+
+// Пороги подсветки устаревших отсчётов. Предупреждение настраивается в списке серверов
+const STALE_WARNING: Duration = Duration::from_secs(5);
+const STALE_ERROR:   Duration = Duration::from_secs(60);
+
+const EXCEL_PATH: &str = "monitoring_data.xlsx";
+const CSV_PATH:   &str = "monitoring_data.csv";
+const JSON_PATH:  &str = "monitoring_session.json";
+
+// Черновики правок полей сервера. Значение уходит в сбор только после Enter
+// или потери фокуса и успешной проверки, до этого опрос идёт по старому адресу.
+// Ключ — id сервера (ServerInfo::id): удаление и возврат серверов не переносят черновик на соседа
+#[derive(Default)]
+struct ServerDrafts {
+    texts:  HashMap<(u32, ServerField), String>,
+    errors: HashMap<(u32, ServerField), String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum ServerField {
+    Name,
+    Address,
+    Command,
+    Url,
+    JsonPointer,
+    Request,
+    Terminator,
+    Expression,
 }
 
 // Структура для хранения данных
@@ -22,32 +397,473 @@ struct State {
 struct ServerData {
     computed_results: Vec<ComputationResults>,
     servers:          Vec<ServerInfo>,
+    // Раскладка flow текущей сессии. Ведёт её сборщик и рассылает через CollectorUpdate::Layout
+    columns:          Vec<channel::Column>,
+    // Unix-время первого отсчёта в миллисекундах, только для подписей и экспорта
     start_time:       Option<u64>,
+    // Момент первого отсчёта по монотонным часам, None для загруженной сессии
+    started:          Option<Instant>,
+    failure_counts:   HashMap<FetchFailure, u64>,
+    alerts:           Vec<alert::AlertEvent>,
+    live_tail:        LiveTailSettings,
+    metrics:          MetricsSettings,
+    feed:             FeedSettings,
+    autosave:         AutosaveSettings,
+    live_log:         LiveLogSettings,
+    // Почему журнал отсчётов был выключен сборщиком
+    live_log_error:   Option<String>,
+    auto_stop:        AutoStopSettings,
+    breaker:          BreakerSettings,
+    // Сколько серверов опрашивается одновременно, 0 — без ограничения
+    max_connections:  usize,
+    // Почему сборщик сам приостановил сбор; в GUI сбрасывается кнопкой на баннере
+    auto_stopped:     Option<AutoStop>,
+    retry:            RetryPolicy,
+    config_dirty:     bool,
+    // Время обработки последнего тика сборщиком: разбор, запись, рассылка
+    processing_time:  Duration,
+    // Заметки оператора к сессии, уходят в метаданные экспорта
+    notes:            String,
+    // Значения генерирует demo.rs, а не приборы
+    demo:             bool,
+    export_dir:       Option<PathBuf>,
+    // Последний загруженный или сохранённый профиль
+    profile:          Option<String>,
+    shortcuts:        ShortcutSettings,
+    // Есть отсчёты, пришедшие после последнего успешного экспорта
+    unsaved:          bool,
+    // Сколько раз за сессию канал остался без отсчёта, копится по приходу отсчётов
+    missed:           u64,
+    // Отметки оператора, ставятся только в GUI и очищаются вместе с сессией
+    markers:          Vec<Marker>,
+}
+
+// Повторы внутри тика при кратковременных сбоях соединения
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct RetryPolicy {
+    max_retries: u32,
+    backoff_ms:  u64,
+}
+
+// Настройки файла «живого хвоста» (см. live_tail.rs)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct LiveTailSettings {
+    enabled: bool,
+    path:    String,
+}
+
+// Эндпоинт Prometheus (см. metrics.rs)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct MetricsSettings {
+    enabled: bool,
+    listen:  String,
+}
+
+// Автоостановка: если все включённые серверы не отвечают ticks тиков подряд, сбор ставится на паузу
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct AutoStopSettings {
+    enabled: bool,
+    ticks:   u32,
+}
+
+// Пропуск молчащего сервера (см. breaker.rs): failures ошибок подряд размыкают цепь на cooldown_secs,
+// каждая неудачная проба удваивает паузу до max_cooldown_secs
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct BreakerSettings {
+    enabled:           bool,
+    failures:          u32,
+    cooldown_secs:     u64,
+    max_cooldown_secs: u64,
+}
+
+// Журнал отсчётов, дописываемый по мере сбора (см. live_log.rs)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct LiveLogSettings {
+    enabled: bool,
+    path:    String,
+}
+
+// Горячие клавиши в записи egui, например "F5" или "Ctrl+S". Пустая строка — клавиша не назначена
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct ShortcutSettings {
+    start_stop: String,
+    export:     String,
+    marker:     String,
+}
+
+// Периодические снимки сессии на случай падения (см. autosave.rs)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct AutosaveSettings {
+    enabled:      bool,
+    interval_min: u64,
+    dir:          String,
+}
+
+// WebSocket-трансляция отсчётов (см. feed.rs)
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct FeedSettings {
+    enabled:  bool,
+    listen:   String,
+    // Сколько последних отсчётов получает новый клиент
+    snapshot: usize,
+}
+
+// Обновления от сборщика к GUI. Обе стороны применяют их через apply_update,
+// поэтому копии ServerData совпадают без общей блокировки
+#[derive(Clone)]
+enum CollectorUpdate {
+    Status {
+        // ServerInfo::id
+        server:  u32,
+        address: String,
+        status:  ServerStatus,
+        failure: Option<FetchFailure>,
+        value:   Option<f64>,
+        error:   Option<String>,
+        retries: u32,
+        latency: Duration,
+        at:      Instant,
+        // Только при включённой отладке сервера
+        raw:     Option<raw_log::RawResponse>,
+        breaker: breaker::Breaker,
+    },
+    Sample {
+        start_time: u64,
+        // Монотонная точка отсчёта сессии, от неё считается result.timestamp
+        started:    Instant,
+        result:     ComputationResults,
+    },
+    Cleared,
+    // Новая раскладка колонок: в начале сессии или после добавления сервера
+    Layout(Vec<channel::Column>),
+    // Все серверы молчали дольше AutoStopSettings::ticks, сбор приостановлен
+    AutoStopped(AutoStop),
+    // Запись журнала отсчётов не удалась, журнал выключен
+    LiveLogFailed {
+        error: String,
+    },
+    // Конец тика сборщика
+    Tick {
+        processing: Duration,
+    },
+}
+
+// Команды от GUI к сборщику. Configure приходит редко, упаковывать его в Box ради размера Trim незачем
+#[allow(clippy::large_enum_variant)]
+enum CollectorCommand {
+    // Новая конфигурация серверов и опроса, действует со следующего тика
+    Configure {
+        servers:   Vec<ServerInfo>,
+        retry:     RetryPolicy,
+        live_tail: LiveTailSettings,
+        metrics:   MetricsSettings,
+        feed:      FeedSettings,
+        autosave:  AutosaveSettings,
+        live_log:  LiveLogSettings,
+        auto_stop: AutoStopSettings,
+        breaker:   BreakerSettings,
+        max_connections: usize,
+        // Демо-режим вместо опроса приборов, в GUI переключается только при остановленном сборе
+        demo:      bool,
+    },
+    // Отбросить отсчёты раньше before мс и начать сессию с нуля (см. session::trim). Только на паузе
+    Trim { before: u64 },
+    // Отметки оператора ставятся в GUI; копия сборщика нужна для снимков автосохранения
+    Markers(Vec<Marker>),
 }
 
 // Структура для хранения результатов вычислений
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct ComputationResults {
+    // Миллисекунды от начала сбора
     timestamp: u64,
-    flow: Vec<f64>
+    // Значения каналов по колонкам ServerData::columns. None — канал не дал корректного отсчёта на этом тике
+    flow: Vec<Option<f64>>,
+    // Полнота данных: сколько каналов дали корректный отсчёт из скольких опрошенных
+    sampled:  usize,
+    channels: usize,
+    // Первый отсчёт после паузы: на графике линия перед ним разрывается
+    #[serde(default)]
+    after_pause: bool,
+    // Откуда взялось значение каждой колонки, параллельно flow. Пусто у сессий до появления поля
+    #[serde(default)]
+    quality: Vec<SampleQuality>,
 }
 
-#[derive(Clone)]
+// Отметка оператора на шкале времени: «открыт клапан», «поджиг горелки»
+#[derive(Clone, Serialize, Deserialize)]
+struct Marker {
+    // Миллисекунды от начала сбора, как у отсчётов
+    timestamp: u64,
+    label:     String,
+}
+
+// Качество отсчёта канала на тике. Значение в flow есть только у Good и Retried
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SampleQuality {
+    Good,
+    // Получено с повторами внутри тика: значение настоящее, но связь нестабильна
+    Retried,
+    Timeout,
+    // Ответ пришёл, но поле канала отсутствует или не число
+    Parse,
+    // Прочие ошибки опроса: отказ соединения, DNS, протокол
+    Error,
+    // Сервер выключен
+    Disabled,
+    // Опрос пропущен: цепь сервера разомкнута (см. breaker.rs)
+    Skipped,
+    // Сервер удалён посреди сессии
+    Missing,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct ServerInfo {
+    // Устойчивый идентификатор: по нему колонки отсчётов и статусы находят свой сервер,
+    // как бы ни менялся порядок списка. 0 — ещё не выдан (см. channel::assign_ids)
+    #[serde(default)]
+    id:      u32,
     name:    String,
+    // host:port для источников поверх TCP
     address: String,
-    online:  bool,
+    #[serde(default)]
+    source:  SourceKind,
+    // Поле command из конфигураций до появления source, переносится в SourceKind::Tcp при загрузке
+    #[serde(default, rename = "command", skip_serializing)]
+    legacy_command: Option<String>,
+    // Таймаут ответа в мс, 0 — общий по умолчанию
+    #[serde(default)]
+    timeout_ms: u64,
+    // Значения, извлекаемые из одного ответа. Меняется только при остановленном сборе:
+    // колонка адресует канал по номеру, правка списка переназначила бы уже собранные значения
+    #[serde(default = "channel::default_channels")]
+    channels: Vec<ChannelDef>,
+    // Калибровка всех каналов сервера (например, psi -> Па: 6894.75672), применяется до записи.
+    // Меняется только при остановленном сборе, чтобы единицы внутри сессии не смешивались
+    #[serde(default = "channel::default_scale")]
+    scale:   f64,
+    #[serde(default)]
+    offset:  f64,
+    // Выключенный сервер не опрашивается, но сохраняет настройки и место своих колонок
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(skip)]
+    status:  ServerStatus,
+    #[serde(skip)]
+    failure: Option<FetchFailure>,
+    // Монотонное время последнего корректного отсчёта. Обновляется опросом, даже когда сбор остановлен
+    #[serde(skip)]
+    last_good: Option<Instant>,
+    // Последнее разобранное значение и время его получения (unix, с)
+    #[serde(skip)]
+    last_value: Option<f64>,
+    #[serde(skip)]
+    last_seen:  Option<u64>,
+    // Текст последней ошибки опроса и с какого момента (unix, с) она повторяется
+    #[serde(skip)]
+    last_error:  Option<String>,
+    #[serde(skip)]
+    error_since: Option<u64>,
+    // Сколько повторов понадобилось для последнего отсчёта
+    #[serde(skip)]
+    retries:   u32,
+    // Длительность опроса прибора, включая повторы
+    #[serde(skip)]
+    latency:   Duration,
+    // Последние длительности опроса с моментом получения: для среднего в списке и графика задержек
+    #[serde(skip)]
+    latency_history: VecDeque<(Instant, Duration)>,
+    // Ведёт сборщик, GUI получает копию со статусом
+    #[serde(skip)]
+    breaker: breaker::Breaker,
+    // Был ли корректный отсчёт на последних тиках и доля таких тиков за сессию (см. health.rs)
+    #[serde(skip)]
+    health:  health::Health,
+    // Когда эта копия данных получила последний статус и сколько продержался предыдущий
+    #[serde(skip)]
+    received_at:  Option<Instant>,
+    #[serde(skip)]
+    display_peak: Duration,
+    // Запись сырых ответов для отладки прибора. Не сохраняется: каждый запуск начинается с выключенной
+    #[serde(skip)]
+    debug:   bool,
+    #[serde(skip)]
+    raw_log: raw_log::RawLog,
+}
+
+// Протокол опроса сервера
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum SourceKind {
+    // Строка запроса; escape-последовательности \n, \r, \t, \\ и \xHH раскрываются при отправке
+    // В потоковом режиме соединение держится открытым, прибор сам шлёт строки,
+    // а в отсчёт тика идёт последняя из них. Команда при этом не отправляется.
+    // TLS применяется только в режиме запрос-ответ, по умолчанию выключен
+    Tcp {
+        #[serde(default = "default_command")]
+        command: String,
+        #[serde(default)]
+        streaming: bool,
+        #[serde(default)]
+        tls: tls::TlsSettings,
+    },
+    // GET url, значение выбирается из JSON-ответа указателем вида /num1
+    Http {
+        url: String,
+        #[serde(default)]
+        json_pointer: String,
+    },
+    // Modbus TCP, чтение holding-регистров по address
+    Modbus {
+        unit_id:  u8,
+        register: u16,
+        count:    u16,
+        #[serde(default)]
+        decode:   modbus::Decode,
+    },
+    // Последовательный порт (RS-232/RS-485), 8N1. Запрос и конец строки — с теми же escape, что у TCP
+    Serial {
+        port:       String,
+        baud:       u32,
+        #[serde(default)]
+        request:    String,
+        #[serde(default = "default_terminator")]
+        terminator: String,
+    },
+    // Датаграммы от address, принимаемые на local_port. trigger, если не пуст, отправляется перед ожиданием
+    Udp {
+        local_port: u16,
+        #[serde(default)]
+        trigger:    String,
+    },
+    // Производный канал: выражение над подписями других каналов (см. expr.rs), без опроса.
+    // Считается по значениям того же тика, поэтому хранится и выгружается как обычный канал
+    Derived {
+        expression: String,
+    },
+}
+
+fn default_terminator() -> String {
+    "\\r\\n".to_string()
+}
+
+impl Default for SourceKind {
+    fn default() -> Self {
+        SourceKind::Tcp { command: default_command(), streaming: false, tls: Default::default() }
+    }
+}
+
+impl SourceKind {
+    const KINDS: [&'static str; 6] = ["TCP", "HTTP", "Modbus", "Serial", "UDP", "Выражение"];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SourceKind::Tcp { .. }    => "TCP",
+            SourceKind::Http { .. }   => "HTTP",
+            SourceKind::Modbus { .. } => "Modbus",
+            SourceKind::Serial { .. } => "Serial",
+            SourceKind::Udp { .. }    => "UDP",
+            SourceKind::Derived { .. } => "Выражение",
+        }
+    }
+
+    fn with_label(label: &str) -> Self {
+        match label {
+            "HTTP" => SourceKind::Http { url: "http://127.0.0.1:8000/".to_string(), json_pointer: String::new() },
+            "Modbus" => SourceKind::Modbus { unit_id: 1, register: 0, count: 1, decode: modbus::Decode::default() },
+            "Serial" => SourceKind::Serial {
+                port:       serial::available_ports().into_iter().next().unwrap_or_default(),
+                baud:       9600,
+                request:    default_command(),
+                terminator: default_terminator(),
+            },
+            "UDP" => SourceKind::Udp { local_port: 5005, trigger: String::new() },
+            "Выражение" => SourceKind::Derived { expression: "m1".to_string() },
+            _ => SourceKind::default(),
+        }
+    }
+
+    // Опрашивается ли сервер по полю address
+    fn uses_address(&self) -> bool {
+        matches!(self, SourceKind::Tcp { .. } | SourceKind::Modbus { .. } | SourceKind::Udp { .. })
+    }
+
+    fn is_derived(&self) -> bool {
+        matches!(self, SourceKind::Derived { .. })
+    }
+}
+
+// До первого ответа сервер не считается ни доступным, ни недоступным
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+enum ServerStatus {
+    #[default]
+    Unchecked,
+    Online,
+    Offline,
 }
 
+// Категории ошибок опроса с подсказкой для оператора
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum FetchFailure {
+    Refused,
+    TimedOut,
+    Unreachable,
+    Dns,
+    Http,
+    Modbus,
+    Tls,
+    Expr,
+    NoData,
+    InvalidUtf8,
+    Protocol,
+    BadValue,
+    Other,
+}
+
+// Таблица соответствия io::ErrorKind -> категория. Новые протоколы дописывают сюда свои виды ошибок
+const IO_FAILURE_TABLE: &[(ErrorKind, FetchFailure)] = &[
+    (ErrorKind::ConnectionRefused,  FetchFailure::Refused),
+    (ErrorKind::ConnectionReset,    FetchFailure::Refused),
+    (ErrorKind::TimedOut,           FetchFailure::TimedOut),
+    (ErrorKind::HostUnreachable,    FetchFailure::Unreachable),
+    (ErrorKind::NetworkUnreachable, FetchFailure::Unreachable),
+    (ErrorKind::AddrNotAvailable,   FetchFailure::Unreachable),
+    (ErrorKind::InvalidData,        FetchFailure::Protocol),
+];
+
 
 #[tokio::main]
 async fn main() -> eframe::Result {
-    let servers       = create_default_servers();
-    let shared_data   = Arc::new(Mutex::new(ServerData::new(servers)));
-    let is_collecting = Arc::new(Mutex::new(false));
+    let cli           = Cli::parse();
+    let _log_guard    = logging::init();
+    let config        = config::load().unwrap_or_else(|| config::Config {
+        servers: create_default_servers(),
+        ..Default::default()
+    });
+    let run           = Arc::new(RunControl::new());
+    let outputs       = CollectorOutputs {
+        tail_tx: live_tail::start_writer(),
+        metrics: metrics::Exporter::new(),
+        feed:    feed::Feed::new(),
+    };
+    let (updates_tx, updates_rx)   = crossbeam_channel::unbounded();
+    let (commands_tx, commands_rx) = mpsc::unbounded_channel();
     
-    start_data_collection_task(shared_data.clone(), is_collecting.clone());
-    run_gui(shared_data, is_collecting).await
+    start_data_collection_task(ServerData::new(config.clone()), run.clone(), updates_tx, commands_rx, outputs, fetcher::Network);
+    if cli.headless {
+        headless::run(ServerData::new(config), updates_rx, run, cli.duration.map(Duration::from_secs), &cli.out_dir).await;
+        return Ok(());
+    }
+    run_gui(ServerData::new(config), updates_rx, commands_tx, run).await
 }
 
 // Инициализация ===========================================================
@@ -63,336 +879,4761 @@ fn create_default_servers() -> Vec<ServerInfo> {
 impl ServerInfo {
     fn new(name: &str, address: &str) -> Self {
         Self {
+            id:      0,
             name:    name.to_string(),
             address: address.to_string(),
-            online:  false,
+            source:  SourceKind::default(),
+            legacy_command: None,
+            timeout_ms: 0,
+            channels: channel::default_channels(),
+            scale:   1.0,
+            offset:  0.0,
+            enabled: true,
+            status:  ServerStatus::Unchecked,
+            failure: None,
+            last_good: None,
+            last_value: None,
+            last_seen:  None,
+            last_error:  None,
+            error_since: None,
+            retries:   0,
+            latency:   Duration::ZERO,
+            latency_history: VecDeque::new(),
+            breaker: breaker::Breaker::default(),
+            health:  health::Health::default(),
+            received_at:  None,
+            display_peak: Duration::ZERO,
+            debug:   false,
+            raw_log: raw_log::RawLog::default(),
         }
     }
-}
 
-impl ServerData {
-    fn new(servers: Vec<ServerInfo>) -> Self {
-        Self {
-            computed_results: Vec::new(),
-            servers,
-            start_time: None,
+    // Старые конфигурации хранили команду TCP прямо в сервере
+    fn migrate_legacy_command(&mut self) {
+        if let (Some(legacy), SourceKind::Tcp { command, .. }) = (self.legacy_command.take(), &mut self.source) {
+            *command = legacy;
         }
     }
-}
-
-// Логика сбора данных =====================================================
 
-fn start_data_collection_task(
-    shared_data:   Arc<Mutex<ServerData>>,
-    is_collecting: Arc<Mutex<bool>>,
-) {
-    tokio::spawn(async move {
-        data_collection_loop(shared_data, is_collecting).await
-    });
-}
+    fn response_timeout(&self, default: Duration) -> Duration {
+        if self.timeout_ms == 0 { default } else { Duration::from_millis(self.timeout_ms) }
+    }
 
-async fn data_collection_loop(
-    shared_data:   Arc<Mutex<ServerData>>,
-    is_collecting: Arc<Mutex<bool>>,
-) {
-    let mut interval = time::interval(Duration::from_secs(1));
-    
-    loop {
-        interval.tick().await;
+    // None — корректных отсчётов ещё не было
+    fn sample_age(&self) -> Option<Duration> {
+        self.last_good.map(|at| at.elapsed())
+    }
 
-        let responses = fetch_all_servers(&shared_data).await;
-        update_server_statuses(&shared_data, &responses);
+    fn has_good_sample(&self) -> bool {
+        self.status == ServerStatus::Online && self.failure.is_none()
+    }
 
-        if *is_collecting.lock().unwrap() {
-            let timestamp = current_timestamp();
-            let flow = parse_responses(&responses);
-            save_computation_result(&shared_data.clone(), ComputationResults { timestamp, flow });
+    fn push_latency(&mut self, at: Instant, latency: Duration) {
+        if self.latency_history.len() == LATENCY_HISTORY {
+            self.latency_history.pop_front();
         }
+        self.latency_history.push_back((at, latency));
     }
-}
 
-fn current_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs()
+    // Среднее по последним LATENCY_AVERAGE опросам: одиночный медленный ответ не перекрашивает статус
+    fn latency_avg(&self) -> Option<Duration> {
+        let recent = self.latency_history.iter().rev().take(LATENCY_AVERAGE);
+        let count = recent.len() as u32;
+        (count > 0).then(|| recent.map(|(_, latency)| *latency).sum::<Duration>() / count)
+    }
 }
 
-async fn fetch_all_servers(shared_data: &Arc<Mutex<ServerData>>) -> Vec<Result<String, std::io::Error>> {
-    let servers = shared_data.lock().unwrap().servers.clone();
-
-    futures::future::join_all(
-        servers.iter().map(|server| fetch_data_async(&server.address))
-    ).await
+fn default_command() -> String {
+    "rffff0".to_string()
 }
 
-fn parse_responses(responses: &[Result<String, std::io::Error>]) -> Vec<f64> {
-    responses
-        .iter()
-        .map(|resp| resp
-            .as_ref()
-            .map(|s| s.parse().unwrap_or(0.0))
-            .unwrap_or(0.0)
-        )
-        .collect()
+fn default_enabled() -> bool {
+    true
 }
 
-fn update_server_statuses(shared_data: &Arc<Mutex<ServerData>>, responses: &[Result<String, std::io::Error>]) {
-    let mut data = shared_data.lock().unwrap();
-    for (server, resp) in data.servers.iter_mut().zip(responses.iter()) {
-        server.online = resp.is_ok();
-    }
-}
 
-fn save_computation_result(shared_data: &Arc<Mutex<ServerData>>, result: ComputationResults) {
-    let mut data = shared_data.lock().unwrap();
+impl FetchFailure {
+    const ALL: [FetchFailure; 13] = [
+        FetchFailure::Refused,
+        FetchFailure::TimedOut,
+        FetchFailure::Unreachable,
+        FetchFailure::Dns,
+        FetchFailure::Http,
+        FetchFailure::Modbus,
+        FetchFailure::Tls,
+        FetchFailure::Expr,
+        FetchFailure::NoData,
+        FetchFailure::InvalidUtf8,
+        FetchFailure::Protocol,
+        FetchFailure::BadValue,
+        FetchFailure::Other,
+    ];
 
-    // Устанавливаем время начала при первом сохранении
-    if data.start_time.is_none() {
-        data.start_time = Some(result.timestamp);
+    fn from_io_error(err: &std::io::Error) -> Self {
+        if address::is_dns_error(err) {
+            return FetchFailure::Dns;
+        }
+        if http::is_http_error(err) {
+            return FetchFailure::Http;
+        }
+        if modbus::is_modbus_error(err) {
+            return FetchFailure::Modbus;
+        }
+        // До таблицы: ошибки rustls и неверная кодировка приходят с видом InvalidData, как и прочие ошибки кадра
+        if tls::is_tls_error(err) {
+            return FetchFailure::Tls;
+        }
+        if source::is_utf8_error(err) {
+            return FetchFailure::InvalidUtf8;
+        }
+        if expr::is_expr_error(err) {
+            return FetchFailure::Expr;
+        }
+        if stream::is_no_recent_data(err) {
+            return FetchFailure::NoData;
+        }
+        IO_FAILURE_TABLE
+            .iter()
+            .find(|(kind, _)| *kind == err.kind())
+            .map(|(_, failure)| *failure)
+            .unwrap_or(FetchFailure::Other)
     }
-    
-    // Вычисляем относительное время
-    let relative_timestamp = result.timestamp - data.start_time.unwrap();
-    let new_result = ComputationResults {
-        timestamp: relative_timestamp,
-        flow: result.flow,
-    };
 
-    data.computed_results.push(new_result);
-}
+    // Ошибка соединения или ответ, из которого не удалось извлечь все каналы
+    fn classify(resp: &Result<String, std::io::Error>, values: &[Option<f64>]) -> Option<Self> {
+        match resp {
+            Err(e) => Some(Self::from_io_error(e)),
+            Ok(_) if values.iter().any(Option::is_none) => Some(FetchFailure::BadValue),
+            Ok(_) => None,
+        }
+    }
 
-async fn fetch_data_async(address: &str) -> Result<String, std::io::Error> {
-    let mut stream = TcpStream::connect(address).await?;
-    stream.write_all(b"rffff0").await?;
+    // Сбой, который имеет смысл повторить в том же тике
+    fn is_transient(&self) -> bool {
+        matches!(self, FetchFailure::Refused | FetchFailure::TimedOut)
+    }
 
-    let mut response = Vec::new();
-    match tokio::time::timeout(Duration::from_secs(1), stream.read_to_end(&mut response)).await {
-        Ok(Ok(_bytes_read)) => Ok(String::from_utf8_lossy(&response).into_owned()),
-        Ok(Err(e)) => Err(e),
-        Err(_) => Err(std::io::Error::new(
-            std::io::ErrorKind::TimedOut, 
-            "Response timeout"
-        )),
+    // Машиночитаемый код для внешних файлов
+    fn code(&self) -> &'static str {
+        match self {
+            FetchFailure::Refused     => "REFUSED",
+            FetchFailure::TimedOut    => "TIMEOUT",
+            FetchFailure::Unreachable => "UNREACHABLE",
+            FetchFailure::Dns         => "DNS",
+            FetchFailure::Http        => "HTTP",
+            FetchFailure::Modbus      => "MODBUS",
+            FetchFailure::Tls         => "TLS",
+            FetchFailure::Expr        => "EXPR",
+            FetchFailure::NoData      => "NO_DATA",
+            FetchFailure::InvalidUtf8 => "INVALID_UTF8",
+            FetchFailure::Protocol    => "PROTOCOL",
+            FetchFailure::BadValue    => "BAD_VALUE",
+            FetchFailure::Other       => "ERROR",
+        }
     }
-}
 
-// GUI ======================================================================
+    fn label(&self) -> &'static str {
+        match self {
+            FetchFailure::Refused     => "Соединение отклонено",
+            FetchFailure::TimedOut    => "Таймаут",
+            FetchFailure::Unreachable => "Узел недоступен",
+            FetchFailure::Dns         => "Ошибка DNS",
+            FetchFailure::Http        => "Ошибка HTTP",
+            FetchFailure::Modbus      => "Ошибка Modbus",
+            FetchFailure::Tls         => "Ошибка сертификата",
+            FetchFailure::Expr        => "Ошибка выражения",
+            FetchFailure::NoData      => "Нет свежих данных",
+            FetchFailure::InvalidUtf8 => "Неверная кодировка",
+            FetchFailure::Protocol    => "Неверный ответ",
+            FetchFailure::BadValue    => "Не число",
+            FetchFailure::Other       => "Ошибка",
+        }
+    }
 
-async fn run_gui(
-    shared_data:   Arc<Mutex<ServerData>>,
-    is_collecting: Arc<Mutex<bool>>
-) -> eframe::Result {
-    eframe::run_native(
-        "Server Monitoring System",
-        eframe::NativeOptions::default(),
-        Box::new(|cc| {
-            egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(State {
-                shared_data,
-                points_to_show: 20,
-                is_collecting,
-            }))
-        }),
-    )
+    fn hint(&self) -> &'static str {
+        match self {
+            FetchFailure::Refused     => "Порт устройства закрыт — проверьте службу на приборе",
+            FetchFailure::TimedOut    => "Нет ответа вовремя — проверьте файрвол и нагрузку прибора",
+            FetchFailure::Unreachable => "Нет маршрута — проверьте коммутатор/VLAN",
+            FetchFailure::Dns         => "Имя не разрешилось — проверьте написание хоста и DNS-сервер",
+            FetchFailure::Http        => "Ошибочный статус, не JSON или нет поля — проверьте URL и указатель",
+            FetchFailure::Modbus      => "Устройство вернуло исключение — проверьте unit id и адреса регистров",
+            FetchFailure::Tls         => "Рукопожатие TLS не прошло — проверьте CA-сертификат и имя узла в адресе",
+            FetchFailure::Expr        => "Выражение не вычислилось — проверьте имена каналов и что они дают значения",
+            FetchFailure::NoData      => "Соединение открыто, но прибор молчит — проверьте, что он передаёт данные",
+            FetchFailure::InvalidUtf8 => "Ответ не в UTF-8 — проверьте формат кадра протокола",
+            FetchFailure::Protocol    => "Ответ не разобран протоколом — проверьте тип источника и настройки кадра",
+            FetchFailure::BadValue    => "Ответ получен, но не все каналы — числа: проверьте команду и номера полей",
+            FetchFailure::Other       => "Неизвестная ошибка соединения",
+        }
+    }
 }
 
-impl eframe::App for State {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.request_repaint_after(Duration::from_secs(1));
-
-        egui::SidePanel::right("right_panel")
-            .resizable(false)
-            .default_width(200.0)
-            .show(ctx, |ui| {
-                render_side_panel(ui, self);
-            });
+impl ServerData {
+    fn new(mut config: config::Config) -> Self {
+        for server in &mut config.servers {
+            server.migrate_legacy_command();
+        }
+        // GUI и сборщик создаются из одной конфигурации, поэтому id у них совпадают
+        channel::assign_ids(&mut config.servers);
+        Self {
+            computed_results: Vec::new(),
+            columns: channel::layout(&config.servers),
+            servers: config.servers,
+            retry: config.retry,
+            start_time: None,
+            started: None,
+            failure_counts: HashMap::new(),
+            alerts: Vec::new(),
+            live_tail: config.live_tail,
+            metrics: config.metrics,
+            feed: config.feed,
+            autosave: config.autosave,
+            live_log: config.live_log,
+            live_log_error: None,
+            auto_stop: config.auto_stop,
+            breaker: config.breaker,
+            max_connections: config.max_connections,
+            auto_stopped: None,
+            config_dirty: false,
+            processing_time: Duration::ZERO,
+            notes: String::new(),
+            demo: false,
+            export_dir: config.export_dir,
+            profile: config.profile,
+            shortcuts: config.shortcuts,
+            unsaved: false,
+            missed: 0,
+            markers: Vec::new(),
+        }
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            render_main_content(ui, self);
-        });
+    fn to_config(&self) -> config::Config {
+        config::Config {
+            servers:   self.servers.clone(),
+            retry:     self.retry,
+            live_tail: self.live_tail.clone(),
+            metrics:   self.metrics.clone(),
+            feed:      self.feed.clone(),
+            autosave:  self.autosave.clone(),
+            live_log:  self.live_log.clone(),
+            auto_stop: self.auto_stop.clone(),
+            breaker:   self.breaker.clone(),
+            max_connections: self.max_connections,
+            export_dir: self.export_dir.clone(),
+            profile:    self.profile.clone(),
+            shortcuts:  self.shortcuts.clone(),
+        }
     }
 }
 
-// Боковая панель
-fn render_side_panel(ui: &mut egui::Ui, state: &mut State) {
-    ui.vertical_centered(|ui| ui.heading("Настройки"));
-    ui.separator();
-
-    render_plot_settings(ui, state);
-    render_collection_control(ui, state);
-    render_server_list(ui, state);
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            backoff_ms:  100,
+        }
+    }
 }
 
-fn render_plot_settings(ui: &mut egui::Ui, state: &mut State) {
-    ui.heading("Настройки графика");
-    ui.horizontal(|ui| {
-        ui.label("Точек на графике:");
-        ui.add(egui::DragValue::new(&mut state.points_to_show).range(2..=500));
-    });
+impl Default for LiveTailSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path:    "enlil_live.txt".to_string(),
+        }
+    }
 }
 
-fn render_collection_control(ui: &mut egui::Ui, state: &mut State) {
-    ui.separator();
-    ui.heading("Управление сбором");
-    
-    let is_collecting = *state.is_collecting.lock().unwrap();
-    let button_text = if is_collecting { "⏹ Остановить сбор" } else { "▶ Начать сбор" };
-    
-    if ui.button(button_text).clicked() {
-        toggle_collection_state(state, is_collecting);
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen:  "0.0.0.0:9184".to_string(),
+        }
     }
 }
 
-fn toggle_collection_state(state: &mut State, current_state: bool) {
-    let mut is_collecting = state.is_collecting.lock().unwrap();
-    *is_collecting = !current_state;
-
-    let mut data = state.shared_data.lock().unwrap();
-    if !*is_collecting {
-        // Очищаем данные при остановке
-        data.computed_results.clear();
-        data.start_time = None;
-    } else {
-        // Сбрасываем время начала при новом сборе
-        data.start_time = None;
+impl Default for LiveLogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path:    "enlil_log.csv".to_string(),
+        }
     }
 }
 
-fn render_server_list(ui: &mut egui::Ui, state: &mut State) {
-    ui.separator();
-    ui.vertical(|ui| {
-        let is_collecting = *state.is_collecting.lock().unwrap();
-        let mut data = state.shared_data.lock().unwrap();
-        let mut to_remove = Vec::new();
-
-        render_server_list_header(ui, &mut data, is_collecting);
-        render_servers(ui, &mut data, is_collecting, &mut to_remove);
-        remove_selected_servers(&mut data, to_remove);
-    });
+impl Default for BreakerSettings {
+    fn default() -> Self {
+        Self {
+            enabled:           false,
+            failures:          3,
+            cooldown_secs:     5,
+            max_cooldown_secs: 120,
+        }
+    }
 }
 
-fn render_server_list_header(ui: &mut egui::Ui, data: &mut ServerData, is_collecting: bool) {
-    ui.horizontal(|ui| {
-        ui.heading("Серверы");
-        if !is_collecting && ui.button("+ добавить").clicked() {
-            add_new_server(data);
+impl Default for AutoStopSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ticks:   60,
         }
-    });
+    }
 }
 
-fn add_new_server(data: &mut ServerData) {
-    let len = data.servers.len() + 1;
-    data.servers.push(ServerInfo {
-        name: format!("m{}", len),
-        address: "127.0.0.1:9000".to_string(),
-        online: false,
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        Self {
+            start_stop: "F5".to_string(),
+            export:     "Ctrl+S".to_string(),
+            marker:     "F9".to_string(),
+        }
+    }
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled:      true,
+            interval_min: 5,
+            dir:          autosave::default_dir(),
+        }
+    }
+}
+
+impl Default for FeedSettings {
+    fn default() -> Self {
+        Self {
+            enabled:  false,
+            listen:   "0.0.0.0:9185".to_string(),
+            snapshot: 60,
+        }
+    }
+}
+
+// Логика сбора данных =====================================================
+
+// Куда сборщик отдаёт данные помимо GUI
+struct CollectorOutputs {
+    tail_tx: Sender<live_tail::TailBlock>,
+    metrics: metrics::Exporter,
+    feed:    feed::Feed,
+}
+
+fn start_data_collection_task(
+    data:     ServerData,
+    run:      Arc<RunControl>,
+    updates:  Sender<CollectorUpdate>,
+    commands: mpsc::UnboundedReceiver<CollectorCommand>,
+    outputs:  CollectorOutputs,
+    fetcher:  impl fetcher::Fetcher,
+) {
+    tokio::spawn(async move {
+        data_collection_loop(data, run, updates, commands, outputs, fetcher).await
+    });
+}
+
+// Сборщик владеет своей копией ServerData и рассылает изменения GUI
+async fn data_collection_loop(
+    mut data:     ServerData,
+    run:          Arc<RunControl>,
+    updates:      Sender<CollectorUpdate>,
+    mut commands: mpsc::UnboundedReceiver<CollectorCommand>,
+    outputs:      CollectorOutputs,
+    fetcher:      impl fetcher::Fetcher,
+) {
+    let CollectorOutputs { tail_tx, mut metrics, mut feed } = outputs;
+    let demo = demo::Demo::new();
+    metrics.configure(&data.metrics);
+    feed.configure(&data.feed);
+    let mut interval = time::interval(TICK_INTERVAL);
+    let mut run_state = run.subscribe();
+    // Первый опрос при запуске идёт с коротким таймаутом, чтобы статусы появились быстро
+    let mut timeout = STARTUP_PROBE_TIMEOUT;
+    let mut was_collecting = false;
+    let mut autosaver = autosave::Autosaver::default();
+    let mut live_log = live_log::LiveLog::default();
+    let mut outage = Outage::default();
+    
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            Ok(()) = run_state.changed() => {
+                let state = *run_state.borrow_and_update();
+                handle_run_state(&mut data, &updates, &run, state);
+                if state == RunState::Stopping {
+                    feed.clear();
+                    autosaver.reset();
+                    live_log.close();
+                }
+                continue;
+            }
+            Some(command) = commands.recv() => {
+                handle_command(&mut data, command);
+                sync_layout(&mut data, &updates);
+                metrics.configure(&data.metrics);
+                feed.configure(&data.feed);
+                continue;
+            }
+        }
+
+        let deadline = collector_now() + TICK_INTERVAL;
+        let responses = if data.demo {
+            fetch_all_servers(&demo, &mut data, &updates, timeout, deadline).await
+        } else {
+            fetch_all_servers(&fetcher, &mut data, &updates, timeout, deadline).await
+        };
+        let collecting = run_state.borrow().is_collecting();
+        check_auto_stop(&mut data, &mut outage, collecting, &run, &updates);
+        let processing_start = Instant::now();
+        timeout = RESPONSE_TIMEOUT;
+        let (flow, quality) = parse_responses(&data.columns, &data.servers, &responses);
+        send_live_tail(&data, &tail_tx, &flow);
+
+        let collecting = run_state.borrow().is_collecting();
+        metrics.publish(&data, &flow, collecting);
+        if collecting {
+            let after_pause = !was_collecting;
+            let update = save_computation_result(&data, collector_now(), ComputationResults { flow, quality, after_pause, ..Default::default() });
+            if let CollectorUpdate::Sample { start_time, result, .. } = &update {
+                feed.publish(&data.columns, &data.servers, *start_time, result);
+                if data.live_log.enabled {
+                    // Ошибка файла выключает журнал, но не останавливает сбор
+                    if let Err(e) = live_log.append(&data.live_log.path, &data.columns, &data.servers, result) {
+                        tracing::error!(path = %data.live_log.path, error = %e, "live log write failed, disabled");
+                        live_log.close();
+                        let error = format!("{}: {}", data.live_log.path, e);
+                        publish(&mut data, &updates, CollectorUpdate::LiveLogFailed { error });
+                    }
+                }
+            }
+            publish(&mut data, &updates, update);
+            autosaver.tick(&data, &data.autosave, collector_now());
+        }
+        was_collecting = collecting;
+        publish(&mut data, &updates, CollectorUpdate::Tick { processing: processing_start.elapsed() });
+    }
+}
+
+// Применяет обновление к своей копии и отправляет его GUI
+fn publish(data: &mut ServerData, updates: &Sender<CollectorUpdate>, update: CollectorUpdate) {
+    apply_update(data, &update);
+    let _ = updates.send(update);
+}
+
+fn apply_update(data: &mut ServerData, update: &CollectorUpdate) {
+    match update {
+        CollectorUpdate::Status { server, address, status, failure, value, error, retries, latency, at, raw, breaker } => {
+            // Пока шёл опрос, сервер могли удалить или сменить ему адрес — такой ответ отбрасывается
+            let id = *server;
+            let Some(server) = data.servers.iter_mut().find(|s| s.id == id && s.address == *address) else { return };
+            if let Some(raw) = raw {
+                server.raw_log.push(raw.clone());
+            }
+            server.status = *status;
+            server.failure = *failure;
+            server.retries = *retries;
+            server.latency = *latency;
+            server.breaker = *breaker;
+            // У производного канала нет своего опроса, его задержка всегда нулевая
+            if !server.source.is_derived() {
+                server.push_latency(*at, *latency);
+            }
+            let now = Instant::now();
+            server.display_peak = server.received_at.map_or(Duration::ZERO, |prev| now - prev);
+            server.received_at = Some(now);
+            server.health.push(server.has_good_sample());
+            if server.has_good_sample() {
+                server.last_good = Some(*at);
+            }
+            if value.is_some() {
+                server.last_value = *value;
+                server.last_seen = Some(current_timestamp());
+            }
+            // Повтор той же ошибки не сбрасывает время её появления
+            if *error != server.last_error {
+                server.error_since = error.as_ref().map(|_| current_timestamp());
+                server.last_error = error.clone();
+            }
+            if let Some(failure) = failure {
+                *data.failure_counts.entry(*failure).or_insert(0) += 1;
+            }
+        }
+        CollectorUpdate::Sample { start_time, started, result } => {
+            data.start_time = Some(*start_time);
+            data.started = Some(*started);
+            alert::check(&mut data.alerts, &data.columns, &data.servers, result.timestamp, &result.flow);
+            data.missed += result.channels.saturating_sub(result.sampled) as u64;
+            data.computed_results.push(result.clone());
+            data.unsaved = true;
+        }
+        CollectorUpdate::Cleared => {
+            data.computed_results.clear();
+            data.missed = 0;
+            data.unsaved = false;
+            data.alerts.clear();
+            data.markers.clear();
+            data.start_time = None;
+            data.started = None;
+            for server in &mut data.servers {
+                server.health = health::Health::default();
+            }
+        }
+        CollectorUpdate::Layout(columns) => {
+            data.columns = columns.clone();
+        }
+        CollectorUpdate::AutoStopped(stop) => {
+            data.auto_stopped = Some(stop.clone());
+        }
+        CollectorUpdate::LiveLogFailed { error } => {
+            data.live_log.enabled = false;
+            data.live_log_error = Some(error.clone());
+            // Выключенный журнал сохраняется в конфигурации
+            data.config_dirty = true;
+        }
+        CollectorUpdate::Tick { processing } => {
+            data.processing_time = *processing;
+        }
+    }
+}
+
+fn handle_command(data: &mut ServerData, command: CollectorCommand) {
+    match command {
+        CollectorCommand::Configure { servers, retry, live_tail, metrics, feed, autosave, live_log, auto_stop, breaker, max_connections, demo } => {
+            data.servers = servers;
+            data.retry = retry;
+            data.live_tail = live_tail;
+            data.metrics = metrics;
+            data.feed = feed;
+            data.autosave = autosave;
+            data.live_log = live_log;
+            data.auto_stop = auto_stop;
+            data.breaker = breaker;
+            data.max_connections = max_connections;
+            data.demo = demo;
+            let streaming = data.servers
+                .iter()
+                .filter(|s| s.enabled && matches!(s.source, SourceKind::Tcp { streaming: true, .. }))
+                .map(|s| s.address.as_str())
+                .collect();
+            stream::retain(&streaming);
+        }
+        CollectorCommand::Trim { before } => session::trim(data, before),
+        CollectorCommand::Markers(markers) => data.markers = markers,
+    }
+}
+
+// Реакция сборщика на смену состояния
+fn handle_run_state(
+    data:    &mut ServerData,
+    updates: &Sender<CollectorUpdate>,
+    run:     &RunControl,
+    state:   RunState,
+) {
+    if state == RunState::Stopping {
+        // Очищаем данные при остановке. Колонки удалённых серверов уходят вместе с ними
+        publish(data, updates, CollectorUpdate::Cleared);
+        publish(data, updates, CollectorUpdate::Layout(channel::layout(&data.servers)));
+        let _ = run.try_transition(RunCommand::Finish);
+    }
+}
+
+#[derive(Clone)]
+struct AutoStop {
+    // Unix-время первого тика без единого ответа, мс
+    since: u64,
+    ticks: u32,
+}
+
+// Серия тиков подряд, на которых не ответил ни один включённый сервер
+#[derive(Default)]
+struct Outage {
+    ticks: u32,
+    since: Option<u64>,
+}
+
+// Ночной прогон с упавшим коммутатором иначе писал бы часы пустых отсчётов. Пауза, а не стоп:
+// остановка сбрасывает сессию, а собранное до обрыва нужно сохранить
+fn check_auto_stop(
+    data:       &mut ServerData,
+    outage:     &mut Outage,
+    collecting: bool,
+    run:        &RunControl,
+    updates:    &Sender<CollectorUpdate>,
+) {
+    let mut enabled = data.servers.iter().filter(|s| s.enabled).peekable();
+    let all_failed = enabled.peek().is_some() && enabled.all(|s| !s.has_good_sample());
+    if !collecting || !all_failed {
+        *outage = Outage::default();
+        return;
+    }
+    outage.ticks += 1;
+    let since = *outage.since.get_or_insert_with(current_timestamp_ms);
+    if !data.auto_stop.enabled || outage.ticks < data.auto_stop.ticks {
+        return;
+    }
+
+    tracing::error!(ticks = outage.ticks, since, "no server responded, collection paused");
+    if run.try_transition(RunCommand::Pause).is_ok() {
+        publish(data, updates, CollectorUpdate::AutoStopped(AutoStop { since, ticks: outage.ticks }));
+    }
+    *outage = Outage::default();
+}
+
+// Раскладку ведёт только сборщик: GUI получает её готовой и не может разойтись с ним,
+// даже если сервер удалили в момент остановки
+fn sync_layout(data: &mut ServerData, updates: &Sender<CollectorUpdate>) {
+    // Пока отсчётов нет, раскладку можно построить заново без колонок удалённых серверов
+    let columns = if data.computed_results.is_empty() {
+        channel::layout(&data.servers)
+    } else {
+        let mut columns = data.columns.clone();
+        channel::extend_layout(&mut columns, &data.servers);
+        columns
+    };
+    if columns != data.columns {
+        publish(data, updates, CollectorUpdate::Layout(columns));
+    }
+}
+
+// Монотонные часы сборщика. Берутся у tokio: в работе это те же часы, что Instant::now,
+// а тест с остановленным временем (start_paused) проходит минуты сбора мгновенно
+fn collector_now() -> Instant {
+    time::Instant::now().into_std()
+}
+
+// Настенное время только для подписей: часы до 1970 года дают 0, а не панику
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn current_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+// Статус каждого сервера уходит в GUI сразу по приходу его ответа, не дожидаясь остальных.
+// Серверы опрашиваются параллельно, поэтому тик длится не дольше самого большого таймаута
+async fn fetch_all_servers(
+    fetcher:         &impl fetcher::Fetcher,
+    data:            &mut ServerData,
+    updates:         &Sender<CollectorUpdate>,
+    default_timeout: Duration,
+    deadline:        Instant,
+) -> Vec<Result<String, std::io::Error>> {
+    let retry = data.retry;
+    let breakers = &data.breaker;
+    // Ограничение одновременных соединений для больших стендов: остальные серверы ждут свободного места
+    let limit = (data.max_connections > 0).then(|| Semaphore::new(data.max_connections));
+    let limit = &limit;
+
+    let (mut responses, statuses): (Vec<_>, Vec<_>) = futures::future::join_all(
+        data.servers.iter().map(|server| async move {
+            // Колонки выключенного сервера остаются в отсчёте пустыми, статус не меняется
+            if !server.enabled {
+                return (Err(std::io::Error::other("Сервер выключен")), None);
+            }
+            // Пока цепь разомкнута, молчащий прибор не держит тик своим таймаутом. Статус не шлётся:
+            // в списке остаётся последняя ошибка и время до пробной попытки
+            if server.breaker.is_open(collector_now()) {
+                return (Err(breaker::open_error()), None);
+            }
+            // Производный канал соединений не открывает. Место ждём не дольше конца тика,
+            // чтобы очередь не растягивала тик сверх интервала: не дождавшийся сервер получает таймаут
+            let _permit = match limit {
+                Some(limit) if !server.source.is_derived() => match time::timeout_at(deadline.into(), limit.acquire()).await {
+                    Ok(permit) => permit.ok(),
+                    Err(_) => return (Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "Нет свободного соединения до конца тика")), None),
+                },
+                _ => None,
+            };
+            let timeout = server.response_timeout(default_timeout);
+            let started = collector_now();
+            let (resp, retries) = fetch_with_retry(fetcher, server, timeout, retry, deadline).await;
+            // Статус производного канала известен только после опроса остальных
+            if server.source.is_derived() {
+                return (resp, None);
+            }
+            // Открытое потоковое соединение без свежей строки — не отказ прибора
+            let ok = resp.as_ref().map_or_else(stream::is_no_recent_data, |_| true);
+            let breaker = server.breaker.after(ok, breakers, collector_now());
+            let status = status_update(server, &resp, retries, collector_now() - started, breaker);
+            let _ = updates.send(status.clone());
+            (resp, Some(status))
+        })
+    ).await.into_iter().unzip();
+
+    let mut statuses: Vec<_> = statuses.into_iter().flatten().collect();
+    evaluate_derived(&data.servers, &mut responses);
+    for (index, server) in data.servers.iter().enumerate().filter(|(_, s)| s.enabled && s.source.is_derived()) {
+        let status = status_update(server, &responses[index], 0, Duration::ZERO, breaker::Breaker::default());
+        let _ = updates.send(status.clone());
+        statuses.push(status);
+    }
+
+    for status in &statuses {
+        log_status_change(&data.servers, status);
+        apply_update(data, status);
+    }
+    responses
+}
+
+// В журнал попадают смены ошибки сервера, а не каждый неудачный опрос
+fn log_status_change(servers: &[ServerInfo], update: &CollectorUpdate) {
+    let CollectorUpdate::Status { server, address, error, retries, .. } = update else { return };
+    let Some(server) = servers.iter().find(|s| s.id == *server && s.address == *address) else { return };
+    if *error == server.last_error {
+        return;
+    }
+    match error {
+        Some(error) => tracing::warn!(server = %server.name, source = %server.describe(), retries, %error, "fetch failed"),
+        None => tracing::info!(server = %server.name, source = %server.describe(), "fetch recovered"),
+    }
+}
+
+// Ответ производного канала — его значение текстом, как если бы его прислал прибор.
+// Сначала собираются все опрошенные каналы, затем производные по порядку списка:
+// выражение может ссылаться на производный канал, стоящий выше
+fn evaluate_derived(servers: &[ServerInfo], responses: &mut [Result<String, std::io::Error>]) {
+    let mut values = HashMap::new();
+    for (server, resp) in servers.iter().zip(responses.iter()).filter(|(s, _)| !s.source.is_derived()) {
+        record_values(&mut values, server, resp);
+    }
+    for (server, resp) in servers.iter().zip(responses.iter_mut()) {
+        let SourceKind::Derived { expression } = &server.source else { continue };
+        if !server.enabled {
+            continue;
+        }
+        *resp = expr::parse(expression)
+            .and_then(|e| e.eval(&values))
+            .map(|value| value.to_string())
+            .map_err(std::io::Error::other);
+        record_values(&mut values, server, resp);
+    }
+}
+
+fn record_values(
+    values: &mut HashMap<String, Option<f64>>,
+    server: &ServerInfo,
+    resp:   &Result<String, std::io::Error>,
+) {
+    let parsed = match resp {
+        Ok(s) => channel::parse(s, server),
+        Err(_) => vec![None; server.channels.len()],
+    };
+    for (def, value) in server.channels.iter().zip(parsed) {
+        values.insert(channel::label(server, def), value);
+    }
+}
+
+// Повторяет запрос при кратковременном сбое, пока повтор укладывается в бюджет тика
+async fn fetch_with_retry(
+    fetcher:  &impl fetcher::Fetcher,
+    server:   &ServerInfo,
+    timeout:  Duration,
+    policy:   RetryPolicy,
+    deadline: Instant,
+) -> (Result<String, std::io::Error>, u32) {
+    let backoff = Duration::from_millis(policy.backoff_ms);
+    let mut retries = 0;
+    loop {
+        let resp = fetcher.fetch(server, timeout).await;
+        let transient = resp
+            .as_ref()
+            .err()
+            .is_some_and(|e| FetchFailure::from_io_error(e).is_transient());
+        let fits_budget = collector_now() + backoff + timeout <= deadline;
+
+        if !transient || retries >= policy.max_retries || !fits_budget {
+            return (resp, retries);
+        }
+        time::sleep(backoff).await;
+        retries += 1;
+    }
+}
+
+// Значения по колонкам сессии и их качество. Ошибка опроса, нечисловое поле или удалённый сервер
+// дают пропуск, а не ноль. Повторы берутся из статуса, уже применённого в fetch_all_servers
+fn parse_responses(
+    columns:   &[channel::Column],
+    servers:   &[ServerInfo],
+    responses: &[Result<String, std::io::Error>],
+) -> (Vec<Option<f64>>, Vec<SampleQuality>) {
+    let by_id: HashMap<u32, (&ServerInfo, &Result<String, std::io::Error>)> = servers
+        .iter()
+        .zip(responses)
+        .map(|(server, resp)| (server.id, (server, resp)))
+        .collect();
+    let parsed: HashMap<u32, Vec<Option<f64>>> = by_id
+        .iter()
+        .filter_map(|(&id, (server, resp))| Some((id, channel::parse(resp.as_ref().ok()?, server))))
+        .collect();
+
+    columns
+        .iter()
+        .map(|column| {
+            let value = parsed.get(&column.server).and_then(|values| values.get(column.channel).copied().flatten());
+            let quality = match by_id.get(&column.server) {
+                None => SampleQuality::Missing,
+                Some((server, _)) if !server.enabled => SampleQuality::Disabled,
+                Some((_, Err(e))) if breaker::is_open_error(e) => SampleQuality::Skipped,
+                Some((_, Err(e))) if FetchFailure::from_io_error(e) == FetchFailure::TimedOut => SampleQuality::Timeout,
+                Some((_, Err(_))) => SampleQuality::Error,
+                Some(_) if value.is_none() => SampleQuality::Parse,
+                Some((server, _)) if server.retries > 0 => SampleQuality::Retried,
+                Some(_) => SampleQuality::Good,
+            };
+            (value, quality)
+        })
+        .unzip()
+}
+
+impl SampleQuality {
+    // Код для файлов экспорта, в духе FetchFailure::code
+    fn code(&self) -> &'static str {
+        match self {
+            SampleQuality::Good     => "OK",
+            SampleQuality::Retried  => "RETRIED",
+            SampleQuality::Timeout  => "TIMEOUT",
+            SampleQuality::Parse    => "PARSE",
+            SampleQuality::Error    => "ERROR",
+            SampleQuality::Disabled => "DISABLED",
+            SampleQuality::Skipped  => "SKIPPED",
+            SampleQuality::Missing  => "MISSING",
+        }
+    }
+}
+
+// Часть прошивок отвечает с десятичной запятой: "12,345". Единственная запятая без точки
+// считается десятичным разделителем, "1,234,5" и "1,234.5" отклоняются как неоднозначные
+fn parse_value(response: &str) -> Option<f64> {
+    let text = response.trim();
+    if text.matches(',').count() == 1 && !text.contains('.') {
+        return text.replacen(',', ".", 1).parse().ok();
+    }
+    text.parse().ok()
+}
+
+fn status_update(
+    server:  &ServerInfo,
+    resp:    &Result<String, std::io::Error>,
+    retries: u32,
+    latency: Duration,
+    breaker: breaker::Breaker,
+) -> CollectorUpdate {
+    let values = resp.as_ref().map(|s| channel::parse(s, server)).unwrap_or_default();
+    CollectorUpdate::Status {
+        server:  server.id,
+        address: server.address.clone(),
+        status:  match resp {
+            Ok(_) => ServerStatus::Online,
+            Err(e) if stream::is_no_recent_data(e) => ServerStatus::Online,
+            Err(_) => ServerStatus::Offline,
+        },
+        failure: FetchFailure::classify(resp, &values),
+        // В боковой панели показывается первый канал
+        value:   values.first().copied().flatten(),
+        error:   resp.as_ref().err().map(|e| e.to_string()),
+        retries,
+        latency,
+        at:      collector_now(),
+        raw:     server.debug.then(|| raw_log::RawResponse::capture(resp, current_timestamp_ms())),
+        breaker,
+    }
+}
+
+// Отправляет последние значения писателю живого файла. Если писатель не успевает, блок пропускается
+fn send_live_tail(data: &ServerData, tail_tx: &Sender<live_tail::TailBlock>, flow: &[Option<f64>]) {
+    if !data.live_tail.enabled {
+        return;
+    }
+
+    // Колонки удалённых серверов в хвост не попадают
+    let channels = channel::resolved(&data.columns, &data.servers).zip(flow).filter_map(|(c, v)| c.map(|c| (c, v)));
+    let lines = channels.map(|((server, def), &value)| live_tail::TailLine {
+        name:   channel::label(server, def),
+        value:  value.filter(|_| server.has_good_sample()),
+        unit:   "-".to_string(),
+        status: server.failure.map_or("OK", |f| f.code()).to_string(),
+    }).collect();
+
+    let _ = tail_tx.try_send(live_tail::TailBlock {
+        path:      PathBuf::from(&data.live_tail.path),
+        timestamp: current_timestamp(),
+        lines,
+    });
+}
+
+// Начало сессии фиксируется один раз при первом сохранении: настенное время — для подписей,
+// монотонное — для времени отсчётов. Перевод системных часов (NTP) время отсчётов не сдвигает
+fn save_computation_result(data: &ServerData, now: Instant, result: ComputationResults) -> CollectorUpdate {
+    let (start_time, started) = match (data.start_time, data.started) {
+        (Some(start_time), Some(started)) => (start_time, started),
+        _ => (current_timestamp_ms(), now),
+    };
+
+    // Относительное время, мс. Строго возрастает, даже если два отсчёта попали в одну миллисекунду
+    let elapsed = now.saturating_duration_since(started).as_millis() as u64;
+    let timestamp = match data.computed_results.last() {
+        Some(last) => elapsed.max(last.timestamp + 1),
+        None => elapsed,
+    };
+    let new_result = ComputationResults {
+        timestamp,
+        flow:        result.flow,
+        sampled:     data.servers.iter().filter(|s| s.enabled && s.has_good_sample()).count(),
+        channels:    data.servers.iter().filter(|s| s.enabled).count(),
+        after_pause: result.after_pause && !data.computed_results.is_empty(),
+        quality:     result.quality,
+    };
+
+    CollectorUpdate::Sample { start_time, started, result: new_result }
+}
+
+async fn connect(address: &str) -> Result<TcpStream, std::io::Error> {
+    TcpStream::connect(&resolve(address).await?[..]).await
+}
+
+// Непустой список адресов для host:port
+async fn resolve(address: &str) -> Result<Vec<SocketAddr>, std::io::Error> {
+    let target = address::parse(address).map_err(|e| std::io::Error::new(ErrorKind::InvalidInput, e))?;
+    // Разрешаем имя отдельно, чтобы ошибка DNS не сливалась с ошибкой соединения
+    let addrs: Vec<_> = match target {
+        address::Target::Socket(addr) => vec![addr],
+        address::Target::Host(host, port) => net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|e| address::dns_error(e.to_string()))?
+            .collect(),
+    };
+    if addrs.is_empty() {
+        return Err(address::dns_error(format!("{} не разрешается ни в один адрес", address)));
+    }
+    Ok(addrs)
+}
+
+// Раскрывает \n, \r, \t, \\ и \xHH для строчно-ориентированных приборов.
+// Нераспознанная последовательность отправляется как есть
+fn unescape_command(command: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(command.len());
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.peek().copied() {
+            Some('n')  => { chars.next(); bytes.push(b'\n'); }
+            Some('r')  => { chars.next(); bytes.push(b'\r'); }
+            Some('t')  => { chars.next(); bytes.push(b'\t'); }
+            Some('\\') => { chars.next(); bytes.push(b'\\'); }
+            Some('x')  => {
+                let hex: String = chars.clone().skip(1).take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) if hex.len() == 2 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+                        chars.nth(2);
+                        bytes.push(byte);
+                    }
+                    _ => bytes.push(b'\\'),
+                }
+            }
+            _ => bytes.push(b'\\'),
+        }
+    }
+    bytes
+}
+
+// GUI ======================================================================
+
+async fn run_gui(
+    data:     ServerData,
+    updates:  Receiver<CollectorUpdate>,
+    commands: mpsc::UnboundedSender<CollectorCommand>,
+    run:      Arc<RunControl>,
+) -> eframe::Result {
+    let mut state = State::new(data, updates, commands, run);
+    state.recovery = autosave::find(&state.data.autosave.dir);
+    state.profiles.selected = state.data.profile.clone().unwrap_or_default();
+    refresh_profiles(&mut state.profiles);
+    eframe::run_native(
+        "Server Monitoring System",
+        eframe::NativeOptions::default(),
+        Box::new(|cc| {
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            Ok(Box::new(state))
+        }),
+    )
+}
+
+impl State {
+    // Восстановление после сбоя и список профилей читает с диска run_gui
+    fn new(
+        data:     ServerData,
+        updates:  Receiver<CollectorUpdate>,
+        commands: mpsc::UnboundedSender<CollectorCommand>,
+        run:      Arc<RunControl>,
+    ) -> Self {
+        Self {
+            data,
+            updates,
+            commands,
+            window: TimeWindow { secs: 60, show_all: false },
+            y_axis: YAxis::default(),
+            y_right: YAxis::default(),
+            smoothing: 1,
+            legend: LegendValues { enabled: true, decimals: 2, names: HashMap::new() },
+            run_state: run.subscribe(),
+            run,
+            run_error: None,
+            show_completeness: false,
+            show_latency: false,
+            crosshair: false,
+            layout: PlotLayout::default(),
+            selection: RangeSelection::default(),
+            trim_at: 0.0,
+            confirm_trim: None,
+            absolute_time: false,
+            server_drafts: ServerDrafts::default(),
+            address_checks: AddressChecks::new(),
+            export_error: None,
+            excel_export: None,
+            excel_per_server: false,
+            stale_filter: StaleFilter { enabled: false, min_age: 10, warn_after: STALE_WARNING.as_secs() },
+            server_view: ServerListView::default(),
+            fft: FftTool {
+                open: false,
+                channel: 0,
+                remove_dc: true,
+                peaks: 5,
+                result: None,
+                status: None,
+            },
+            latency_budget: LatencyBudget {
+                network_ms:    500,
+                processing_ms: 50,
+                display_ms:    2000,
+            },
+            session_path: JSON_PATH.to_string(),
+            viewing: None,
+            recovery: None,
+            close_guard: CloseGuard::default(),
+            confirm_remove: None,
+            undo: None,
+            profiles: ProfilePanel::default(),
+            marker_label: String::new(),
+        }
+    }
+}
+
+impl eframe::App for State {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        draw(ctx, self);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        sync_config_if_dirty(self);
+    }
+}
+
+// Один кадр окна. Данные сборщика только забираются из канала, кадр их не ждёт
+fn draw(ctx: &egui::Context, state: &mut State) {
+    ctx.request_repaint_after(Duration::from_secs(1));
+    while let Ok(update) = state.updates.try_recv() {
+        apply_update(live_data(state), &update);
+    }
+    sync_config_if_dirty(state);
+    poll_excel_export(ctx, state);
+    // До панелей: съеденное нажатие не дойдёт до кнопки в фокусе
+    handle_shortcuts(ctx, state);
+
+    egui::SidePanel::right("right_panel")
+        .resizable(false)
+        .default_width(200.0)
+        .show(ctx, |ui| {
+            render_side_panel(ui, state);
+        });
+
+    render_status_bar(ctx, state);
+    egui::CentralPanel::default().show(ctx, |ui| {
+        render_main_content(ui, state);
+    });
+
+    render_fft_window(ctx, state);
+    confirm_server_removal(ctx, state);
+    confirm_profile_action(ctx, state);
+    confirm_trim(ctx, state);
+    render_undo_toast(ctx, state);
+    guard_close(ctx, state);
+}
+
+// Данные, которые ведёт сборщик, независимо от того, открыта ли сохранённая сессия
+fn live_data(state: &mut State) -> &mut ServerData {
+    match &mut state.viewing {
+        Some(viewing) => &mut viewing.live,
+        None => &mut state.data,
+    }
+}
+
+// Изменённая конфигурация сразу уходит сборщику и сохраняется на диск
+fn sync_config_if_dirty(state: &mut State) {
+    if state.viewing.is_some() {
+        // Правки загруженной сессии (например, видимость линий) остаются в ней
+        state.data.config_dirty = false;
+        return;
+    }
+    let data = &mut state.data;
+    if !data.config_dirty {
+        return;
+    }
+    data.config_dirty = false;
+
+    let _ = state.commands.send(CollectorCommand::Configure {
+        servers:   data.servers.clone(),
+        retry:     data.retry,
+        live_tail: data.live_tail.clone(),
+        metrics:   data.metrics.clone(),
+        feed:      data.feed.clone(),
+        autosave:  data.autosave.clone(),
+        live_log:  data.live_log.clone(),
+        auto_stop: data.auto_stop.clone(),
+        breaker:   data.breaker.clone(),
+        max_connections: data.max_connections,
+        demo:      data.demo,
+    });
+    if let Err(e) = config::save(&data.to_config()) {
+        tracing::error!(error = %e, "config save failed");
+    }
+}
+
+// Боковая панель
+fn render_side_panel(ui: &mut egui::Ui, state: &mut State) {
+    ui.vertical_centered(|ui| ui.heading("Настройки"));
+    ui.separator();
+
+    render_plot_settings(ui, state);
+    if state.viewing.is_some() {
+        render_viewing_control(ui, state);
+    } else {
+        render_collection_control(ui, state);
+    }
+    let live = state.viewing.is_none();
+    ui.add_enabled_ui(live, |ui| {
+        render_retry_settings(ui, state);
+        render_live_tail_settings(ui, state);
+        render_metrics_settings(ui, state);
+        render_feed_settings(ui, state);
+        render_autosave_settings(ui, state);
+        render_live_log_settings(ui, state);
+        render_auto_stop_settings(ui, state);
+        render_breaker_settings(ui, state);
+        render_connection_limit(ui, state);
+        render_profiles(ui, state);
+        render_shortcut_settings(ui, state);
+    });
+    render_diagnostics(ui, state);
+    let absolute_time = state.absolute_time;
+    let viewing = state.viewing.is_some();
+    render_events(ui, live_data(state), absolute_time, viewing);
+    render_markers(ui, state);
+    ui.add_enabled_ui(live, |ui| render_server_list(ui, state));
+}
+
+fn render_plot_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.heading("Настройки графика");
+    ui.horizontal(|ui| {
+        ui.label("Окно:");
+        ui.add_enabled(
+            !state.window.show_all,
+            egui::DragValue::new(&mut state.window.secs).range(2..=86_400).suffix(" с"),
+        );
+        ui.checkbox(&mut state.window.show_all, "Всё");
+    });
+    render_y_axis_settings(ui, &mut state.y_axis, "Autoscale Y");
+    if state.data.servers.iter().flat_map(|s| &s.channels).any(|def| def.right_axis) {
+        render_y_axis_settings(ui, &mut state.y_right, "Autoscale Y (правая ось)");
+    }
+    ui.horizontal(|ui| {
+        ui.label("Сглаживание:");
+        ui.add(egui::DragValue::new(&mut state.smoothing).range(1..=100).suffix(" отсч."))
+            .on_hover_text("Скользящее среднее по последним N отсчётам, 1 — выключено");
+    });
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut state.legend.enabled, "Значения в легенде")
+            .on_hover_text("Последний отсчёт канала в видимом окне рядом с его именем");
+        ui.add_enabled(state.legend.enabled, egui::DragValue::new(&mut state.legend.decimals).range(0..=6).suffix(" зн."));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Раскладка:");
+        ui.selectable_value(&mut state.layout, PlotLayout::Combined, "общий");
+        ui.selectable_value(&mut state.layout, PlotLayout::Grid, "сетка")
+            .on_hover_text("Отдельный график на каждый видимый канал со своим масштабом Y. \
+                            Ось времени общая: перетаскивание и Ctrl+колесо сдвигают все графики, двойной щелчок возвращает окно");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Время:");
+        ui.selectable_value(&mut state.absolute_time, false, "от начала");
+        ui.selectable_value(&mut state.absolute_time, true, "по часам")
+            .on_hover_text("Местное время отсчётов по началу сбора — для сверки с журналом стенда");
+    });
+    render_range_selection(ui, &mut state.selection);
+    render_trim(ui, state);
+    ui.checkbox(&mut state.crosshair, "Перекрестие")
+        .on_hover_text("Значения всех видимых каналов в отсчёте под курсором");
+    ui.checkbox(&mut state.show_completeness, "Полнота данных");
+    ui.checkbox(&mut state.show_latency, "Задержка опроса")
+        .on_hover_text("Длительность опроса каждого сервера за последние минуты");
+    if ui.button("FFT…").clicked() {
+        state.fft.open = true;
+    }
+    render_line_visibility(ui, &mut state.data);
+}
+
+// Обрезка меняет время всех отсчётов, поэтому во время сбора недоступна: только на паузе или в открытом файле
+fn can_trim(state: &State) -> bool {
+    let run_state = *state.run_state.borrow();
+    !state.data.computed_results.is_empty()
+        && (state.viewing.is_some() || run_state == RunState::Paused)
+}
+
+fn render_trim(ui: &mut egui::Ui, state: &mut State) {
+    let enabled = can_trim(state);
+    ui.horizontal(|ui| {
+        ui.add_enabled(enabled, egui::DragValue::new(&mut state.trim_at).range(0.0..=f64::MAX).speed(1.0).suffix(" с"));
+        if ui.add_enabled(enabled, egui::Button::new("Обрезать до времени…"))
+            .on_hover_text("Удалить отсчёты раньше этого времени, сессия начнётся с нуля. \
+                            Также правым щелчком по графику. Только на паузе или в открытом файле")
+            .clicked()
+        {
+            state.confirm_trim = Some((state.trim_at * 1000.0) as u64);
+        }
+    });
+}
+
+fn confirm_trim(ctx: &egui::Context, state: &mut State) {
+    let Some(before) = state.confirm_trim else { return };
+    let mut trim = None;
+    let modal = egui::Modal::new(egui::Id::new("confirm_trim")).show(ctx, |ui| {
+        ui.heading("Обрезать сессию?");
+        // Как в session::trim: последний отсчёт остаётся всегда
+        let results = &state.data.computed_results;
+        let dropped = results.partition_point(|r| r.timestamp < before).min(results.len().saturating_sub(1));
+        ui.label(format!("Отсчёты до {} будут удалены: {} шт.", format_seconds(before, true), dropped));
+        ui.label("Время оставшихся отсчётов, событий и отметок начнётся с нуля. Отменить нельзя.");
+        ui.horizontal(|ui| {
+            if ui.button("Обрезать").clicked() {
+                trim = Some(true);
+            }
+            if ui.button("Отмена").clicked() {
+                trim = Some(false);
+            }
+        });
+    });
+    if modal.should_close() {
+        trim.get_or_insert(false);
+    }
+
+    let Some(trim) = trim else { return };
+    state.confirm_trim = None;
+    // Пока диалог был открыт, сбор могли возобновить
+    if !trim || !can_trim(state) {
+        return;
+    }
+    // Копия сборщика на паузе совпадает с копией GUI, обе обрезаются одинаково
+    if state.viewing.is_none() {
+        let _ = state.commands.send(CollectorCommand::Trim { before });
+    }
+    session::trim(&mut state.data, before);
+    state.selection.active = false;
+    state.selection.export_only = false;
+    tracing::info!(before, "session trimmed");
+}
+
+fn render_range_selection(ui: &mut egui::Ui, selection: &mut RangeSelection) {
+    ui.horizontal(|ui| {
+        ui.label("Диапазон:").on_hover_text("Также перетаскиванием по графику с зажатым Shift");
+        let mut changed = ui.add(egui::DragValue::new(&mut selection.from).range(0.0..=f64::MAX).speed(1.0).prefix("с ").suffix(" с")).changed();
+        changed |= ui.add(egui::DragValue::new(&mut selection.to).range(0.0..=f64::MAX).speed(1.0).prefix("по ").suffix(" с")).changed();
+        selection.active |= changed;
+        if selection.active && ui.small_button("✖").on_hover_text("Снять выделение").clicked() {
+            selection.active = false;
+            selection.export_only = false;
+        }
+    });
+}
+
+fn render_y_axis_settings(ui: &mut egui::Ui, y_axis: &mut YAxis, title: &str) {
+    ui.checkbox(&mut y_axis.autoscale, title);
+    ui.add_enabled_ui(!y_axis.autoscale, |ui| {
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut y_axis.min).speed(0.1).prefix("min "));
+            ui.add(egui::DragValue::new(&mut y_axis.max).speed(0.1).prefix("max "));
+        });
+    });
+    if !y_axis.autoscale && y_axis.min >= y_axis.max {
+        ui.colored_label(ui.visuals().error_fg_color, "⚠ min должен быть меньше max — ось подстраивается под данные");
+    }
+}
+
+fn render_line_visibility(ui: &mut egui::Ui, data: &mut ServerData) {
+    egui::CollapsingHeader::new("Линии").show(ui, |ui| {
+        // Цвет — по колонке, как на графике. Линию удалённого сервера скрыть нельзя: настроек уже нет
+        let labels = channel::column_labels(&data.columns, &data.servers);
+        for (i, (column, label)) in data.columns.iter().zip(labels).enumerate() {
+            let server = data.servers.iter_mut().find(|s| s.id == column.server);
+            let Some(def) = server.and_then(|s| s.channels.get_mut(column.channel)) else { continue };
+            let text = egui::RichText::new(label).color(server_color(i));
+            ui.horizontal(|ui| {
+                data.config_dirty |= ui.checkbox(&mut def.visible, text).changed();
+                data.config_dirty |= ui.add(egui::TextEdit::singleline(&mut def.unit).hint_text("ед.").desired_width(40.0))
+                    .lost_focus();
+                data.config_dirty |= ui.selectable_value(&mut def.right_axis, false, "Л")
+                    .on_hover_text("Левая ось Y")
+                    .changed();
+                data.config_dirty |= ui.selectable_value(&mut def.right_axis, true, "П")
+                    .on_hover_text("Правая ось Y со своими пределами")
+                    .changed();
+            });
+        }
+    });
+}
+
+fn render_collection_control(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    ui.heading("Управление сбором");
+    
+    let run_state = *state.run_state.borrow();
+    ui.label(format!("Состояние: {}", run_state.label()));
+
+    let commands: &[(&str, RunCommand)] = match run_state {
+        RunState::Idle       => &[("▶ Начать сбор", RunCommand::Start)],
+        RunState::Collecting => &[("⏸ Пауза", RunCommand::Pause), ("⏹ Остановить сбор", RunCommand::Stop)],
+        RunState::Paused     => &[("▶ Продолжить", RunCommand::Resume), ("⏹ Остановить сбор", RunCommand::Stop)],
+        RunState::Stopping   => &[],
+    };
+
+    let mut clicked = None;
+    ui.horizontal(|ui| {
+        let start_stop = shortcut_hint(ui.ctx(), &state.data.shortcuts.start_stop);
+        for &(text, command) in commands {
+            let mut button = ui.button(text);
+            if matches!(command, RunCommand::Start | RunCommand::Stop) {
+                if let Some(hint) = &start_stop {
+                    button = button.on_hover_text(hint);
+                }
+            }
+            if button.clicked() {
+                clicked = Some(command);
+            }
+        }
+        if run_state == RunState::Stopping {
+            ui.add_enabled(false, egui::Button::new("⏳ Остановка…"));
+        }
+    });
+    match clicked {
+        Some(RunCommand::Stop) => request_stop(state),
+        Some(command) => request_transition(state, command),
+        None => {}
+    }
+    if let Some(error) = &state.run_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    // Смена режима посреди сбора смешала бы в одной сессии настоящие и сгенерированные значения
+    let response = ui.add_enabled(run_state.is_idle(), egui::Checkbox::new(&mut state.data.demo, "Демо-режим"))
+        .on_hover_text("Синусы с шумом вместо опроса приборов; переключается при остановленном сборе");
+    state.data.config_dirty |= response.changed();
+}
+
+// Вместо управления сбором: загруженную сессию нужно закрыть, прежде чем начинать новый сбор
+fn render_viewing_control(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    ui.heading("Просмотр сессии");
+    let Some(viewing) = &mut state.viewing else { return };
+    ui.label(&viewing.path);
+
+    let mut close = false;
+    let mut start = false;
+    if viewing.confirm_discard {
+        ui.label("Закрыть загруженную сессию и начать сбор?");
+        ui.horizontal(|ui| {
+            start = ui.button("Да").clicked();
+            if ui.button("Нет").clicked() {
+                viewing.confirm_discard = false;
+            }
+        });
+    } else {
+        ui.horizontal(|ui| {
+            if ui.button("▶ Начать сбор").clicked() {
+                viewing.confirm_discard = true;
+            }
+            close = ui.button("Закрыть сессию").clicked();
+        });
+    }
+
+    if close || start {
+        close_session(state);
+    }
+    if start {
+        request_transition(state, RunCommand::Start);
+    }
+}
+
+// Старт и остановка сбора одной клавишей: руки оператора на стенде, а не на мыши.
+// Пока фокус в текстовом поле или открыт диалог, клавиши не действуют
+fn handle_shortcuts(ctx: &egui::Context, state: &mut State) {
+    let dialog_open = state.close_guard.prompt.is_some()
+        || state.confirm_remove.is_some()
+        || state.profiles.confirm.is_some()
+        || state.confirm_trim.is_some();
+    if ctx.wants_keyboard_input() || dialog_open {
+        return;
+    }
+    let pressed = |text: &str| parse_shortcut(text).is_some_and(|shortcut| ctx.input_mut(|i| i.consume_shortcut(&shortcut)));
+
+    // Загруженную сессию закрывают явно, через панель просмотра
+    if pressed(&state.data.shortcuts.start_stop) && state.viewing.is_none() {
+        let run_state = *state.run_state.borrow();
+        match run_state {
+            RunState::Idle => request_transition(state, RunCommand::Start),
+            // Случайное нажатие не должно стереть невыгруженную сессию: остановка спрашивает, как кнопка
+            RunState::Collecting | RunState::Paused => request_stop(state),
+            RunState::Stopping => {}
+        }
+    }
+    if pressed(&state.data.shortcuts.export) && state.excel_export.is_none() {
+        start_excel_export(state, AfterExport::Stay);
+    }
+    if pressed(&state.data.shortcuts.marker) {
+        add_marker(state);
+    }
+}
+
+// "Ctrl+Shift+F5" -> модификаторы и клавиша. Ctrl означает Cmd на macOS, как в egui
+fn parse_shortcut(text: &str) -> Option<egui::KeyboardShortcut> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key = egui::Key::from_name(parts.pop()?)?;
+    let mut modifiers = egui::Modifiers::NONE;
+    for part in parts {
+        modifiers = modifiers | match part.to_lowercase().as_str() {
+            "ctrl" | "cmd" => egui::Modifiers::COMMAND,
+            "shift" => egui::Modifiers::SHIFT,
+            "alt" => egui::Modifiers::ALT,
+            _ => return None,
+        };
+    }
+    Some(egui::KeyboardShortcut::new(modifiers, key))
+}
+
+fn shortcut_hint(ctx: &egui::Context, text: &str) -> Option<String> {
+    parse_shortcut(text).map(|shortcut| format!("Горячая клавиша: {}", ctx.format_shortcut(&shortcut)))
+}
+
+fn request_transition(state: &mut State, command: RunCommand) {
+    if command == RunCommand::Start {
+        let addresses = state.data.servers.iter().filter(|s| s.source.uses_address()).map(|s| s.address.as_str());
+        if let Some((address, error)) = state.address_checks.first_error(addresses) {
+            state.run_error = Some(format!("Неверный адрес {}: {} (проверяется снова)", address, error));
+            let address = address.to_string();
+            state.address_checks.recheck(&address);
+            return;
+        }
+    }
+    state.run_error = state.run.try_transition(command).err();
+}
+
+fn render_server_list(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    ui.vertical(|ui| {
+        let is_collecting = !state.run_state.borrow().is_idle();
+        let data = &mut state.data;
+        let mut to_remove = Vec::new();
+
+        render_server_list_header(ui, data);
+        render_stale_filter(ui, &mut state.stale_filter);
+        render_server_search(ui, &mut state.server_view);
+        let stale_filter = state.stale_filter.enabled.then(|| Duration::from_secs(state.stale_filter.min_age));
+        state.address_checks.poll();
+        let mut ctx = ServerListCtx {
+            drafts:        &mut state.server_drafts,
+            checks:        &mut state.address_checks,
+            is_collecting,
+            warn_after:    Duration::from_secs(state.stale_filter.warn_after),
+            latency_warn:  Duration::from_millis(state.latency_budget.network_ms),
+            time_origin:   state.absolute_time.then_some(data.start_time).flatten(),
+        };
+        render_servers(ui, data, &mut ctx, &mut state.server_view, stale_filter, &mut to_remove);
+        // Удаляется только после подтверждения, см. confirm_server_removal
+        if let Some(server) = to_remove.first().and_then(|&index| data.servers.get(index)) {
+            state.confirm_remove = Some(server.id);
+        }
+    });
+}
+
+fn render_server_list_header(ui: &mut egui::Ui, data: &mut ServerData) {
+    ui.horizontal(|ui| {
+        ui.heading("Серверы");
+        // Новый сервер получает свои колонки в конце раскладки, собранное не сдвигается
+        if ui.button("+ добавить").clicked() {
+            add_new_server(data);
+            data.config_dirty = true;
+        }
+    });
+}
+
+fn add_new_server(data: &mut ServerData) {
+    let len = data.servers.len() + 1;
+    let mut server = ServerInfo::new(&format!("m{}", len), "127.0.0.1:9000");
+    server.id = channel::next_id(&data.servers, &data.columns);
+    data.servers.push(server);
+}
+
+fn render_stale_filter(ui: &mut egui::Ui, filter: &mut StaleFilter) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut filter.enabled, "Только без данных дольше");
+        ui.add_enabled(filter.enabled, egui::DragValue::new(&mut filter.min_age).suffix(" с"));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Отсчёт устарел через");
+        ui.add(egui::DragValue::new(&mut filter.warn_after).range(1..=STALE_ERROR.as_secs()).suffix(" с"));
+    });
+}
+
+fn render_server_search(ui: &mut egui::Ui, view: &mut ServerListView) {
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut view.search).hint_text("🔍 имя или адрес").desired_width(140.0));
+        if !view.search.is_empty() && ui.small_button("✖").clicked() {
+            view.search.clear();
+        }
+        ui.checkbox(&mut view.compact, "Компактно")
+            .on_hover_text("Одна строка на сервер; полная запись открывается щелчком по имени");
+    });
+}
+
+impl ServerListView {
+    fn matches(&self, server: &ServerInfo) -> bool {
+        let search = self.search.trim().to_lowercase();
+        search.is_empty()
+            || server.name.to_lowercase().contains(&search)
+            || server.address.to_lowercase().contains(&search)
+    }
+}
+
+// Рисуются только записи в видимой части прокрутки: на стенде из сотни приборов
+// полный список каждый кадр заметно тормозит панель
+fn render_servers(
+    ui: &mut egui::Ui,
+    data: &mut ServerData,
+    ctx: &mut ServerListCtx,
+    view: &mut ServerListView,
+    stale_filter: Option<Duration>,
+    to_remove: &mut Vec<usize>,
+) {
+    // Фильтр давности скрывает серверы со свежим отсчётом
+    let fresh = |server: &ServerInfo| stale_filter.is_some_and(|min_age| server.sample_age().is_some_and(|age| age < min_age));
+    let visible: Vec<usize> = data.servers
+        .iter()
+        .enumerate()
+        .filter(|(_, server)| view.matches(server) && !fresh(server))
+        .map(|(index, _)| index)
+        .collect();
+    if visible.len() < data.servers.len() {
+        ui.label(format!("Показано {} из {}", visible.len(), data.servers.len()));
+    }
+    let changed = if view.compact {
+        render_compact_servers(ui, data, ctx, view, &visible, to_remove)
+    } else {
+        render_full_servers(ui, data, ctx, &mut view.heights, &visible, to_remove)
+    };
+    data.config_dirty |= changed;
+}
+
+// Полные записи разной высоты, поэтому вместо show_rows — show_viewport: видимая запись
+// рисуется и запоминает свою высоту, невидимая заменяется отступом
+fn render_full_servers(
+    ui: &mut egui::Ui,
+    data: &mut ServerData,
+    ctx: &mut ServerListCtx,
+    heights: &mut HashMap<u32, f32>,
+    visible: &[usize],
+    to_remove: &mut Vec<usize>,
+) -> bool {
+    let mut changed = false;
+    egui::ScrollArea::vertical().id_salt("servers").show_viewport(ui, |ui, viewport| {
+        let mut top = 0.0;
+        for &index in visible {
+            let id = data.servers[index].id;
+            let height = heights.get(&id).copied().unwrap_or(SERVER_ENTRY_HEIGHT);
+            if top + height < viewport.min.y || top > viewport.max.y {
+                ui.add_space(height);
+                top += height;
+                continue;
+            }
+            let start = ui.cursor().top();
+            ui.add_space(10.0);
+            let server = &mut data.servers[index];
+            let alerts = alert::active_for(&data.alerts, server);
+            changed |= render_server_entry(ui, server, ctx, index, &alerts, to_remove);
+            let height = ui.cursor().top() - start;
+            heights.insert(id, height);
+            top += height;
+        }
+    });
+    changed
+}
+
+// Строки одной высоты прокручиваются через show_rows. Щелчок по имени открывает
+// полную запись сервера над списком, там же правятся все его поля
+fn render_compact_servers(
+    ui: &mut egui::Ui,
+    data: &mut ServerData,
+    ctx: &mut ServerListCtx,
+    view: &mut ServerListView,
+    visible: &[usize],
+    to_remove: &mut Vec<usize>,
+) -> bool {
+    let mut changed = false;
+    if let Some(index) = view.expanded.and_then(|id| data.servers.iter().position(|s| s.id == id)) {
+        ui.add_space(10.0);
+        let server = &mut data.servers[index];
+        let alerts = alert::active_for(&data.alerts, server);
+        changed |= render_server_entry(ui, server, ctx, index, &alerts, to_remove);
+        if ui.button("Свернуть").clicked() {
+            view.expanded = None;
+        }
+        ui.separator();
+    }
+
+    let row_height = ui.spacing().interact_size.y;
+    egui::ScrollArea::vertical().id_salt("servers").show_rows(ui, row_height, visible.len(), |ui, rows| {
+        for &index in &visible[rows] {
+            let server = &mut data.servers[index];
+            let alarm = !alert::active_for(&data.alerts, server).is_empty();
+            ui.horizontal(|ui| {
+                ui.set_height(row_height);
+                changed |= ui.checkbox(&mut server.enabled, "").on_hover_text("Опрашивать сервер").changed();
+                let mut name = egui::RichText::new(&server.name).strong();
+                if alarm {
+                    name = name.color(ui.visuals().error_fg_color);
+                }
+                let open = view.expanded == Some(server.id);
+                if ui.selectable_label(open, name).on_hover_text(server.describe()).clicked() {
+                    view.expanded = (!open).then_some(server.id);
+                }
+                render_server_status(ui, server, ctx.latency_warn);
+                if ui.button("-").clicked() {
+                    to_remove.push(index);
+                }
+            });
+        }
+    });
+    changed
+}
+
+fn render_server_entry(
+    ui: &mut egui::Ui,
+    server: &mut ServerInfo,
+    ctx: &mut ServerListCtx,
+    index: usize,
+    alerts: &[&alert::AlertEvent],
+    to_remove: &mut Vec<usize>,
+) -> bool {
+    let mut changed = false;
+    let ServerListCtx { drafts, checks, is_collecting, warn_after, latency_warn, time_origin } = ctx;
+    let is_collecting = *is_collecting;
+    let server_id = server.id;
+    // Канал за порогом — рамка записи красная, пока тревога не разрешится
+    let mut frame = egui::Frame::group(ui.style());
+    if !alerts.is_empty() {
+        frame = frame.stroke(egui::Stroke::new(2.0, ui.visuals().error_fg_color));
+    }
+    frame.show(ui, |ui| {
+        // Выключенный сервер остаётся редактируемым, но приглушён
+        if !server.enabled {
+            ui.multiply_opacity(0.5);
+        }
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut server.enabled, "")
+                .on_hover_text("Опрашивать сервер. Выключенный не удаляется, его колонки в отсчётах пустые")
+                .changed();
+            ui.label("Имя:");
+            changed |= edit_server_field(ui, drafts, (server_id, ServerField::Name), &mut server.name, !is_collecting);
+        });
+        render_draft_error(ui, drafts, (server_id, ServerField::Name));
+        changed |= render_source_kind(ui, &mut server.source, index, is_collecting);
+        if server.source.uses_address() {
+            ui.horizontal(|ui| {
+                ui.label("Адрес:");
+                changed |= edit_server_field(ui, drafts, (server_id, ServerField::Address), &mut server.address, !is_collecting);
+                render_address_check(ui, checks.check(&server.address));
+            });
+            render_draft_error(ui, drafts, (server_id, ServerField::Address));
+        }
+        let fields = match &mut server.source {
+            SourceKind::Tcp { command, streaming, .. } => {
+                changed |= ui.add_enabled(!is_collecting, egui::Checkbox::new(streaming, "Постоянное соединение"))
+                    .on_hover_text("Прибор сам присылает строки, в отсчёт идёт последняя")
+                    .changed();
+                if *streaming { Vec::new() } else { vec![("Команда:", ServerField::Command, command)] }
+            }
+            SourceKind::Http { url, json_pointer } => vec![
+                ("URL:", ServerField::Url, url),
+                ("Поле JSON:", ServerField::JsonPointer, json_pointer),
+            ],
+            SourceKind::Modbus { .. } => Vec::new(),
+            SourceKind::Serial { request, terminator, .. } => vec![
+                ("Запрос:", ServerField::Request, request),
+                ("Конец строки:", ServerField::Terminator, terminator),
+            ],
+            SourceKind::Udp { local_port, trigger } => {
+                ui.horizontal(|ui| {
+                    ui.label("Локальный порт:");
+                    changed |= ui.add_enabled(!is_collecting, egui::DragValue::new(local_port)).changed();
+                });
+                vec![("Триггер:", ServerField::Request, trigger)]
+            }
+            SourceKind::Derived { expression } => vec![("Выражение:", ServerField::Expression, expression)],
+        };
+        for (label, field, value) in fields {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                changed |= edit_server_field(ui, drafts, (server_id, field), value, !is_collecting);
+            });
+            render_draft_error(ui, drafts, (server_id, field));
+        }
+        changed |= render_modbus_settings(ui, &mut server.source, index, is_collecting);
+        changed |= render_serial_settings(ui, &mut server.source, index, is_collecting);
+        changed |= render_tls_settings(ui, &mut server.source, is_collecting);
+        ui.horizontal(|ui| {
+            ui.label("Таймаут:");
+            changed |= ui.add_enabled(
+                !is_collecting,
+                egui::DragValue::new(&mut server.timeout_ms)
+                    .range(0..=60_000)
+                    .speed(10)
+                    .suffix(" мс"),
+            ).on_hover_text("0 — общий таймаут по умолчанию").changed();
+        });
+        ui.add_enabled_ui(!is_collecting, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Калибровка:");
+                changed |= ui.add(egui::DragValue::new(&mut server.scale).speed(0.01).prefix("×")).changed();
+                changed |= ui.add(egui::DragValue::new(&mut server.offset).speed(0.01).prefix("+")).changed();
+            }).response.on_hover_text("Значение × множитель + смещение, поверх преобразования каналов");
+        });
+        changed |= render_channel_editor(ui, &mut server.channels, index, is_collecting);
+        changed |= render_thresholds(ui, &mut server.channels, index);
+        ui.horizontal(|ui| {
+            render_server_status(ui, server, *latency_warn);
+            if server.retries > 0 {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("↻ {}", server.retries))
+                    .on_hover_text("Последний отсчёт получен с повторами — связь нестабильна");
+            }
+            if ui.button("-").clicked() {
+                to_remove.push(index);
+            }
+        });
+        if server.enabled {
+            render_sample_age(ui, server, *warn_after);
+        }
+        render_health(ui, &server.health);
+        changed |= render_raw_log(ui, server, index);
+        for alert in alerts {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!(
+                    "⚠ {} {}: {:.3} (порог {:.3}, с {})",
+                    alert.channel, alert.bound.label(), alert.value, alert.limit, format_time(alert.timestamp, false, *time_origin),
+                ),
+            );
+        }
+    });
+    changed
+}
+
+fn render_source_kind(ui: &mut egui::Ui, source: &mut SourceKind, index: usize, is_collecting: bool) -> bool {
+    let mut selected = source.label();
+    ui.add_enabled_ui(!is_collecting, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Протокол:");
+            egui::ComboBox::from_id_salt(("source", index))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for kind in SourceKind::KINDS {
+                        ui.selectable_value(&mut selected, kind, kind);
+                    }
+                });
+        });
+    });
+    if selected == source.label() {
+        return false;
+    }
+    *source = SourceKind::with_label(selected);
+    true
+}
+
+fn render_modbus_settings(ui: &mut egui::Ui, source: &mut SourceKind, index: usize, is_collecting: bool) -> bool {
+    let SourceKind::Modbus { unit_id, register, count, decode } = source else { return false };
+    let mut changed = false;
+    ui.add_enabled_ui(!is_collecting, |ui| {
+        ui.horizontal(|ui| {
+            changed |= ui.add(egui::DragValue::new(unit_id).prefix("unit ")).changed();
+            changed |= ui.add(egui::DragValue::new(register).prefix("рег. ")).changed();
+            changed |= ui.add(egui::DragValue::new(count).range(1..=modbus::MAX_REGISTERS).prefix("× ")).changed();
+        });
+        egui::ComboBox::from_id_salt(("decode", index))
+            .selected_text(decode.label())
+            .show_ui(ui, |ui| {
+                for option in modbus::Decode::ALL {
+                    changed |= ui.selectable_value(decode, option, option.label()).changed();
+                }
+            });
+    });
+    changed
+}
+
+fn render_serial_settings(ui: &mut egui::Ui, source: &mut SourceKind, index: usize, is_collecting: bool) -> bool {
+    let SourceKind::Serial { port, baud, .. } = source else { return false };
+    let mut changed = false;
+    ui.add_enabled_ui(!is_collecting, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Порт:");
+            // Список портов перечитывается, только пока меню открыто
+            egui::ComboBox::from_id_salt(("serial_port", index))
+                .selected_text(port.as_str())
+                .show_ui(ui, |ui| {
+                    for name in serial::available_ports() {
+                        let text = name.clone();
+                        changed |= ui.selectable_value(port, name, text).changed();
+                    }
+                });
+            changed |= ui.add(egui::DragValue::new(baud).range(300..=921_600).suffix(" бод")).changed();
+        });
+    });
+    changed
+}
+
+// Пустой путь к CA — системные корневые сертификаты
+fn render_tls_settings(ui: &mut egui::Ui, source: &mut SourceKind, is_collecting: bool) -> bool {
+    let SourceKind::Tcp { streaming: false, tls, .. } = source else { return false };
+    let mut changed = false;
+    ui.add_enabled_ui(!is_collecting, |ui| {
+        changed |= ui.checkbox(&mut tls.enabled, "TLS").changed();
+        if !tls.enabled {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label("CA:");
+            changed |= ui.add(egui::TextEdit::singleline(&mut tls.ca_path).hint_text("системные корни")).lost_focus();
+        });
+        let insecure = egui::RichText::new("Skip verification (insecure)").color(ui.visuals().warn_fg_color);
+        changed |= ui.checkbox(&mut tls.insecure, insecure)
+            .on_hover_text("Любой сертификат принимается — только для стендовых проверок")
+            .changed();
+    });
+    changed
+}
+
+// Каналы правятся только при остановленном сборе: их порядок задаёт колонки результатов
+fn render_channel_editor(ui: &mut egui::Ui, channels: &mut Vec<ChannelDef>, index: usize, is_collecting: bool) -> bool {
+    let mut changed = false;
+    egui::CollapsingHeader::new(format!("Каналы: {}", channels.len()))
+        .id_salt(("channels", index))
+        .show(ui, |ui| {
+            ui.add_enabled_ui(!is_collecting, |ui| {
+                let removable = channels.len() > 1;
+                let mut remove = None;
+                for (i, def) in channels.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        changed |= ui.add(egui::TextEdit::singleline(&mut def.name).hint_text("имя").desired_width(60.0)).lost_focus();
+                        changed |= ui.add(egui::DragValue::new(&mut def.index).prefix("поле ")).changed();
+                        changed |= ui.add(egui::DragValue::new(&mut def.scale).speed(0.01).prefix("×")).changed();
+                        changed |= ui.add(egui::DragValue::new(&mut def.offset).speed(0.01).prefix("+")).changed();
+                        if removable && ui.button("-").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    channels.remove(i);
+                    changed = true;
+                }
+                if ui.button("+ канал").clicked() {
+                    channels.push(ChannelDef { index: channels.len(), ..Default::default() });
+                    changed = true;
+                }
+            });
+        });
+    changed
+}
+
+// Пороги не меняют колонки результатов, поэтому правятся и во время сбора
+fn render_thresholds(ui: &mut egui::Ui, channels: &mut [ChannelDef], index: usize) -> bool {
+    let mut changed = false;
+    egui::CollapsingHeader::new("Пороги")
+        .id_salt(("thresholds", index))
+        .show(ui, |ui| {
+            for (i, def) in channels.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let name = if def.name.is_empty() { format!("#{}", i + 1) } else { def.name.clone() };
+                    ui.label(name);
+                    changed |= edit_limit(ui, &mut def.min, "min");
+                    changed |= edit_limit(ui, &mut def.max, "max");
+                });
+            }
+        });
+    changed
+}
+
+fn edit_limit(ui: &mut egui::Ui, limit: &mut Option<f64>, name: &str) -> bool {
+    let mut enabled = limit.is_some();
+    let mut changed = ui.checkbox(&mut enabled, name).changed();
+    if changed {
+        *limit = enabled.then_some(0.0);
+    }
+    if let Some(value) = limit {
+        changed |= ui.add(egui::DragValue::new(value).speed(0.1)).changed();
+    }
+    changed
+}
+
+// Отладка сервера: последние сырые ответы текстом и hex-дампом, с выгрузкой в файл.
+// Переключатель уходит сборщику вместе со списком серверов
+fn render_raw_log(ui: &mut egui::Ui, server: &mut ServerInfo, index: usize) -> bool {
+    let changed = ui.checkbox(&mut server.debug, "Запись сырых ответов")
+        .on_hover_text("Последние 64 ответа прибора до разбора, для поиска неисправностей")
+        .changed();
+    if !server.debug && server.raw_log.is_empty() {
+        return changed;
+    }
+    egui::CollapsingHeader::new(format!("Сырые ответы: {}", server.raw_log.len()))
+        .id_salt(("raw_log", index))
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!server.raw_log.is_empty(), egui::Button::new("Сохранить в файл")).clicked() {
+                    dump_raw_log(server);
+                }
+                if ui.button("Очистить").clicked() {
+                    server.raw_log.clear();
+                }
+            });
+            if let Some(note) = &server.raw_log.note {
+                ui.label(note);
+            }
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for entry in server.raw_log.iter() {
+                    let time = format_clock(entry.at / 1000).unwrap_or_default();
+                    ui.label(format!("{} · {} байт{}", time, entry.bytes.len(), if entry.truncated { " (обрезано)" } else { "" }));
+                    if let Some(error) = &entry.error {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    ui.monospace(entry.text());
+                    ui.monospace(entry.hex());
+                    ui.separator();
+                }
+            });
+        });
+    changed
+}
+
+fn dump_raw_log(server: &mut ServerInfo) {
+    let Some(path) = rfd::FileDialog::new()
+        .set_file_name(format!("{}_raw.txt", server.name))
+        .add_filter("Text", &["txt"])
+        .save_file()
+    else {
+        return;
+    };
+    server.raw_log.note = Some(match server.raw_log.dump(&server.name, &path) {
+        Ok(()) => format!("Сохранено в {}", path.display()),
+        Err(e) => {
+            tracing::error!(server = %server.name, path = %path.display(), error = %e, "raw log dump failed");
+            format!("Ошибка записи {}: {}", path.display(), e)
+        }
+    });
+}
+
+// Подсветка идёт по давности корректного отсчёта, даже если сокет продолжает принимать соединения
+fn render_sample_age(ui: &mut egui::Ui, server: &ServerInfo, warn_after: Duration) {
+    let Some(age) = server.sample_age() else {
+        ui.colored_label(ui.visuals().error_fg_color, "Последний отсчёт: никогда");
+        return;
+    };
+    let text = match server.last_value {
+        Some(value) => format!("Последний отсчёт: {:.2} — {} назад", value, format_age(age)),
+        None        => format!("Последний отсчёт: {} назад", format_age(age)),
+    };
+    let label = if age >= STALE_ERROR {
+        ui.colored_label(ui.visuals().error_fg_color, text)
+    } else if age >= warn_after {
+        ui.colored_label(ui.visuals().warn_fg_color, text)
+    } else {
+        ui.label(text)
+    };
+    if let Some(time) = server.last_seen.and_then(format_clock) {
+        label.on_hover_text(format!("Получен в {}", time));
+    }
+}
+
+// Полоска последних тиков (зелёный — отсчёт получен, красный — нет) и доступность за сессию
+fn render_health(ui: &mut egui::Ui, health: &health::Health) {
+    let Some(uptime) = health.uptime() else { return };
+    ui.horizontal(|ui| {
+        // Полоска ужимается под узкую панель, оставляя место для процента
+        let tick = ((ui.available_width() - 60.0) / health::DEPTH as f32).clamp(1.0, 2.0);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(tick * health::DEPTH as f32, 10.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        for (i, online) in health.recent().enumerate() {
+            let left = rect.left() + tick * i as f32;
+            let bar = egui::Rect::from_min_max(egui::pos2(left, rect.top()), egui::pos2(left + tick, rect.bottom()));
+            let color = if online { egui::Color32::from_rgb(60, 170, 80) } else { ui.visuals().error_fg_color };
+            painter.rect_filled(bar, 0.0, color);
+        }
+        ui.label(format!("{:.1}%", uptime * 100.0)).on_hover_text(format!(
+            "Доля тиков с корректным отсчётом за сессию ({} опросов); полоска — последние {} тиков",
+            health.ticks(),
+            health::DEPTH,
+        ));
+    });
+}
+
+// Unix-время в местные часы HH:MM:SS
+fn format_clock(secs: u64) -> Option<String> {
+    let time = chrono::DateTime::from_timestamp(secs as i64, 0)?;
+    Some(time.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59    => format!("{} с", secs),
+        60..=3599 => format!("{} мин", secs / 60),
+        _         => format!("{} ч", secs / 3600),
+    }
+}
+
+// Поле редактируется в черновике и применяется по Enter или при потере фокуса.
+// Возвращает true, если значение было применено
+fn edit_server_field(
+    ui: &mut egui::Ui,
+    drafts: &mut ServerDrafts,
+    key: (u32, ServerField),
+    committed: &mut String,
+    enabled: bool,
+) -> bool {
+    let mut text = drafts.texts.get(&key).cloned().unwrap_or_else(|| committed.clone());
+    let response = ui.add_enabled(enabled, egui::TextEdit::singleline(&mut text));
+    if response.changed() {
+        drafts.texts.insert(key, text);
+    }
+    response.lost_focus() && drafts.commit(key, committed)
+}
+
+fn render_address_check(ui: &mut egui::Ui, check: &address::CheckResult) {
+    match check {
+        None => {
+            ui.label("⏳").on_hover_text("Адрес проверяется…");
+        }
+        Some(Err(error)) => {
+            ui.colored_label(ui.visuals().error_fg_color, "⚠").on_hover_text(error);
+        }
+        Some(Ok(())) => {}
+    }
+}
+
+fn render_draft_error(ui: &mut egui::Ui, drafts: &ServerDrafts, key: (u32, ServerField)) {
+    if let Some(error) = drafts.errors.get(&key) {
+        ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+    }
+}
+
+impl ServerDrafts {
+    // Применяет черновик при успешной проверке, иначе откатывает к прежнему значению
+    fn commit(&mut self, key: (u32, ServerField), committed: &mut String) -> bool {
+        let Some(draft) = self.texts.remove(&key) else { return false };
+        match validate_server_field(key.1, &draft) {
+            Ok(value) => {
+                self.errors.remove(&key);
+                let changed = *committed != value;
+                *committed = value;
+                changed
+            }
+            Err(error) => {
+                self.errors.insert(key, error);
+                false
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.texts.clear();
+        self.errors.clear();
+    }
+
+    // Правки удалённого сервера
+    fn forget(&mut self, server: u32) {
+        self.texts.retain(|(id, _), _| *id != server);
+        self.errors.retain(|(id, _), _| *id != server);
+    }
+}
+
+fn validate_server_field(field: ServerField, text: &str) -> Result<String, String> {
+    let text = text.trim();
+    match field {
+        ServerField::Name if text.is_empty() => Err("Имя не может быть пустым".to_string()),
+        ServerField::Name => Ok(text.to_string()),
+        ServerField::Command if text.is_empty() => Err("Команда не может быть пустой".to_string()),
+        ServerField::Command => Ok(text.to_string()),
+        ServerField::Address => {
+            address::parse(text)?;
+            Ok(text.to_string())
+        }
+        ServerField::Url if !(text.starts_with("http://") || text.starts_with("https://")) => {
+            Err("URL должен начинаться с http:// или https://".to_string())
+        }
+        ServerField::Url => Ok(text.to_string()),
+        ServerField::JsonPointer if !text.is_empty() && !text.starts_with('/') => {
+            Err("Указатель начинается с /, например /num1".to_string())
+        }
+        ServerField::JsonPointer => Ok(text.to_string()),
+        // Пустой запрос — прибор сам выдаёт строки, достаточно слушать
+        ServerField::Request => Ok(text.to_string()),
+        ServerField::Terminator if text.is_empty() => Err("Конец строки не может быть пустым".to_string()),
+        ServerField::Terminator => Ok(text.to_string()),
+        // Имена каналов проверяются при вычислении: их можно переименовать позже
+        ServerField::Expression => {
+            expr::parse(text).map_err(|e| e.to_string())?;
+            Ok(text.to_string())
+        }
+    }
+}
+
+fn render_server_status(ui: &mut egui::Ui, server: &ServerInfo, latency_warn: Duration) {
+    if !server.enabled {
+        ui.label("⏸ Выключен");
+        return;
+    }
+    let latency = server.latency_avg();
+    let text = match (server.status, server.failure) {
+        (ServerStatus::Unchecked, _)             => "⏳ Не проверен".to_string(),
+        (ServerStatus::Online,    None)          => match latency {
+            Some(latency) => format!("✅ {} мс", latency.as_millis()),
+            None => "✅ Online".to_string(),
+        },
+        (ServerStatus::Online,    Some(failure)) => format!("⚠ {}", failure.label()),
+        (ServerStatus::Offline,   Some(failure)) => format!("❌ {}", failure.label()),
+        (ServerStatus::Offline,   None)          => "❌ Offline".to_string(),
+    };
+    if let Some(left) = server.breaker.retry_in(Instant::now()) {
+        ui.colored_label(ui.visuals().warn_fg_color, format!("⏭ пропуск, повтор через {} с", left.as_secs() + 1))
+            .on_hover_text(format!(
+                "{} ошибок подряд; пока прибор молчит, он опрашивается раз в {} с",
+                server.breaker.failures,
+                server.breaker.cooldown.as_secs(),
+            ));
+    }
+    let slow = server.failure.is_none() && latency.is_some_and(|latency| latency > latency_warn);
+    let label = if slow {
+        ui.colored_label(ui.visuals().warn_fg_color, text)
+    } else {
+        ui.label(text)
+    };
+    let mut hover = server.failure.map(|failure| failure.hint().to_string());
+    if slow {
+        hover = Some(format!("Средняя задержка опроса выше {} мс", latency_warn.as_millis()));
+    }
+    if let Some(error) = &server.last_error {
+        let since = server.error_since.and_then(format_clock).map(|t| format!(" (с {})", t)).unwrap_or_default();
+        let line = format!("{}{}", error, since);
+        hover = Some(match hover {
+            Some(hint) => format!("{}\n{}", hint, line),
+            None => line,
+        });
+    }
+    if let Some(hover) = hover {
+        label.on_hover_text(hover);
+    }
+}
+
+// Подтверждение удаления: случайный клик по «-» во время сбора не должен терять сервер
+fn confirm_server_removal(ctx: &egui::Context, state: &mut State) {
+    let Some(id) = state.confirm_remove else { return };
+    let Some(index) = state.data.servers.iter().position(|s| s.id == id) else {
+        state.confirm_remove = None;
+        return;
+    };
+
+    let server = &state.data.servers[index];
+    let mut remove = None;
+    let modal = egui::Modal::new(egui::Id::new("confirm_remove")).show(ctx, |ui| {
+        ui.heading("Удалить сервер?");
+        ui.label(format!("{} ({})", server.name, server.describe()));
+        ui.label(format!("Удаление можно отменить в течение {} с.", UNDO_TIMEOUT.as_secs()));
+        ui.horizontal(|ui| {
+            if ui.button("Удалить").clicked() {
+                remove = Some(true);
+            }
+            if ui.button("Отмена").clicked() {
+                remove = Some(false);
+            }
+        });
+    });
+    if modal.should_close() {
+        remove.get_or_insert(false);
+    }
+
+    let Some(remove) = remove else { return };
+    state.confirm_remove = None;
+    if remove {
+        let server = state.data.servers.remove(index);
+        state.server_drafts.forget(server.id);
+        state.data.config_dirty = true;
+        state.undo = Some(RemovedServer { server, index, at: Instant::now() });
+    }
+}
+
+// Плашка «Вернуть» в углу окна, пока не истёк UNDO_TIMEOUT
+fn render_undo_toast(ctx: &egui::Context, state: &mut State) {
+    let Some(undo) = &state.undo else { return };
+    let left = UNDO_TIMEOUT.saturating_sub(undo.at.elapsed());
+    if left.is_zero() {
+        state.undo = None;
+        return;
+    }
+
+    let mut restore = false;
+    egui::Area::new(egui::Id::new("undo_toast"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Сервер {} удалён", undo.server.name));
+                    restore = ui.button(format!("Вернуть ({} с)", left.as_secs() + 1)).clicked();
+                });
+            });
+        });
+    if !restore {
+        return;
+    }
+
+    let Some(mut undo) = state.undo.take() else { return };
+    // Второй копии сервера с тем же id не будет: если id успели занять, сервер возвращается
+    // под новым и пишется в новые колонки
+    if state.data.servers.iter().any(|s| s.id == undo.server.id) {
+        let id = channel::next_id(&state.data.servers, &state.data.columns);
+        tracing::info!(server = %undo.server.name, old = undo.server.id, new = id, "restored server got a new id");
+        undo.server.id = id;
+    }
+    let index = undo.index.min(state.data.servers.len());
+    state.data.servers.insert(index, undo.server);
+    state.data.config_dirty = true;
+}
+
+fn render_retry_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let mut retry = data.retry;
+    ui.horizontal(|ui| {
+        ui.label("Повторы:");
+        ui.add(egui::DragValue::new(&mut retry.max_retries).range(0..=5));
+        ui.label("через");
+        ui.add(egui::DragValue::new(&mut retry.backoff_ms).range(0..=1000).speed(10).suffix(" мс"));
+    });
+    if retry != data.retry {
+        data.retry = retry;
+        data.config_dirty = true;
+    }
+}
+
+fn render_live_tail_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let settings = &mut data.live_tail;
+    let mut changed = ui.checkbox(&mut settings.enabled, "Живой файл значений").changed();
+    ui.horizontal(|ui| {
+        ui.label("Файл:");
+        changed |= ui.text_edit_singleline(&mut settings.path).lost_focus();
+    });
+    data.config_dirty |= changed;
+}
+
+fn render_metrics_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let settings = &mut data.metrics;
+    let mut changed = ui.checkbox(&mut settings.enabled, "Метрики Prometheus")
+        .on_hover_text("GET /metrics на указанном адресе")
+        .changed();
+    ui.horizontal(|ui| {
+        ui.label("Адрес:");
+        changed |= ui.text_edit_singleline(&mut settings.listen).lost_focus();
+    });
+    data.config_dirty |= changed;
+}
+
+fn render_live_log_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let settings = &mut data.live_log;
+    let mut changed = ui.checkbox(&mut settings.enabled, "Журнал отсчётов (CSV)")
+        .on_hover_text("Каждый отсчёт сразу дописывается в файл; файл начинается заново с каждой сессией")
+        .changed();
+    ui.horizontal(|ui| {
+        ui.label("Файл:");
+        changed |= ui.text_edit_singleline(&mut settings.path).lost_focus();
+    });
+    if changed && settings.enabled {
+        data.live_log_error = None;
+    }
+    if let Some(error) = &data.live_log_error {
+        ui.colored_label(ui.visuals().error_fg_color, format!("⚠ Журнал выключен: {}", error));
+    }
+    data.config_dirty |= changed;
+}
+
+fn render_shortcut_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let mut changed = false;
+    egui::CollapsingHeader::new("Горячие клавиши").show(ui, |ui| {
+        let settings = &mut data.shortcuts;
+        let fields = [
+            ("Старт/стоп:", &mut settings.start_stop),
+            ("Экспорт Excel:", &mut settings.export),
+            ("Отметка:", &mut settings.marker),
+        ];
+        for (label, value) in fields {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                changed |= ui.add(egui::TextEdit::singleline(value).desired_width(80.0)).lost_focus();
+                if !value.is_empty() && parse_shortcut(value).is_none() {
+                    ui.colored_label(ui.visuals().error_fg_color, "⚠ не распознано");
+                }
+            });
+        }
+        ui.label("Например F5, Space, Ctrl+S, Ctrl+Shift+E");
+    });
+    data.config_dirty |= changed;
+}
+
+// Профили стенда: серверы с калибровкой и вид графика под именем.
+// Переключение — только при остановленном сборе, когда в памяти нет отсчётов сессии
+fn render_profiles(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let idle = state.run_state.borrow().is_idle();
+    let mut save = None;
+    let mut rename = None;
+    egui::CollapsingHeader::new("Профили").show(ui, |ui| {
+        let panel = &mut state.profiles;
+        if let Some(active) = &state.data.profile {
+            ui.label(format!("Текущий: {}", active));
+        }
+        let selected = if panel.selected.is_empty() { "—" } else { panel.selected.as_str() };
+        egui::ComboBox::from_id_salt("profile")
+            .selected_text(selected.to_string())
+            .show_ui(ui, |ui| {
+                for name in &panel.names {
+                    ui.selectable_value(&mut panel.selected, name.clone(), name);
+                }
+            });
+
+        let chosen = panel.names.contains(&panel.selected);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(idle && chosen, egui::Button::new("Загрузить"))
+                .on_disabled_hover_text("Профиль переключается при остановленном сборе")
+                .clicked()
+            {
+                panel.confirm = Some(ProfileAction::Switch(panel.selected.clone()));
+            }
+            if ui.add_enabled(chosen, egui::Button::new("Сохранить"))
+                .on_hover_text("Записать текущие серверы и вид графика в выбранный профиль")
+                .clicked()
+            {
+                save = Some(panel.selected.clone());
+            }
+            if ui.add_enabled(chosen, egui::Button::new("Удалить")).clicked() {
+                panel.confirm = Some(ProfileAction::Delete(panel.selected.clone()));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut panel.name).hint_text("имя").desired_width(100.0));
+            if ui.button("Сохранить как").clicked() {
+                save = Some(panel.name.clone());
+            }
+            if ui.add_enabled(chosen, egui::Button::new("Переименовать")).clicked() {
+                rename = Some((panel.selected.clone(), panel.name.clone()));
+            }
+        });
+        if let Some(status) = &panel.status {
+            ui.label(status);
+        }
+    });
+
+    if let Some(name) = save {
+        save_profile(state, name);
+    }
+    if let Some((from, to)) = rename {
+        rename_profile(state, &from, to);
+    }
+}
+
+fn refresh_profiles(panel: &mut ProfilePanel) {
+    match profile::list() {
+        Ok(names) => panel.names = names,
+        Err(e) => {
+            tracing::error!(error = %e, "profile list failed");
+            panel.status = Some(format!("Ошибка чтения профилей: {}", e));
+        }
+    }
+}
+
+fn save_profile(state: &mut State, name: String) {
+    if let Err(e) = profile::check_name(&name) {
+        state.profiles.status = Some(e);
+        return;
+    }
+    let current = profile::Profile {
+        servers:   state.data.servers.clone(),
+        window:    state.window.clone(),
+        y_axis:    state.y_axis.clone(),
+        y_right:   state.y_right.clone(),
+        smoothing: state.smoothing,
+    };
+    state.profiles.status = Some(match profile::save(&name, &current) {
+        Ok(()) => {
+            state.data.profile = Some(name.clone());
+            state.data.config_dirty = true;
+            state.profiles.selected = name.clone();
+            format!("Профиль {} сохранён", name)
+        }
+        Err(e) => {
+            tracing::error!(profile = %name, error = %e, "profile save failed");
+            format!("Ошибка записи профиля {}: {}", name, e)
+        }
+    });
+    refresh_profiles(&mut state.profiles);
+}
+
+fn rename_profile(state: &mut State, from: &str, to: String) {
+    if let Err(e) = profile::check_name(&to) {
+        state.profiles.status = Some(e);
+        return;
+    }
+    state.profiles.status = Some(match profile::rename(from, &to) {
+        Ok(()) => {
+            if state.data.profile.as_deref() == Some(from) {
+                state.data.profile = Some(to.clone());
+                state.data.config_dirty = true;
+            }
+            state.profiles.selected = to.clone();
+            format!("Профиль {} переименован в {}", from, to)
+        }
+        Err(e) => {
+            tracing::error!(profile = %from, error = %e, "profile rename failed");
+            format!("Ошибка переименования {}: {}", from, e)
+        }
+    });
+    refresh_profiles(&mut state.profiles);
+}
+
+// Переключение заменяет список серверов целиком, удаление стирает файл — оба только после подтверждения
+fn confirm_profile_action(ctx: &egui::Context, state: &mut State) {
+    let Some(action) = &state.profiles.confirm else { return };
+    let mut confirmed = None;
+    let modal = egui::Modal::new(egui::Id::new("confirm_profile")).show(ctx, |ui| {
+        match action {
+            ProfileAction::Switch(name) => {
+                ui.heading(format!("Загрузить профиль {}?", name));
+                ui.label("Текущие серверы и вид графика заменятся настройками профиля.");
+                ui.label("Несохранённые правки списка серверов пропадут.");
+            }
+            ProfileAction::Delete(name) => {
+                ui.heading(format!("Удалить профиль {}?", name));
+                ui.label("Файл профиля будет удалён.");
+            }
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Да").clicked() {
+                confirmed = Some(true);
+            }
+            if ui.button("Отмена").clicked() {
+                confirmed = Some(false);
+            }
+        });
+    });
+    if modal.should_close() {
+        confirmed.get_or_insert(false);
+    }
+
+    let Some(confirmed) = confirmed else { return };
+    let Some(action) = state.profiles.confirm.take() else { return };
+    if !confirmed {
+        return;
+    }
+    match action {
+        ProfileAction::Switch(name) => switch_profile(state, name),
+        ProfileAction::Delete(name) => {
+            state.profiles.status = Some(match profile::delete(&name) {
+                Ok(()) => format!("Профиль {} удалён", name),
+                Err(e) => {
+                    tracing::error!(profile = %name, error = %e, "profile delete failed");
+                    format!("Ошибка удаления {}: {}", name, e)
+                }
+            });
+            if state.data.profile.as_deref() == Some(name.as_str()) {
+                state.data.profile = None;
+                state.data.config_dirty = true;
+            }
+            refresh_profiles(&mut state.profiles);
+        }
+    }
+}
+
+fn switch_profile(state: &mut State, name: String) {
+    let loaded = match profile::load(&name) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            tracing::error!(profile = %name, error = %e, "profile load failed");
+            state.profiles.status = Some(format!("Ошибка чтения профиля {}: {}", name, e));
+            return;
+        }
+    };
+    let data = &mut state.data;
+    data.servers = loaded.servers;
+    for server in &mut data.servers {
+        server.migrate_legacy_command();
+    }
+    channel::assign_ids(&mut data.servers);
+    data.profile = Some(name.clone());
+    data.config_dirty = true;
+    state.window = loaded.window;
+    state.y_axis = loaded.y_axis;
+    state.y_right = loaded.y_right;
+    state.smoothing = loaded.smoothing;
+    state.server_drafts.clear();
+    state.undo = None;
+    state.confirm_remove = None;
+    state.fft.result = None;
+    tracing::info!(profile = %name, "profile loaded");
+    state.profiles.status = Some(format!("Загружен профиль {}", name));
+}
+
+fn render_auto_stop_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let settings = &mut data.auto_stop;
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut settings.enabled, "Пауза, если все молчат").changed();
+        changed |= ui.add(egui::DragValue::new(&mut settings.ticks).range(1..=86_400).suffix(" тиков")).changed();
+    }).response.on_hover_text("Сбор приостанавливается, если ни один включённый сервер не ответил столько тиков подряд");
+    data.config_dirty |= changed;
+}
+
+fn render_breaker_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let settings = &mut data.breaker;
+    let mut changed = ui.checkbox(&mut settings.enabled, "Пропускать молчащие серверы")
+        .on_hover_text("Сервер без ответа перестаёт задерживать тик: опрашивается только пробно, с растущей паузой")
+        .changed();
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("После");
+            changed |= ui.add(egui::DragValue::new(&mut settings.failures).range(1..=100)).changed();
+            ui.label("ошибок, пауза");
+            changed |= ui.add(egui::DragValue::new(&mut settings.cooldown_secs).range(1..=3600).suffix(" с")).changed();
+            ui.label("до");
+            changed |= ui.add(egui::DragValue::new(&mut settings.max_cooldown_secs).range(1..=86_400).suffix(" с")).changed();
+        });
+    });
+    data.config_dirty |= changed;
+}
+
+fn render_connection_limit(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let response = ui.horizontal(|ui| {
+        ui.label("Одновременных опросов:");
+        ui.add(egui::DragValue::new(&mut data.max_connections).range(0..=1024).custom_formatter(|n, _| {
+            if n == 0.0 { "без ограничения".to_string() } else { format!("{}", n) }
+        }))
+    }).inner.on_hover_text("Сколько серверов опрашивается одновременно. Остальные ждут очереди, но не дольше конца тика");
+    data.config_dirty |= response.changed();
+}
+
+fn render_autosave_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let settings = &mut data.autosave;
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui.checkbox(&mut settings.enabled, "Автосохранение каждые").changed();
+        changed |= ui.add(egui::DragValue::new(&mut settings.interval_min).range(1..=120).suffix(" мин")).changed();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Папка:");
+        changed |= ui.text_edit_singleline(&mut settings.dir).lost_focus();
+    });
+    data.config_dirty |= changed;
+}
+
+fn render_feed_settings(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &mut state.data;
+    let settings = &mut data.feed;
+    let mut changed = ui.checkbox(&mut settings.enabled, "WebSocket-трансляция")
+        .on_hover_text("ws://<адрес>/live, каждый отсчёт — сообщение JSON")
+        .changed();
+    ui.horizontal(|ui| {
+        ui.label("Адрес:");
+        changed |= ui.text_edit_singleline(&mut settings.listen).lost_focus();
+    });
+    ui.horizontal(|ui| {
+        ui.label("История:");
+        changed |= ui.add(egui::DragValue::new(&mut settings.snapshot).range(0..=3600).suffix(" отсч."))
+            .on_hover_text("Сколько последних отсчётов получает клиент при подключении")
+            .changed();
+    });
+    data.config_dirty |= changed;
+}
+
+// Время новой отметки: во время сбора — текущее, на паузе — последнего отсчёта.
+// Без отсчётов отметку ставить некуда
+fn marker_time(data: &ServerData, run_state: RunState) -> Option<u64> {
+    let last = data.computed_results.last().map(|r| r.timestamp);
+    match (run_state, data.started) {
+        (RunState::Collecting, Some(started)) => Some((started.elapsed().as_millis() as u64).max(last.unwrap_or(0))),
+        _ => last,
+    }
+}
+
+// Отметки ставятся только в живую сессию; список держится по возрастанию времени
+fn add_marker(state: &mut State) {
+    if state.viewing.is_some() {
+        return;
+    }
+    let run_state = *state.run_state.borrow();
+    let Some(timestamp) = marker_time(&state.data, run_state) else { return };
+    let markers = &mut state.data.markers;
+    let label = match state.marker_label.trim() {
+        "" => format!("Отметка {}", markers.len() + 1),
+        label => label.to_string(),
+    };
+    let at = markers.partition_point(|m| m.timestamp <= timestamp);
+    markers.insert(at, Marker { timestamp, label });
+    state.data.unsaved = true;
+    state.marker_label.clear();
+    sync_markers(state);
+}
+
+// Отметки живой сессии целиком уходят сборщику после каждой правки: список короткий
+fn sync_markers(state: &State) {
+    if state.viewing.is_none() {
+        let _ = state.commands.send(CollectorCommand::Markers(state.data.markers.clone()));
+    }
+}
+
+// Кнопка отметки всегда на виду, список с правкой подписей — в раскрывающемся блоке
+fn render_markers(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let run_state = *state.run_state.borrow();
+    let can_add = state.viewing.is_none() && marker_time(&state.data, run_state).is_some();
+    let mut add = false;
+    ui.horizontal(|ui| {
+        let field = ui.add(egui::TextEdit::singleline(&mut state.marker_label).hint_text("подпись отметки").desired_width(120.0));
+        add |= field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && can_add;
+        let hint = shortcut_hint(ui.ctx(), &state.data.shortcuts.marker).unwrap_or_default();
+        add |= ui.add_enabled(can_add, egui::Button::new("Отметка"))
+            .on_hover_text(format!("Отметка на текущем времени, на паузе — на последнем отсчёте. {}", hint))
+            .clicked();
+    });
+    if add {
+        add_marker(state);
+    }
+
+    let origin = state.absolute_time.then_some(state.data.start_time).flatten();
+    let data = &mut state.data;
+    let mut remove = None;
+    let mut edited = false;
+    egui::CollapsingHeader::new(format!("Отметки: {}", data.markers.len())).id_salt("markers").show(ui, |ui| {
+        for (i, marker) in data.markers.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format_time(marker.timestamp, false, origin));
+                edited |= ui.add(egui::TextEdit::singleline(&mut marker.label).desired_width(140.0)).changed();
+                if ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+    });
+    if let Some(i) = remove {
+        data.markers.remove(i);
+        edited = true;
+    }
+    if edited {
+        data.unsaved = true;
+        sync_markers(state);
+    }
+}
+
+// Журнал тревог живой сессии, новые сверху. Подтверждённые записи остаются, но выводятся серым.
+// Журнал из файла не загружается, поэтому при просмотре файла здесь тоже идущий сбор — и это подписано
+fn render_events(ui: &mut egui::Ui, data: &mut ServerData, absolute_time: bool, viewing: bool) {
+    ui.separator();
+    let origin = absolute_time.then_some(data.start_time).flatten();
+    let title = if viewing {
+        format!("События текущего сбора: {}", data.alerts.len())
+    } else {
+        format!("События: {}", data.alerts.len())
+    };
+    egui::CollapsingHeader::new(title).id_salt("events").show(ui, |ui| {
+        if viewing {
+            ui.colored_label(ui.visuals().warn_fg_color, "Открыт файл: события относятся к идущему сбору, а не к файлу");
+        }
+        if data.alerts.is_empty() {
+            ui.label("Пороги не нарушались");
+            return;
+        }
+        if alert::unacknowledged(&data.alerts) > 0 && ui.button("Ack все").clicked() {
+            data.alerts.iter_mut().for_each(|a| a.acknowledged = true);
+        }
+        egui::ScrollArea::vertical().id_salt("events_scroll").max_height(200.0).show(ui, |ui| {
+            egui::Grid::new("events_grid").striped(true).show(ui, |ui| {
+                for event in data.alerts.iter_mut().rev() {
+                    let resolved = event.resolved.map_or("активно".to_string(), |t| format!("до {}", format_time(t, true, origin)));
+                    let cells = [
+                        format_time(event.timestamp, true, origin),
+                        event.channel.clone(),
+                        format!("{:.3}", event.value),
+                        format!("{} {:.3}", event.bound.label(), event.limit),
+                        resolved,
+                    ];
+                    for cell in cells {
+                        let text = egui::RichText::new(cell);
+                        if event.acknowledged {
+                            ui.label(text.color(ui.visuals().weak_text_color()));
+                        } else if event.resolved.is_none() {
+                            ui.label(text.color(ui.visuals().error_fg_color));
+                        } else {
+                            ui.label(text);
+                        }
+                    }
+                    if !event.acknowledged && ui.small_button("Ack").clicked() {
+                        event.acknowledged = true;
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    });
+}
+
+fn render_diagnostics(ui: &mut egui::Ui, state: &mut State) {
+    ui.separator();
+    let data = &state.data;
+    egui::CollapsingHeader::new("Диагностика").show(ui, |ui| {
+        egui::Grid::new("failure_counts").show(ui, |ui| {
+            for failure in FetchFailure::ALL {
+                let count = data.failure_counts.get(&failure).copied().unwrap_or(0);
+                ui.label(failure.label()).on_hover_text(failure.hint());
+                ui.label(count.to_string());
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        render_latency_budget(ui, &state.data, &mut state.latency_budget);
+        ui.separator();
+        if ui.button("Открыть папку логов").clicked() {
+            if let Err(e) = logging::open_dir() {
+                tracing::error!(error = %e, "cannot open log directory");
+            }
+        }
+    });
+}
+
+// Свежесть значений на экране по звеньям: сеть (опрос прибора), обработка в сборщике,
+// отображение (сколько значение держалось на экране до замены). Худший случай — их сумма
+fn render_latency_budget(ui: &mut egui::Ui, data: &ServerData, budget: &mut LatencyBudget) {
+    ui.label("Бюджет задержек, мс:");
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut budget.network_ms).prefix("сеть "));
+        ui.add(egui::DragValue::new(&mut budget.processing_ms).prefix("обр. "));
+        ui.add(egui::DragValue::new(&mut budget.display_ms).prefix("экран "));
+    });
+
+    let processing = data.processing_time;
+    egui::Grid::new("latency_budget").striped(true).show(ui, |ui| {
+        ui.label("Канал");
+        ui.label("Сеть");
+        ui.label("Обр.");
+        ui.label("Экран");
+        ui.label("Итого");
+        ui.end_row();
+        for server in &data.servers {
+            let display = server.display_peak.max(server.received_at.map_or(Duration::ZERO, |at| at.elapsed()));
+            ui.label(&server.name);
+            latency_cell(ui, server.latency, budget.network_ms);
+            latency_cell(ui, processing, budget.processing_ms);
+            latency_cell(ui, display, budget.display_ms);
+            ui.label(format!("≤{:.1} с", (server.latency + processing + display).as_secs_f64()));
+            ui.end_row();
+        }
+    });
+}
+
+fn latency_cell(ui: &mut egui::Ui, value: Duration, budget_ms: u64) {
+    let text = format!("{} мс", value.as_millis());
+    if value > Duration::from_millis(budget_ms) {
+        ui.colored_label(ui.visuals().warn_fg_color, text);
+    } else {
+        ui.label(text);
+    }
+}
+
+// Главная панель
+fn render_main_content(ui: &mut egui::Ui, state: &mut State) {
+    render_header(ui, state);
+    render_auto_stop_banner(ui, state);
+    ui.separator();
+    // Полоса резервируется снизу до графика, иначе график займёт всё место
+    egui::TopBottomPanel::bottom("stats_strip").show_inside(ui, |ui| render_stats_strip(ui, state));
+    render_plot(ui, state);
+}
+
+// Оператор возвращается утром: причина паузы должна быть видна сразу, пока её не закроют
+fn render_auto_stop_banner(ui: &mut egui::Ui, state: &mut State) {
+    let data = live_data(state);
+    let Some(stop) = &data.auto_stopped else { return };
+    let since = chrono::DateTime::from_timestamp_millis(stop.since as i64)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+    let text = format!(
+        "⛔ Сбор приостановлен автоматически: ни один сервер не отвечал {} тиков подряд, начиная с {}",
+        stop.ticks, since,
+    );
+    let mut dismiss = false;
+    egui::Frame::none()
+        .fill(ui.visuals().error_fg_color)
+        .inner_margin(6.0)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(text).strong().color(egui::Color32::WHITE));
+                dismiss = ui.button("Понятно").clicked();
+            });
+        });
+    if dismiss {
+        data.auto_stopped = None;
+    }
+}
+
+fn render_header(ui: &mut egui::Ui, state: &mut State) {
+    ui.horizontal(|ui| {
+        let icon = egui::include_image!("../assets/logo_big.svg");
+        ui.add(egui::Image::new(icon).fit_to_exact_size(egui::Vec2::new(64.0, 64.0)));
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Real-time Server Monitoring");
+                if live_data(state).demo {
+                    ui.label(
+                        egui::RichText::new("ДЕМО: данные сгенерированы")
+                            .strong()
+                            .color(egui::Color32::BLACK)
+                            .background_color(ui.visuals().warn_fg_color),
+                    );
+                }
+                let unacknowledged = alert::unacknowledged(&live_data(state).alerts);
+                if unacknowledged > 0 {
+                    ui.label(
+                        egui::RichText::new(format!("⚠ {}", unacknowledged))
+                            .color(egui::Color32::WHITE)
+                            .background_color(ui.visuals().error_fg_color),
+                    ).on_hover_text("Неподтверждённые события — см. «События» в боковой панели");
+                }
+            });
+            egui::widgets::global_theme_preference_buttons(ui);
+            ui.horizontal(|ui| {
+                let idle = state.excel_export.is_none();
+                let hint = shortcut_hint(ui.ctx(), &state.data.shortcuts.export).unwrap_or_default();
+                if ui.add_enabled(idle, egui::Button::new("Save to excel")).on_hover_text(hint).clicked() {
+                    start_excel_export(state, AfterExport::Stay);
+                }
+                if ui.add_enabled(idle, egui::Button::new("Save to excel and quit")).clicked() {
+                    start_excel_export(state, AfterExport::Close);
+                }
+                ui.checkbox(&mut state.excel_per_server, "лист на сервер")
+                    .on_hover_text("Кроме общего листа Summary — по листу с каналами каждого сервера");
+                if state.selection.active {
+                    let selection = &mut state.selection;
+                    let (from, to) = (selection.from.min(selection.to), selection.from.max(selection.to));
+                    ui.selectable_value(&mut selection.export_only, false, "вся сессия");
+                    ui.selectable_value(&mut selection.export_only, true, format!("{:.0}–{:.0} с", from, to))
+                        .on_hover_text("Выгрузить только выделенный на графике диапазон");
+                }
+                if ui.button("Save as CSV").clicked() {
+                    save_csv(state);
+                }
+                if ui.button("Save as JSON").clicked() {
+                    save_json(state);
+                }
+            });
+            render_notes(ui, state);
+            render_excel_progress(ui, state);
+            render_session_open(ui, state);
+            render_recovery_offer(ui, state);
+            if let Some(error) = &state.export_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+    });
+}
+
+// Диалог сохранения открывается в каталоге прошлого экспорта с именем по времени начала сессии.
+// None — диалог закрыт без выбора, тогда ничего не пишется
+fn pick_save_path(state: &mut State, filter: &str, extension: &str) -> Option<PathBuf> {
+    let start = state.data.start_time
+        .and_then(|ms| chrono::DateTime::from_timestamp_millis(ms as i64))
+        .map(|time| time.with_timezone(&chrono::Local))
+        .unwrap_or_else(chrono::Local::now);
+    let name = format!("enlil_{}.{}", start.format("%Y-%m-%d_%H%M"), extension);
+
+    let mut dialog = rfd::FileDialog::new()
+        .set_file_name(name)
+        .add_filter(filter, &[extension]);
+    let live = live_data(state);
+    if let Some(dir) = &live.export_dir {
+        dialog = dialog.set_directory(dir);
+    }
+    let path = dialog.save_file()?;
+
+    if let Some(dir) = path.parent() {
+        live.export_dir = Some(dir.to_path_buf());
+        live.config_dirty = true;
+    }
+    Some(path)
+}
+
+// Выгрузка идёт из всей сессии или из её среза по выделенному диапазону: один срез для Excel, CSV и JSON
+fn with_export_data<R>(state: &State, write: impl FnOnce(&ServerData) -> R) -> R {
+    match state.selection.export_range() {
+        Some((from, to)) => write(&session::slice(&state.data, from, to)),
+        None => write(&state.data),
+    }
+}
+
+// Книга строится в spawn_blocking по копии данных, сбор тем временем продолжается
+fn start_excel_export(state: &mut State, then: AfterExport) {
+    let Some(path) = pick_save_path(state, "Excel", "xlsx") else { return };
+    let (session, alerts) = with_export_data(state, |data| (session::Session::from_data(data), data.alerts.clone()));
+    let data = &state.data;
+    let (progress_tx, progress_rx) = crossbeam_channel::unbounded();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let flag = cancel.clone();
+    let per_server = state.excel_per_server;
+    let target = path.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = export::save_to_excel(&session, &alerts, &target, per_server, |written, total| {
+            let _ = progress_tx.send(ExcelProgress::Rows { written, total });
+            !flag.load(Ordering::Relaxed)
+        });
+        let _ = progress_tx.send(ExcelProgress::Done(result));
+    });
+
+    state.export_error = None;
+    state.excel_export = Some(ExcelExport {
+        progress: progress_rx,
+        cancel,
+        path,
+        written: 0,
+        total: data.computed_results.len(),
+        samples: data.computed_results.len(),
+        then,
+        // Выгруженный срез не сохраняет сессию целиком
+        live: state.viewing.is_none() && state.selection.export_range().is_none(),
+    });
+}
+
+// Окно закрывается и сбор останавливается только после успешной записи файла,
+// ошибка оставляет приложение открытым, а сессию — целой
+fn poll_excel_export(ctx: &egui::Context, state: &mut State) {
+    let Some(job) = &mut state.excel_export else { return };
+    ctx.request_repaint_after(Duration::from_millis(100));
+    let mut done = None;
+    while let Ok(progress) = job.progress.try_recv() {
+        match progress {
+            ExcelProgress::Rows { written, total } => {
+                job.written = written;
+                job.total = total;
+            }
+            ExcelProgress::Done(result) => done = Some(result),
+        }
+    }
+    let Some(result) = done else { return };
+
+    let job = state.excel_export.take().expect("задача экспорта проверена выше");
+    match result {
+        Ok(()) => {
+            if job.live {
+                let live = live_data(state);
+                autosave::discard(&live.autosave.dir);
+                // Отсчёты, пришедшие во время записи, в файл не попали
+                live.unsaved = live.computed_results.len() != job.samples;
+            }
+            match job.then {
+                AfterExport::Stay => {}
+                AfterExport::Close => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+                // Пока шла запись, сбор могли остановить и без нас
+                AfterExport::Stop if !state.run_state.borrow().is_idle() => request_transition(state, RunCommand::Stop),
+                AfterExport::Stop => {}
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::Interrupted => {}
+        Err(e) => {
+            tracing::error!(path = %job.path.display(), error = %e, "Excel export failed");
+            state.export_error = Some(format!("Ошибка записи {}: {}", job.path.display(), e));
+        }
+    }
+}
+
+// Заметки относятся к показанной сессии и правятся в любой момент до выгрузки
+fn render_notes(ui: &mut egui::Ui, state: &mut State) {
+    egui::CollapsingHeader::new("Заметки к сессии").show(ui, |ui| {
+        ui.add(
+            egui::TextEdit::multiline(&mut state.data.notes)
+                .hint_text("стенд, геометрия, калибровка, условия опыта")
+                .desired_rows(3),
+        );
+    });
+}
+
+fn render_excel_progress(ui: &mut egui::Ui, state: &mut State) {
+    let Some(job) = &state.excel_export else { return };
+    ui.horizontal(|ui| {
+        let fraction = if job.total == 0 { 1.0 } else { job.written as f32 / job.total as f32 };
+        let name = job.path.file_name().unwrap_or_default().to_string_lossy();
+        let text = format!("{}: {} / {} строк", name, job.written, job.total);
+        ui.add(egui::ProgressBar::new(fraction).text(text).desired_width(240.0));
+        let cancelling = job.cancel.load(Ordering::Relaxed);
+        if ui.add_enabled(!cancelling, egui::Button::new("Отмена")).clicked() {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    });
+}
+
+fn save_csv(state: &mut State) {
+    let Some(path) = pick_save_path(state, "CSV", "csv") else { return };
+    state.export_error = with_export_data(state, |data| {
+        export::export_csv(&data.computed_results, &data.columns, &data.servers, &data.alerts, &data.markers, &path)
+    })
+        .err()
+        .map(|e| export_failed("CSV", &path, e));
+    discard_autosave_after_export(state);
+}
+
+fn save_json(state: &mut State) {
+    let Some(path) = pick_save_path(state, "JSON", "json") else { return };
+    state.export_error = with_export_data(state, |data| session::save_json(data, &path))
+        .err()
+        .map(|e| export_failed("JSON", &path, e));
+    discard_autosave_after_export(state);
+}
+
+// Пишет ошибку в журнал и возвращает текст для заголовка окна
+fn export_failed(format: &str, path: &Path, error: std::io::Error) -> String {
+    tracing::error!(format, path = %path.display(), %error, "export failed");
+    format!("Ошибка записи {}: {}", path.display(), error)
+}
+
+// Строка состояния под графиком. Каждый кадр, поэтому только из готовых счётчиков, без прохода по истории.
+// Для загруженной сессии и демо-режима не показывается: ни время сбора, ни пропуски там ничего не значат
+fn render_status_bar(ctx: &egui::Context, state: &mut State) {
+    if state.viewing.is_some() || state.data.demo {
+        return;
+    }
+    let run_state = *state.run_state.borrow();
+    let data = &state.data;
+    let samples = data.computed_results.len();
+    let elapsed = data.started.map(|started| started.elapsed());
+
+    egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label(run_state.label());
+            ui.separator();
+            ui.label(format!("Время: {}", elapsed.map_or("—".to_string(), |e| format_seconds(e.as_millis() as u64, false))));
+            ui.separator();
+            ui.label(format!("Отсчётов: {}", samples));
+            if let Some(rate) = elapsed.filter(|e| !e.is_zero()).map(|e| samples as f64 / e.as_secs_f64()) {
+                ui.label(format!("({:.2}/с)", rate));
+            }
+            ui.separator();
+            ui.label(format!("Пропусков каналов: {}", data.missed))
+                .on_hover_text("Сколько раз канал не дал корректного отсчёта за сессию");
+            ui.separator();
+            ui.label(format!("Память: ~{}", format_bytes(results_memory(data))));
+        });
+    });
+}
+
+// Оценка по размеру одного отсчёта: все отсчёты сессии имеют не больше колонок, чем раскладка
+fn results_memory(data: &ServerData) -> usize {
+    let per_result = std::mem::size_of::<ComputationResults>() + data.columns.len() * std::mem::size_of::<Option<f64>>();
+    data.computed_results.capacity() * per_result
+}
+
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["Б", "КБ", "МБ", "ГБ"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn has_unsaved(state: &State) -> bool {
+    let live = state.viewing.as_ref().map_or(&state.data, |viewing| &viewing.live);
+    live.unsaved && !live.computed_results.is_empty()
+}
+
+// Остановка очищает сессию: при невыгруженных отсчётах сначала тот же вопрос, что при закрытии окна
+fn request_stop(state: &mut State) {
+    if has_unsaved(state) {
+        state.close_guard.prompt = Some(Discarding::Stop);
+    } else {
+        request_transition(state, RunCommand::Stop);
+    }
+}
+
+// Крестик окна или остановка при невыгруженных отсчётах: Сохранить / Не сохранять / Отмена.
+// «Сохранить» идёт обычным путём Excel-экспорта с закрытием или остановкой после успешной записи
+fn guard_close(ctx: &egui::Context, state: &mut State) {
+    if ctx.input(|i| i.viewport().close_requested()) && has_unsaved(state) && !state.close_guard.confirmed {
+        ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        state.close_guard.prompt = Some(Discarding::Close);
+    }
+    let Some(action) = state.close_guard.prompt else { return };
+
+    let mut choice = None;
+    egui::Modal::new(egui::Id::new("close_guard")).show(ctx, |ui| {
+        ui.heading("Несохранённые данные");
+        let question = match action {
+            Discarding::Close => "Сохранить перед выходом?",
+            Discarding::Stop => "Остановка очистит сессию. Сохранить перед остановкой?",
+        };
+        ui.label(format!("{} отсчётов не выгружены. {}", live_data(state).computed_results.len(), question));
+        ui.horizontal(|ui| {
+            if ui.button("Сохранить").clicked() {
+                choice = Some(CloseChoice::Save);
+            }
+            if ui.button("Не сохранять").clicked() {
+                choice = Some(CloseChoice::Discard);
+            }
+            if ui.button("Отмена").clicked() {
+                choice = Some(CloseChoice::Cancel);
+            }
+        });
+    });
+
+    let Some(choice) = choice else { return };
+    state.close_guard.prompt = None;
+    match (choice, action) {
+        (CloseChoice::Save, Discarding::Close) => {
+            // Выгружается живая сессия, а не открытый для просмотра файл
+            close_session(state);
+            start_excel_export(state, AfterExport::Close);
+        }
+        (CloseChoice::Save, Discarding::Stop) => start_excel_export(state, AfterExport::Stop),
+        (CloseChoice::Discard, Discarding::Close) => {
+            state.close_guard.confirmed = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        (CloseChoice::Discard, Discarding::Stop) => request_transition(state, RunCommand::Stop),
+        (CloseChoice::Cancel, _) => {}
+    }
+}
+
+enum CloseChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+// Прошлый запуск оставил снимок, который не был выгружен: вероятно, приложение упало
+fn render_recovery_offer(ui: &mut egui::Ui, state: &mut State) {
+    let Some(path) = state.recovery.clone() else { return };
+    ui.horizontal(|ui| {
+        ui.colored_label(ui.visuals().warn_fg_color, "⚠ Найдена несохранённая сессия прошлого запуска");
+        let idle = state.run_state.borrow().is_idle();
+        if ui.add_enabled(idle, egui::Button::new("Открыть")).clicked() {
+            state.session_path = path.to_string_lossy().into_owned();
+            open_session(state);
+            state.recovery = None;
+        }
+        if ui.button("Удалить").clicked() {
+            autosave::discard(&live_data(state).autosave.dir);
+            state.recovery = None;
+        }
+    });
+}
+
+// Выгруженной живой сессии снимки больше не нужны, а её отсчёты считаются сохранёнными
+fn discard_autosave_after_export(state: &mut State) {
+    if state.export_error.is_none() && state.viewing.is_none() && state.selection.export_range().is_none() {
+        autosave::discard(&state.data.autosave.dir);
+        state.data.unsaved = false;
+    }
+}
+
+// Открыть сессию можно только без активного сбора, иначе его нечем было бы остановить
+fn render_session_open(ui: &mut egui::Ui, state: &mut State) {
+    let idle = state.run_state.borrow().is_idle();
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut state.session_path).desired_width(160.0));
+        if ui.add_enabled(idle, egui::Button::new("Открыть сессию")).clicked() {
+            open_session(state);
+        }
+    });
+}
+
+fn open_session(state: &mut State) {
+    let path = state.session_path.trim().to_string();
+    let session = match session::load(Path::new(&path)) {
+        Ok(session) => session,
+        Err(e) => {
+            state.export_error = Some(format!("Ошибка чтения {}: {}", path, e));
+            return;
+        }
+    };
+    let loaded = session.into_data();
+    match &mut state.viewing {
+        Some(viewing) => {
+            viewing.path = path;
+            viewing.confirm_discard = false;
+            state.data = loaded;
+        }
+        None => {
+            let live = std::mem::replace(&mut state.data, loaded);
+            state.viewing = Some(Viewing { path, live, confirm_discard: false });
+        }
+    }
+    state.export_error = None;
+    state.server_drafts.clear();
+    state.fft.result = None;
+    // Удалённый сервер относится к другому списку
+    state.undo = None;
+    state.confirm_remove = None;
+}
+
+fn close_session(state: &mut State) {
+    if let Some(viewing) = state.viewing.take() {
+        state.data = viewing.live;
+        state.server_drafts.clear();
+        state.fft.result = None;
+        state.undo = None;
+        state.confirm_remove = None;
+    }
+}
+
+// График
+fn render_plot(ui: &mut egui::Ui, state: &mut State) {
+    if state.show_completeness {
+        render_completeness_plot(ui, &state.data, &state.window);
+    }
+    if state.show_latency {
+        render_latency_plot(ui, &state.data);
+    }
+    if state.layout == PlotLayout::Grid {
+        render_plot_grid(ui, state);
+        return;
+    }
+
+    let data = &state.data;
+    let right_axis = axis_map(data, &state.window, &state.y_axis, &state.y_right);
+    let plot_lines = prepare_plot_lines(data, &state.window, state.smoothing, right_axis);
+    let short_window = state.window.is_short();
+    let time_origin = state.absolute_time.then_some(data.start_time).flatten();
+    let plot_id = egui::Id::new("combined_plot");
+    let labels = channel::column_labels(&data.columns, &data.servers);
+    let visible = window_results(&data.computed_results, &state.window);
+    let legend_labels = legend_labels(visible, &labels, &state.legend);
+    let hidden = hidden_legend_items(ui.ctx(), plot_id, &state.legend.names, &labels, &legend_labels);
+    state.legend.names = legend_labels.iter().cloned().zip(labels.iter().cloned()).collect();
+
+    let crosshair = state.crosshair;
+    let selection = &mut state.selection;
+    let mut y_axes = vec![AxisHints::new_y().label(axis_label(data, false))];
+    if let Some(map) = right_axis {
+        y_axes.push(
+            AxisHints::new_y()
+                .label(axis_label(data, true))
+                .placement(HPlacement::Right)
+                .formatter(move |mark, _| map.format(mark)),
+        );
+    }
+    let response = Plot::new("combined_plot")
+        .id(plot_id)
+        .legend(Legend::default().position(egui_plot::Corner::RightTop).hidden_items(hidden))
+        .allow_zoom(false).allow_scroll(false).allow_drag(false)
+        .set_margin_fraction(egui::Vec2::new(0.0, 0.0))
+        .x_axis_label("time")
+        .custom_y_axes(y_axes)
+        .x_axis_formatter(move |mark, _| format_time_mark(&mark, short_window, time_origin))
+        .x_grid_spacer(egui_plot::uniform_grid_spacer(time_grid_steps))
+        .link_axis("time_axis", [true, false])
+        // Координаты курсора в перекрестии не нужны: время и значения показывает всплывающая сводка
+        .show_x(!crosshair).show_y(!crosshair)
+        .show(ui, |plot_ui| {
+            // Set отключает автоподбор обеих осей, поэтому X сразу возвращаем в авто.
+            // С правой осью пределы левой задаются всегда: по ним переводятся значения правой
+            match right_axis.map(|map| map.left).or(state.y_axis.manual_range()) {
+                Some((min, max)) => {
+                    let mut bounds = plot_ui.plot_bounds();
+                    bounds.set_y(&egui_plot::PlotBounds::from_min_max([0.0, min], [0.0, max]));
+                    plot_ui.set_plot_bounds(bounds);
+                    plot_ui.set_auto_bounds([true, false].into());
+                }
+                None => plot_ui.set_auto_bounds(true.into()),
+            }
+            for ((lines, label), base) in plot_lines.into_iter().zip(&legend_labels).zip(&labels) {
+                for line in lines.raw {
+                    plot_ui.line(line.name(label));
+                }
+                let avg_label = format!("{} (avg)", base);
+                for line in lines.avg {
+                    plot_ui.line(line.name(&avg_label));
+                }
+                // Кольца поверх линии — отсчёты с повторами, в легенде общая запись с каналом
+                if let Some(points) = lines.degraded {
+                    plot_ui.points(points.name(label));
+                }
+            }
+            // Пороги рисуются цветом своего канала; у скрытых каналов порогов на графике нет
+            let channels = channel::resolved(&data.columns, &data.servers).enumerate();
+            for (i, (_, def)) in channels.filter_map(|(i, c)| c.map(|c| (i, c))).filter(|(_, (_, def))| def.visible) {
+                for limit in [def.min, def.max].into_iter().flatten() {
+                    let limit = match right_axis {
+                        Some(map) if def.right_axis => map.to_plot(limit),
+                        _ => limit,
+                    };
+                    plot_ui.hline(HLine::new(limit).color(server_color(i)).style(LineStyle::dashed_loose()));
+                }
+            }
+            // Shift + перетаскивание выделяет диапазон; ось X при этом не двигается, перетаскивание графика выключено
+            let shift = plot_ui.ctx().input(|i| i.modifiers.shift);
+            let pointer_x = plot_ui.pointer_coordinate().map(|p| p.x.max(0.0));
+            if shift && plot_ui.response().drag_started() {
+                selection.drag_from = pointer_x;
+            }
+            if let (Some(from), Some(to)) = (selection.drag_from, pointer_x) {
+                if plot_ui.response().dragged() {
+                    *selection = RangeSelection { active: true, from: from.min(to), to: from.max(to), ..*selection };
+                }
+            }
+            if plot_ui.response().drag_stopped() {
+                selection.drag_from = None;
+            }
+            if selection.active {
+                let bounds = plot_ui.plot_bounds();
+                let (bottom, top) = (bounds.min()[1], bounds.max()[1]);
+                let (from, to) = (selection.from.min(selection.to), selection.from.max(selection.to));
+                let fill = plot_ui.ctx().style().visuals.selection.bg_fill.gamma_multiply(0.25);
+                plot_ui.polygon(
+                    Polygon::new(PlotPoints::from(vec![[from, bottom], [to, bottom], [to, top], [from, top]]))
+                        .fill_color(fill)
+                        .stroke(egui::Stroke::NONE),
+                );
+            }
+            // Отметки оператора — вертикальные линии с подписью у верхнего края
+            let top = plot_ui.plot_bounds().max()[1];
+            let from = visible.first().map_or(u64::MAX, |r| r.timestamp);
+            for marker in data.markers.iter().filter(|m| m.timestamp >= from) {
+                let x = marker.timestamp as f64 / 1000.0;
+                let color = plot_ui.ctx().style().visuals.text_color();
+                plot_ui.vline(VLine::new(x).color(color).style(LineStyle::dashed_loose()));
+                plot_ui.text(
+                    Text::new(PlotPoint::new(x, top), format!(" {}", marker.label))
+                        .anchor(egui::Align2::LEFT_TOP)
+                        .color(color),
+                );
+            }
+            // Курсор прилипает к ближайшему отсчёту окна
+            let pointer = plot_ui.pointer_coordinate().filter(|_| crosshair)?;
+            let sample = nearest_sample(visible, (pointer.x * 1000.0).max(0.0) as u64)?;
+            let color = plot_ui.ctx().style().visuals.weak_text_color();
+            plot_ui.vline(VLine::new(plot_x(sample)).color(color).style(LineStyle::dashed_dense()));
+            Some(sample)
+        });
+    // Правый щелчок запоминает время под курсором для пункта «Обрезать до курсора»
+    if response.response.secondary_clicked() {
+        if let Some(x) = response.response.interact_pointer_pos().map(|pos| response.transform.value_from_position(pos).x) {
+            state.trim_at = x.max(0.0);
+        }
+    }
+    let trim_at = state.trim_at;
+    let enabled = can_trim(state);
+    let mut trim = false;
+    let plot = match response.inner {
+        Some(sample) => response.response.on_hover_ui_at_pointer(|ui| {
+            render_crosshair_readout(ui, &state.data, &labels, sample, time_origin.is_some())
+        }),
+        None => response.response,
+    };
+    plot.context_menu(|ui| {
+        let text = format!("Обрезать до {}…", format_seconds((trim_at * 1000.0) as u64, false));
+        if ui.add_enabled(enabled, egui::Button::new(text)).clicked() {
+            trim = true;
+            ui.close_menu();
+        }
+    });
+    if trim {
+        state.confirm_trim = Some((trim_at * 1000.0) as u64);
+    }
+}
+
+// Наименьшая высота графика в сетке; не помещающиеся ряды прокручиваются
+const GRID_PLOT_MIN_HEIGHT: f32 = 140.0;
+
+// Сетка малых графиков по одному на видимый канал, линии — те же, что у общего графика.
+// Ось времени связана с общим графиком и полосой полноты и, в отличие от них, двигается:
+// после сдвига окно перестаёт следовать за новыми отсчётами до двойного щелчка. Y у каждого свой
+fn render_plot_grid(ui: &mut egui::Ui, state: &State) {
+    let data = &state.data;
+    let short_window = state.window.is_short();
+    let time_origin = state.absolute_time.then_some(data.start_time).flatten();
+    let labels = channel::column_labels(&data.columns, &data.servers);
+    let visible = window_results(&data.computed_results, &state.window);
+    let legend_labels = legend_labels(visible, &labels, &state.legend);
+    let plot_lines = prepare_plot_lines(data, &state.window, state.smoothing, None);
+    let from = visible.first().map_or(u64::MAX, |r| r.timestamp);
+
+    let channels: Vec<_> = channel::resolved(&data.columns, &data.servers)
+        .zip(plot_lines)
+        .enumerate()
+        .filter(|(_, (channel, _))| channel.is_none_or(|(_, def)| def.visible))
+        .map(|(i, (channel, lines))| (i, channel.map(|(_, def)| def), lines))
+        .collect();
+    if channels.is_empty() {
+        ui.label("Нет видимых каналов");
+        return;
+    }
+
+    let columns = (channels.len() as f32).sqrt().ceil() as usize;
+    let rows = channels.len().div_ceil(columns);
+    let spacing = ui.spacing().item_spacing;
+    let available = ui.available_size();
+    let width = (available.x - spacing.x * (columns - 1) as f32) / columns as f32;
+    let height = ((available.y - spacing.y * (rows - 1) as f32) / rows as f32).max(GRID_PLOT_MIN_HEIGHT);
+    // Над каждым графиком подпись канала вместо легенды: щелчок по единственной записи лишь прятал бы линию
+    let title = ui.text_style_height(&egui::TextStyle::Body) + spacing.y;
+
+    let mut channels = channels.into_iter();
+    egui::ScrollArea::vertical().id_salt("plot_grid_scroll").show(ui, |ui| {
+        for _ in 0..rows {
+            ui.horizontal(|ui| {
+                for (i, def, lines) in channels.by_ref().take(columns) {
+                    ui.vertical(|ui| {
+                        ui.colored_label(server_color(i), &legend_labels[i]);
+                        Plot::new(("grid_plot", i))
+                            .width(width)
+                            .height(height - title)
+                            .allow_zoom([true, false]).allow_drag([true, false]).allow_scroll(false).allow_boxed_zoom(false)
+                            .set_margin_fraction(egui::Vec2::new(0.0, 0.05))
+                            .y_axis_min_width(48.0)
+                            .x_axis_formatter(move |mark, _| format_time_mark(&mark, short_window, time_origin))
+                            .x_grid_spacer(egui_plot::uniform_grid_spacer(time_grid_steps))
+                            .link_axis("time_axis", [true, false])
+                            .link_cursor("time_axis", [true, false].into())
+                            .show(ui, |plot_ui| {
+                                for line in lines.raw.into_iter().chain(lines.avg) {
+                                    plot_ui.line(line);
+                                }
+                                if let Some(points) = lines.degraded {
+                                    plot_ui.points(points);
+                                }
+                                for limit in def.into_iter().flat_map(|def| [def.min, def.max]).flatten() {
+                                    plot_ui.hline(HLine::new(limit).color(server_color(i)).style(LineStyle::dashed_loose()));
+                                }
+                                let color = plot_ui.ctx().style().visuals.text_color();
+                                for marker in data.markers.iter().filter(|m| m.timestamp >= from) {
+                                    plot_ui.vline(VLine::new(marker.timestamp as f64 / 1000.0).color(color).style(LineStyle::dashed_loose()));
+                                }
+                            });
+                    });
+                }
+            });
+        }
+    });
+}
+
+// Отсчёт окна, ближайший к моменту ms: двоичный поиск по времени, отсчёты идут по возрастанию
+fn nearest_sample(results: &[ComputationResults], ms: u64) -> Option<&ComputationResults> {
+    let next = results.partition_point(|r| r.timestamp < ms);
+    let before = next.checked_sub(1).and_then(|i| results.get(i));
+    match (before, results.get(next)) {
+        (Some(a), Some(b)) => Some(if ms - a.timestamp <= b.timestamp - ms { a } else { b }),
+        (a, b) => a.or(b),
+    }
+}
+
+// Сводка перекрестия: время отсчёта в выбранном режиме, в скобках — в другом,
+// затем видимые каналы с тремя знаками, как в статистике окна
+fn render_crosshair_readout(ui: &mut egui::Ui, data: &ServerData, labels: &[String], sample: &ComputationResults, absolute: bool) {
+    let relative = format_seconds(sample.timestamp, true);
+    let heading = match data.start_time.map(|start| format_time(sample.timestamp, true, Some(start))) {
+        Some(clock) if absolute => format!("{} ({})", clock, relative),
+        Some(clock) => format!("{} ({})", relative, clock),
+        None => relative,
+    };
+    ui.strong(heading);
+    egui::Grid::new("crosshair_grid").show(ui, |ui| {
+        let channels = channel::resolved(&data.columns, &data.servers).zip(labels).enumerate();
+        for (i, (_, label)) in channels.filter(|(_, (channel, _))| channel.is_none_or(|(_, def)| def.visible)) {
+            ui.colored_label(server_color(i), label);
+            match sample.flow.get(i).copied().flatten() {
+                Some(value) => ui.label(format!("{:.3}", value)),
+                None => ui.label("—"),
+            };
+            ui.end_row();
+        }
+    });
+}
+
+// Без отсчётов в окне или с выключенными значениями подпись — просто имя канала
+fn legend_labels(results: &[ComputationResults], labels: &[String], legend: &LegendValues) -> Vec<String> {
+    if !legend.enabled {
+        return labels.to_vec();
+    }
+    labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let latest = results.iter().rev().find_map(|r| r.flow.get(i).copied().flatten().filter(|v| !v.is_nan()));
+            match latest {
+                Some(value) => format!("{} — {:.*}", label, legend.decimals, value),
+                None => label.clone(),
+            }
+        })
+        .collect()
+}
+
+// Скрытые щелчком по легенде линии прошлого кадра под подписями этого кадра.
+// Подписи без значения (среднее, старые имена) переносятся как есть
+fn hidden_legend_items(
+    ctx:      &egui::Context,
+    plot_id:  egui::Id,
+    names:    &HashMap<String, String>,
+    labels:   &[String],
+    current:  &[String],
+) -> Vec<String> {
+    let Some(memory) = egui_plot::PlotMemory::load(ctx, plot_id) else { return Vec::new() };
+    memory.hidden_items
+        .iter()
+        .map(|name| names.get(name).unwrap_or(name))
+        .map(|base| match labels.iter().position(|label| label == base) {
+            Some(i) => current[i].clone(),
+            None => base.clone(),
+        })
+        .collect()
+}
+
+// Сводка по видимому окну: те же отсчёты, что рисует prepare_plot_lines
+struct ChannelStats {
+    label: String,
+    count: usize,
+    min:   f64,
+    max:   f64,
+    mean:  f64,
+    // Выборочное СКО, 0 при одном отсчёте
+    std:   f64,
+}
+
+fn window_stats(data: &ServerData, window: &TimeWindow) -> Vec<ChannelStats> {
+    let visible = window_results(&data.computed_results, window);
+    let labels = channel::column_labels(&data.columns, &data.servers);
+    channel::resolved(&data.columns, &data.servers)
+        .zip(labels)
+        .enumerate()
+        .filter(|(_, (channel, _))| channel.is_none_or(|(_, def)| def.visible))
+        .filter_map(|(i, (_, label))| {
+            // Пропуски и NaN не участвуют в сводке
+            let values: Vec<f64> = visible
+                .iter()
+                .filter_map(|r| r.flow.get(i).copied().flatten())
+                .filter(|v| !v.is_nan())
+                .collect();
+            if values.is_empty() {
+                return None;
+            }
+            let count = values.len();
+            let mean = values.iter().sum::<f64>() / count as f64;
+            let variance = if count > 1 {
+                values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64
+            } else {
+                0.0
+            };
+            Some(ChannelStats {
+                label,
+                count,
+                min:   values.iter().copied().fold(f64::INFINITY, f64::min),
+                max:   values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                mean,
+                std:   variance.sqrt(),
+            })
+        })
+        .collect()
+}
+
+const STATS_HEADER: [&str; 6] = ["Канал", "min", "max", "среднее", "СКО", "N"];
+
+fn stats_cells(stats: &ChannelStats) -> [String; 6] {
+    [
+        stats.label.clone(),
+        format!("{:.3}", stats.min),
+        format!("{:.3}", stats.max),
+        format!("{:.3}", stats.mean),
+        format!("{:.3}", stats.std),
+        stats.count.to_string(),
+    ]
+}
+
+fn render_stats_strip(ui: &mut egui::Ui, state: &State) {
+    let stats = window_stats(&state.data, &state.window);
+    ui.horizontal(|ui| {
+        ui.label("Статистика окна");
+        if ui.add_enabled(!stats.is_empty(), egui::Button::new("Копировать TSV")).clicked() {
+            let rows = std::iter::once(STATS_HEADER.join("\t"))
+                .chain(stats.iter().map(|s| stats_cells(s).join("\t")));
+            ui.ctx().copy_text(rows.collect::<Vec<_>>().join("\n"));
+        }
+    });
+    if stats.is_empty() {
+        ui.label("Нет отсчётов в окне");
+        return;
+    }
+    egui::ScrollArea::vertical().id_salt("stats_scroll").max_height(120.0).show(ui, |ui| {
+        egui::Grid::new("stats_grid").striped(true).show(ui, |ui| {
+            for title in STATS_HEADER {
+                ui.strong(title);
+            }
+            ui.end_row();
+            for channel_stats in &stats {
+                for cell in stats_cells(channel_stats) {
+                    ui.label(cell);
+                }
+                ui.end_row();
+            }
+        });
     });
 }
 
-fn render_servers(
-    ui: &mut egui::Ui,
-    data: &mut ServerData,
-    is_collecting: bool,
-    to_remove: &mut Vec<usize>,
-) {
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        for (index, server) in data.servers.iter_mut().enumerate() {
-            ui.add_space(10.0);
-            render_server_entry(ui, server, is_collecting, index, to_remove);
+// Полоса полноты данных над основным графиком: опрошено / всего каналов на каждом тике
+fn render_completeness_plot(ui: &mut egui::Ui, data: &ServerData, window: &TimeWindow) {
+    let visible = window_results(&data.computed_results, window);
+
+    let sampled: PlotPoints = visible.iter().map(|r| [plot_x(r), r.sampled as f64]).collect();
+    let channels: PlotPoints = visible.iter().map(|r| [plot_x(r), r.channels as f64]).collect();
+    let max_channels = visible.iter().map(|r| r.channels).max().unwrap_or(0);
+
+    Plot::new("completeness_plot")
+        .height(60.0)
+        .allow_zoom(false).allow_scroll(false).allow_drag(false)
+        .set_margin_fraction(egui::Vec2::new(0.0, 0.1))
+        .include_y(0.0)
+        .include_y(max_channels as f64)
+        .show_axes([false, true])
+        .y_axis_position(HPlacement::Right)
+        .y_axis_formatter(|value, _| format!("{}", value.value as usize))
+        .link_axis("time_axis", [true, false])
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(sampled).name("Опрошено").fill(0.0));
+            plot_ui.line(Line::new(channels).name("Всего").style(LineStyle::dashed_dense()));
+        });
+}
+
+// Задержка опроса по серверам. Ведётся и без сбора, поэтому ось — секунды до текущего момента,
+// а не время сессии
+fn render_latency_plot(ui: &mut egui::Ui, data: &ServerData) {
+    let now = Instant::now();
+    Plot::new("latency_plot")
+        .height(100.0)
+        .legend(Legend::default().position(egui_plot::Corner::LeftTop))
+        .allow_zoom(false).allow_scroll(false).allow_drag(false)
+        .include_y(0.0)
+        .y_axis_position(HPlacement::Right)
+        .y_axis_formatter(|mark, _| format!("{} мс", mark.value))
+        .x_axis_formatter(|mark, _| format!("{} с", mark.value))
+        .show(ui, |plot_ui| {
+            for (i, server) in data.servers.iter().enumerate().filter(|(_, s)| s.enabled) {
+                let points: PlotPoints = server.latency_history
+                    .iter()
+                    .map(|(at, latency)| [-(now - *at).as_secs_f64(), latency.as_secs_f64() * 1000.0])
+                    .collect();
+                plot_ui.line(Line::new(points).name(&server.name).color(server_color(i)));
+            }
+        });
+}
+
+// Спектр ===================================================================
+
+fn render_fft_window(ctx: &egui::Context, state: &mut State) {
+    let mut open = state.fft.open;
+    egui::Window::new("FFT").open(&mut open).default_width(500.0).show(ctx, |ui| {
+        render_fft_controls(ui, state);
+        ui.separator();
+        if render_fft_result(ui, &mut state.fft) {
+            save_spectrum(state);
         }
     });
+    state.fft.open = open;
 }
 
-fn render_server_entry(
-    ui: &mut egui::Ui,
-    server: &mut ServerInfo,
-    is_collecting: bool,
-    index: usize,
-    to_remove: &mut Vec<usize>,
-) {
-    ui.group(|ui| {
-        ui.horizontal(|ui| {
-            ui.label("Имя:");
-            ui.add_enabled(!is_collecting, egui::TextEdit::singleline(&mut server.name));
-        });
-        ui.horizontal(|ui| {
-            ui.label("Адрес:");
-            ui.add_enabled(!is_collecting, egui::TextEdit::singleline(&mut server.address));
-        });
-        ui.horizontal(|ui| {
-            ui.label(if server.online { "✅ Online" } else { "❌ Offline" });
-            if !is_collecting && ui.button("-").clicked() {
-                to_remove.push(index);
+fn render_fft_controls(ui: &mut egui::Ui, state: &mut State) {
+    let data = &state.data;
+    let tool = &mut state.fft;
+
+    let labels = channel::column_labels(&data.columns, &data.servers);
+    let selected = labels.get(tool.channel).map_or("—", |label| label.as_str());
+    egui::ComboBox::from_label("Канал")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (index, label) in labels.iter().enumerate() {
+                ui.selectable_value(&mut tool.channel, index, label);
             }
         });
+    ui.checkbox(&mut tool.remove_dc, "Убрать постоянную составляющую");
+    ui.horizontal(|ui| {
+        ui.label("Пиков:");
+        ui.add(egui::DragValue::new(&mut tool.peaks).range(1..=20));
     });
-}
 
-fn remove_selected_servers(data: &mut ServerData, to_remove: Vec<usize>) {
-    for &index in to_remove.iter().rev() {
-        data.servers.remove(index);
+    if ui.button("Рассчитать по видимому окну").clicked() {
+        let samples = channel_window(data, tool.channel, &state.window);
+        tool.result = Some(fft::amplitude_spectrum(&samples, tool.remove_dc));
+        tool.status = None;
     }
 }
 
-// Главная панель
-fn render_main_content(ui: &mut egui::Ui, state: &mut State) {
-    render_header(ui);
-    ui.separator();
-    render_plot(ui, state);
+// Отсчёты канала в том же окне, что показывает основной график
+fn channel_window(data: &ServerData, channel: usize, window: &TimeWindow) -> Vec<(f64, f64)> {
+    window_results(&data.computed_results, window)
+        .iter()
+        .filter_map(|r| r.flow.get(channel).copied().flatten().map(|v| (plot_x(r), v)))
+        .collect()
 }
 
-fn render_header(ui: &mut egui::Ui) {
-    ui.horizontal(|ui| {
-        let icon = egui::include_image!("../assets/logo_big.svg");
-        ui.add(egui::Image::new(icon).fit_to_exact_size(egui::Vec2::new(64.0, 64.0)));
-        ui.vertical(|ui| {
-            ui.heading("Real-time Server Monitoring");
-            egui::widgets::global_theme_preference_buttons(ui);
-            if ui.button("Save to excel and quit").clicked() {
-                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
-            }
+// true — нажата кнопка сохранения спектра
+fn render_fft_result(ui: &mut egui::Ui, tool: &mut FftTool) -> bool {
+    let spectrum = match &tool.result {
+        None => return false,
+        Some(Err(error)) => {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            return false;
+        }
+        Some(Ok(spectrum)) => spectrum,
+    };
+
+    ui.label(format!("Частота Найквиста: {:.3} Гц", spectrum.nyquist));
+    for warning in &spectrum.warnings {
+        ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", warning));
+    }
+
+    let peaks = fft::top_peaks(spectrum, tool.peaks);
+    let line: PlotPoints = spectrum.frequencies.iter().zip(&spectrum.amplitudes).map(|(&f, &a)| [f, a]).collect();
+    let markers: PlotPoints = peaks.iter().map(|&(f, a)| [f, a]).collect();
+
+    Plot::new("fft_plot")
+        .height(200.0)
+        .x_axis_label("Гц")
+        .y_axis_label("амплитуда")
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(line).name("Спектр"));
+            plot_ui.points(Points::new(markers).radius(4.0).name("Пики"));
         });
+
+    egui::Grid::new("fft_peaks").show(ui, |ui| {
+        for (f, a) in &peaks {
+            ui.label(format!("{:.4} Гц", f));
+            ui.label(format!("{:.4}", a));
+            ui.end_row();
+        }
     });
+
+    let save = ui.button("Сохранить спектр в CSV").clicked();
+    if let Some(status) = &tool.status {
+        ui.label(status);
+    }
+    save
 }
 
-// График
-fn render_plot(ui: &mut egui::Ui, state: &mut State) {
-    let data = state.shared_data.lock().unwrap();
-    let plot_lines = prepare_plot_lines(&data, state.points_to_show);
+fn save_spectrum(state: &mut State) {
+    let Some(path) = pick_save_path(state, "CSV", "csv") else { return };
+    let tool = &mut state.fft;
+    let Some(Ok(spectrum)) = &tool.result else { return };
+    tool.status = Some(match export::export_spectrum_csv(spectrum, &path) {
+        Ok(()) => format!("Сохранено в {}", path.display()),
+        Err(e) => format!("Ошибка записи {}: {}", path.display(), e),
+    });
+}
 
-    Plot::new("combined_plot")
-        .legend(Legend::default().position(egui_plot::Corner::RightTop))
-        .allow_zoom(false).allow_scroll(false).allow_drag(false)
-        .set_margin_fraction(egui::Vec2::new(0.0, 0.0))
-        .x_axis_label("time")
-        .y_axis_label("signal")
-        .x_axis_formatter(|value, _| format_seconds(&value))
-        .show(ui, |plot_ui| {
-            for (line, server) in plot_lines.into_iter().zip(data.servers.iter()) {
-                plot_ui.line(line.name(&server.name));
-            }
-        });
+// Ось времени в секундах. На длинном окне дробные деления оставляем без подписи,
+// иначе соседние метки повторяют одно и то же время; на коротком подписываем с миллисекундами
+fn format_time_mark(mark: &egui_plot::GridMark, short_window: bool, origin: Option<u64>) -> String {
+    if mark.value.fract() != 0.0 && !short_window {
+        return String::new();
+    }
+    format_time((mark.value * 1000.0).round() as u64, short_window, origin)
+}
+
+// Время отсчёта в подписях: от начала сбора или, если известно начало (origin), местное по часам.
+// В сессиях дольше суток к часам добавляется дата
+fn format_time(millis: u64, show_millis: bool, origin: Option<u64>) -> String {
+    let clock = origin
+        .and_then(|start| chrono::DateTime::from_timestamp_millis((start + millis) as i64))
+        .map(|time| time.with_timezone(&chrono::Local));
+    let Some(time) = clock else { return format_seconds(millis, show_millis) };
+    let date = if millis >= 86_400_000 { "%d.%m " } else { "" };
+    let clock = if show_millis { "%H:%M:%S%.3f" } else { "%H:%M:%S" };
+    time.format(&format!("{}{}", date, clock)).to_string()
 }
 
-// Добавим функцию для форматирования секунд
-fn format_seconds(mark: &egui_plot::GridMark) -> String {
-    let total = mark.value as u64;
-    let hours = total / 3600;
+// Миллисекунды от начала сбора в виде ЧЧ:ММ:СС или ЧЧ:ММ:СС.ммм.
+// После суток впереди идут дни: 90061 с — «1d 01:01:01», а не «25:01:01»
+fn format_seconds(millis: u64, show_millis: bool) -> String {
+    let total = millis / 1000;
+    let days = total / 86_400;
+    let hours = (total % 86_400) / 3600;
     let minutes = (total % 3600) / 60;
     let seconds = total % 60;
-    format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    let day = if days > 0 { format!("{}d ", days) } else { String::new() };
+    if show_millis {
+        format!("{}{:02}:{:02}:{:02}.{:03}", day, hours, minutes, seconds, millis % 1000)
+    } else {
+        format!("{}{:02}:{:02}:{:02}", day, hours, minutes, seconds)
+    }
 }
 
-fn prepare_plot_lines(data: &ServerData, points_to_show: usize) -> Vec<Line> {
-    let computed_results = &data.computed_results;
-    let start_index = computed_results.len().saturating_sub(points_to_show);
-    
-    (0..data.servers.len()).map(|i| {
-        let points: PlotPoints = computed_results[start_index..]
+// Шаги сетки оси времени, с: доли секунды для коротких окон, дальше минуты, часы и сутки
+const TIME_STEPS: [f64; 23] = [
+    0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1800.0,
+    3600.0, 7200.0, 10_800.0, 21_600.0, 43_200.0, 86_400.0, 172_800.0, 604_800.0,
+];
+
+// Три уровня сетки времени: самый мелкий шаг не меньше рекомендованного egui_plot,
+// каждый следующий — кратный предыдущему, чтобы крупные отметки совпадали с мелкими
+fn time_grid_steps(input: egui_plot::GridInput) -> [f64; 3] {
+    let next = |after: f64| {
+        TIME_STEPS
+            .iter()
+            .copied()
+            .find(|step| *step >= after * 2.0 && (step / after).fract() < 1e-9)
+            .unwrap_or(after * 10.0)
+    };
+    let fine = TIME_STEPS.iter().copied().find(|step| *step >= input.base_step_size).unwrap_or(input.base_step_size);
+    let medium = next(fine);
+    [fine, medium, next(medium)]
+}
+
+// Время отсчёта на оси графика, с
+fn plot_x(result: &ComputationResults) -> f64 {
+    result.timestamp as f64 / 1000.0
+}
+
+// Линии одного канала: исходные участки и их скользящее среднее (пусто без сглаживания)
+#[derive(Default)]
+struct ChannelLines {
+    raw:      Vec<Line>,
+    avg:      Vec<Line>,
+    // Отсчёты, полученные с повторами: значение есть, но связь была нестабильной
+    degraded: Option<Points>,
+}
+
+// Подпись оси Y — единицы её видимых каналов через запятую
+fn axis_label(data: &ServerData, right: bool) -> String {
+    let mut units: Vec<&str> = Vec::new();
+    for (_, def) in channel::resolved(&data.columns, &data.servers).flatten() {
+        if def.visible && def.right_axis == right && !def.unit.is_empty() && !units.contains(&def.unit.as_str()) {
+            units.push(&def.unit);
+        }
+    }
+    if units.is_empty() { "signal".to_string() } else { units.join(", ") }
+}
+
+// Пределы обеих осей, если хоть один видимый канал на правой оси. Автомасштаб — по отсчётам окна
+// с запасом 5%; левая ось без своих каналов повторяет правую
+fn axis_map(data: &ServerData, window: &TimeWindow, left: &YAxis, right: &YAxis) -> Option<AxisMap> {
+    let visible = window_results(&data.computed_results, window);
+    let sides: Vec<Option<bool>> = channel::resolved(&data.columns, &data.servers)
+        .map(|channel| match channel {
+            Some((_, def)) if !def.visible => None,
+            Some((_, def)) => Some(def.right_axis),
+            None => Some(false),
+        })
+        .collect();
+    if !sides.contains(&Some(true)) {
+        return None;
+    }
+    let range = |side: bool| -> Option<(f64, f64)> {
+        let values = visible.iter().flat_map(|r| {
+            r.flow.iter().zip(&sides).filter(|(_, s)| **s == Some(side)).filter_map(|(v, _)| *v).filter(|v| v.is_finite())
+        });
+        let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| (min.min(v), max.max(v)));
+        if min > max {
+            return None;
+        }
+        let margin = if max - min > f64::EPSILON { (max - min) * 0.05 } else { min.abs().max(1.0) * 0.05 };
+        Some((min - margin, max + margin))
+    };
+    let right = right.manual_range().or_else(|| range(true)).unwrap_or((0.0, 1.0));
+    let left = left.manual_range().or_else(|| range(false)).unwrap_or(right);
+    Some(AxisMap { left, right })
+}
+
+// По линии на каждый непрерывный участок сбора: паузы и пропуски отсчётов остаются разрывами.
+// Участки одного канала имеют общий цвет и имя, поэтому в легенде это одна запись
+fn prepare_plot_lines(data: &ServerData, window: &TimeWindow, smoothing: usize, right_axis: Option<AxisMap>) -> Vec<ChannelLines> {
+    let visible = window_results(&data.computed_results, window);
+
+    // Для скрытых каналов линий нет, индексы остаются выровнены с колонками.
+    // Линии удалённого посреди сессии сервера рисуются до конца сессии
+    channel::resolved(&data.columns, &data.servers).enumerate().map(|(i, channel)| {
+        if channel.is_some_and(|(_, def)| !def.visible) {
+            return ChannelLines::default();
+        }
+        // Значения каналов правой оси — в координатах левой
+        let map = right_axis.filter(|_| channel.is_some_and(|(_, def)| def.right_axis));
+        let value = |r: &ComputationResults| r.flow.get(i).copied().flatten().map(|v| map.map_or(v, |map| map.to_plot(v)));
+        let runs: Vec<Vec<[f64; 2]>> = segments(visible)
+            .flat_map(|segment| segment.chunk_by(|a, b| value(a).is_some() == value(b).is_some()))
+            .filter(|run| value(&run[0]).is_some())
+            .map(|run| run.iter().filter_map(|r| value(r).map(|v| [plot_x(r), v])).collect())
+            .collect();
+
+        let avg = if smoothing > 1 {
+            runs.iter()
+                .map(|run| moving_average(run, smoothing))
+                .filter(|points| !points.is_empty())
+                .map(|points| Line::new(PlotPoints::from(points)).color(server_color(i)).width(2.5))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let raw = runs.into_iter().map(|run| Line::new(PlotPoints::from(run)).color(server_color(i))).collect();
+
+        let retried: Vec<[f64; 2]> = visible
             .iter()
-            .map(|r| [r.timestamp as f64, r.flow.get(i).copied().unwrap_or(0.0)])
+            .filter(|r| r.quality.get(i) == Some(&SampleQuality::Retried))
+            .filter_map(|r| value(r).map(|v| [plot_x(r), v]))
             .collect();
-        Line::new(points)
+        let degraded = (!retried.is_empty()).then(|| {
+            Points::new(retried).shape(MarkerShape::Circle).filled(false).radius(4.0).color(server_color(i))
+        });
+        ChannelLines { raw, avg, degraded }
+    }).collect()
+}
+
+// Скользящее среднее назад по n отсчётам участка. Первые n-1 точек не выводятся:
+// среднее по неполному окну выдавало бы значения, которых не было бы при полной истории.
+// Считается только по видимому окну, поэтому цена кадра не растёт с длиной сессии
+fn moving_average(run: &[[f64; 2]], n: usize) -> Vec<[f64; 2]> {
+    if run.len() < n {
+        return Vec::new();
+    }
+    let mut sum: f64 = run[..n - 1].iter().map(|p| p[1]).sum();
+    run.iter().enumerate().skip(n - 1).map(|(k, point)| {
+        sum += point[1];
+        let avg = sum / n as f64;
+        sum -= run[k + 1 - n][1];
+        [point[0], avg]
     }).collect()
 }
+
+// Результаты, попадающие в окно. Время отсчётов не убывает, поэтому начало ищется бинарным поиском
+fn window_results<'a>(results: &'a [ComputationResults], window: &TimeWindow) -> &'a [ComputationResults] {
+    let Some(latest) = results.last().map(|r| r.timestamp) else { return results };
+    if window.show_all {
+        return results;
+    }
+    let from = latest.saturating_sub(window.secs * 1000);
+    &results[results.partition_point(|r| r.timestamp < from)..]
+}
+
+fn segments(results: &[ComputationResults]) -> impl Iterator<Item = &[ComputationResults]> {
+    results.chunk_by(|_, next| !next.after_pause)
+}
+
+// Та же палитра, что у egui_plot по умолчанию, но привязанная к индексу сервера
+fn server_color(index: usize) -> egui::Color32 {
+    let golden_ratio = (5.0_f32.sqrt() - 1.0) / 2.0;
+    let hue = (index as f32 * golden_ratio).fract();
+    egui::ecolor::Hsva::new(hue, 0.85, 0.5, 1.0).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn io_error(kind: ErrorKind) -> std::io::Error {
+        std::io::Error::new(kind, "test")
+    }
+
+    #[test]
+    fn io_failure_table_maps_every_kind() {
+        for (kind, failure) in IO_FAILURE_TABLE {
+            assert_eq!(FetchFailure::from_io_error(&io_error(*kind)), *failure, "{:?}", kind);
+        }
+        assert_eq!(FetchFailure::from_io_error(&io_error(ErrorKind::PermissionDenied)), FetchFailure::Other);
+    }
+
+    #[test]
+    fn invalid_data_is_a_protocol_error_unless_utf8() {
+        // Кадр Modbus, серийный порт без терминатора и т. п. — не ошибка кодировки
+        assert_eq!(FetchFailure::from_io_error(&io_error(ErrorKind::InvalidData)), FetchFailure::Protocol);
+        let utf8 = source::decode(vec![b'1', 0xff, 0xfe]).unwrap_err();
+        assert_eq!(utf8.kind(), ErrorKind::InvalidData);
+        assert_eq!(FetchFailure::from_io_error(&utf8), FetchFailure::InvalidUtf8);
+        assert_eq!(source::decode(b"1.5 2".to_vec()).unwrap(), "1.5 2");
+    }
+
+    #[test]
+    fn tagged_errors_win_over_the_table() {
+        assert_eq!(FetchFailure::from_io_error(&address::dns_error("no such host")), FetchFailure::Dns);
+        assert_eq!(FetchFailure::from_io_error(&std::io::Error::other(stream::NoRecentData)), FetchFailure::NoData);
+    }
+
+    #[test]
+    fn classify_flags_missing_channels() {
+        assert_eq!(FetchFailure::classify(&Ok("1 x".to_string()), &[Some(1.0), None]), Some(FetchFailure::BadValue));
+        assert_eq!(FetchFailure::classify(&Ok("1 2".to_string()), &[Some(1.0), Some(2.0)]), None);
+        let refused = Err(io_error(ErrorKind::ConnectionRefused));
+        assert_eq!(FetchFailure::classify(&refused, &[]), Some(FetchFailure::Refused));
+    }
+
+    #[test]
+    fn draft_commit_applies_valid_text() {
+        let mut drafts = ServerDrafts::default();
+        let key = (7, ServerField::Address);
+        let mut address = "127.0.0.1:9000".to_string();
+        drafts.texts.insert(key, " 10.0.0.5:502 ".to_string());
+        assert!(drafts.commit(key, &mut address));
+        assert_eq!(address, "10.0.0.5:502");
+        assert!(drafts.texts.is_empty() && drafts.errors.is_empty());
+
+        // Тот же текст повторно — не изменение
+        drafts.texts.insert(key, "10.0.0.5:502".to_string());
+        assert!(!drafts.commit(key, &mut address));
+        // Нет черновика — нечего применять
+        assert!(!drafts.commit(key, &mut address));
+    }
+
+    #[test]
+    fn draft_commit_rolls_back_invalid_text() {
+        let mut drafts = ServerDrafts::default();
+        let key = (7, ServerField::Address);
+        let mut address = "127.0.0.1:9000".to_string();
+        drafts.texts.insert(key, "127.0.0.1".to_string());
+        assert!(!drafts.commit(key, &mut address));
+        assert_eq!(address, "127.0.0.1:9000");
+        // Черновик сброшен к сохранённому значению, ошибка видна до следующей удачной правки
+        assert!(!drafts.texts.contains_key(&key));
+        assert!(drafts.errors.contains_key(&key));
+
+        drafts.texts.insert(key, "127.0.0.1:9001".to_string());
+        assert!(drafts.commit(key, &mut address));
+        assert!(drafts.errors.is_empty());
+    }
+
+    #[test]
+    fn drafts_follow_server_ids() {
+        let mut drafts = ServerDrafts::default();
+        drafts.texts.insert((1, ServerField::Name), "a".to_string());
+        drafts.texts.insert((2, ServerField::Name), "b".to_string());
+        drafts.errors.insert((1, ServerField::Address), "bad".to_string());
+        drafts.forget(1);
+        assert_eq!(drafts.texts.keys().map(|(id, _)| *id).collect::<Vec<_>>(), [2]);
+        assert!(drafts.errors.is_empty());
+    }
+
+    #[test]
+    fn server_fields_are_validated() {
+        assert!(validate_server_field(ServerField::Name, "  ").is_err());
+        assert_eq!(validate_server_field(ServerField::Name, " m1 "), Ok("m1".to_string()));
+        assert!(validate_server_field(ServerField::Url, "example.com").is_err());
+        assert!(validate_server_field(ServerField::JsonPointer, "num1").is_err());
+        assert!(validate_server_field(ServerField::JsonPointer, "").is_ok());
+        assert!(validate_server_field(ServerField::Terminator, "").is_err());
+        assert!(validate_server_field(ServerField::Expression, "(a + ").is_err());
+    }
+
+    #[test]
+    fn failure_codes_are_unique() {
+        let codes: std::collections::HashSet<_> = FetchFailure::ALL.iter().map(FetchFailure::code).collect();
+        assert_eq!(codes.len(), FetchFailure::ALL.len());
+    }
+
+    // Сборщик в фоне с настоящим циклом сбора; файл хвоста, метрики и лента выключены
+    struct Collector {
+        run:      Arc<RunControl>,
+        updates:  Receiver<CollectorUpdate>,
+        commands: mpsc::UnboundedSender<CollectorCommand>,
+        // Копия данных на стороне GUI, в неё применяются обновления сборщика
+        data:     ServerData,
+    }
+
+    // Каталог автосохранения свой: экспорт headless чистит его после успешной выгрузки
+    fn test_config(servers: Vec<ServerInfo>) -> config::Config {
+        let dir = std::env::temp_dir().join(format!("enlil-test-autosave-{}", std::process::id()));
+        config::Config {
+            servers,
+            autosave: AutosaveSettings { enabled: false, interval_min: 5, dir: dir.display().to_string() },
+            ..Default::default()
+        }
+    }
+
+    // Ответ заготовленного прибора на очередной опрос
+    enum Reply {
+        Text(&'static str),
+        Fail(ErrorKind),
+        // Прибор молчит, опрос заканчивается по таймауту
+        Silent,
+        // Ответ через заданное время
+        Late(Duration, &'static str),
+    }
+
+    // Приборы без сети: ответ зависит от имени сервера и номера его опроса, считая стартовый
+    struct Scripted {
+        script: fn(&str, u64) -> Reply,
+        polls:  std::sync::Mutex<HashMap<u32, u64>>,
+        // Сколько опросов идёт сейчас и сколько шло одновременно в пике
+        active: std::sync::atomic::AtomicUsize,
+        peak:   std::sync::atomic::AtomicUsize,
+    }
+
+    impl Scripted {
+        fn new(script: fn(&str, u64) -> Reply) -> Arc<Self> {
+            Arc::new(Self { script, polls: Default::default(), active: Default::default(), peak: Default::default() })
+        }
+    }
+
+    impl fetcher::Fetcher for Arc<Scripted> {
+        fn fetch(&self, server: &ServerInfo, timeout: Duration) -> impl std::future::Future<Output = std::io::Result<String>> + Send {
+            let poll = {
+                let mut polls = self.polls.lock().unwrap();
+                let count = polls.entry(server.id).or_default();
+                *count += 1;
+                *count - 1
+            };
+            let reply = (self.script)(&server.name, poll);
+            let counters = self.clone();
+            async move {
+                let active = counters.active.fetch_add(1, Ordering::SeqCst) + 1;
+                counters.peak.fetch_max(active, Ordering::SeqCst);
+                let resp = match reply {
+                    Reply::Text(text) => Ok(text.to_string()),
+                    Reply::Fail(kind) => Err(std::io::Error::new(kind, "scripted failure")),
+                    Reply::Silent => {
+                        time::sleep(timeout).await;
+                        Err(std::io::Error::new(ErrorKind::TimedOut, "Response timeout"))
+                    }
+                    Reply::Late(delay, text) => {
+                        time::sleep(delay).await;
+                        Ok(text.to_string())
+                    }
+                };
+                counters.active.fetch_sub(1, Ordering::SeqCst);
+                resp
+            }
+        }
+    }
+
+    fn spawn_collector(config: config::Config, fetcher: impl fetcher::Fetcher) -> Collector {
+        let run = Arc::new(RunControl::new());
+        let outputs = CollectorOutputs {
+            tail_tx: crossbeam_channel::unbounded().0,
+            metrics: metrics::Exporter::new(),
+            feed:    feed::Feed::new(),
+        };
+        let (updates_tx, updates) = crossbeam_channel::unbounded();
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        start_data_collection_task(ServerData::new(config.clone()), run.clone(), updates_tx, commands_rx, outputs, fetcher);
+        Collector { run, updates, commands, data: ServerData::new(config) }
+    }
+
+    impl Collector {
+        // Применяет обновления сборщика к копии GUI, пока не выполнится условие
+        async fn until(&mut self, what: &str, done: impl Fn(&ServerData) -> bool) {
+            let wait = async {
+                loop {
+                    for update in self.updates.try_iter() {
+                        apply_update(&mut self.data, &update);
+                    }
+                    if done(&self.data) {
+                        break;
+                    }
+                    time::sleep(Duration::from_millis(10)).await;
+                }
+            };
+            if time::timeout(Duration::from_secs(60), wait).await.is_err() {
+                panic!("collector: no {}", what);
+            }
+        }
+
+        async fn samples(&mut self, count: usize) {
+            self.until(&format!("{} samples", count), |data| data.computed_results.len() >= count).await;
+        }
+    }
+
+    // Адреса из диапазонов, которые не маршрутизируются: соединение либо висит, либо сразу отвергается сетью
+    const UNROUTABLE: [&str; 3] = ["10.255.255.1:9000", "192.0.2.1:9000", "[100::1]:9000"];
+
+    #[tokio::test]
+    async fn first_frame_does_not_wait_for_startup_probe() {
+        let servers = UNROUTABLE.iter().enumerate().map(|(i, a)| ServerInfo::new(&format!("m{}", i + 1), a)).collect();
+        let collector = spawn_collector(test_config(servers), fetcher::Network);
+        let mut state = State::new(collector.data, collector.updates, collector.commands, collector.run);
+        let ctx = egui::Context::default();
+
+        let started = Instant::now();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| draw(ctx, &mut state));
+        let frame = started.elapsed();
+        // Рантайм теста однопоточный, сборщик ещё не начал опрос: первый кадр видит «не проверен».
+        // Кадр отрисован раньше, чем истёк бы даже короткий таймаут первого опроса
+        assert!(frame < STARTUP_PROBE_TIMEOUT, "first frame took {:?}", frame);
+        assert!(state.data.servers.iter().all(|s| s.status == ServerStatus::Unchecked));
+
+        // Результаты первого круга приходят сами, без участия кадров
+        let deadline = Instant::now() + TICK_INTERVAL * 2;
+        while state.data.servers.iter().any(|s| s.status == ServerStatus::Unchecked) {
+            assert!(Instant::now() < deadline, "startup probe did not finish");
+            time::sleep(Duration::from_millis(20)).await;
+            let _ = ctx.run(egui::RawInput::default(), |ctx| draw(ctx, &mut state));
+        }
+        assert!(state.data.servers.iter().all(|s| s.status == ServerStatus::Offline));
+    }
+
+    fn sample(data: &ServerData, now: Instant, after_pause: bool) -> (u64, Instant, ComputationResults) {
+        match save_computation_result(data, now, ComputationResults { after_pause, ..Default::default() }) {
+            CollectorUpdate::Sample { start_time, started, result } => (start_time, started, result),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn first_sample_starts_the_clock() {
+        let data = ServerData::new(test_config(Vec::new()));
+        let now = Instant::now();
+        let before = current_timestamp_ms();
+        // Флаг паузы у первого отсчёта ничего не разрывает
+        let (start_time, started, result) = sample(&data, now, true);
+        assert!(start_time >= before && start_time <= current_timestamp_ms());
+        assert_eq!(started, now);
+        assert_eq!(result.timestamp, 0);
+        assert!(!result.after_pause);
+    }
+
+    #[test]
+    fn resume_keeps_the_session_clock() {
+        let t0 = Instant::now();
+        let mut data = ServerData::new(test_config(Vec::new()));
+        data.start_time = Some(1_000_000);
+        data.started = Some(t0);
+        for elapsed in [0, 1000, 2000] {
+            data.computed_results.push(ComputationResults { timestamp: elapsed, ..Default::default() });
+        }
+
+        // Пауза длилась 8 с: время продолжается от прежнего начала, а не с нуля
+        let (start_time, started, result) = sample(&data, t0 + Duration::from_secs(10), true);
+        assert_eq!((start_time, started), (1_000_000, t0));
+        assert_eq!(result.timestamp, 10_000);
+        assert!(result.after_pause);
+
+        data.computed_results.push(result);
+        let (_, _, next) = sample(&data, t0 + Duration::from_secs(11), false);
+        assert_eq!(next.timestamp, 11_000);
+        assert!(!next.after_pause);
+        data.computed_results.push(next);
+
+        // На графике пауза — разрыв между двумя отрезками
+        let lengths: Vec<_> = segments(&data.computed_results).map(<[_]>::len).collect();
+        assert_eq!(lengths, [3, 2]);
+    }
+
+    // Минута сбора на ускоренных часах: m1 выходит за порог на опросах 40..=44, m2 не отвечает
+    // на опросах 20..=29. Сбор и выгрузка — тот же путь, что у --headless
+    #[tokio::test(start_paused = true)]
+    async fn headless_minute_smoke_test() {
+        let dir = std::env::temp_dir().join(format!("enlil-smoke-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut servers = vec![ServerInfo::new("m1", "127.0.0.1:9001"), ServerInfo::new("m2", "127.0.0.1:9002")];
+        servers[0].channels[0].max = Some(50.0);
+        let fetcher = Scripted::new(|name, poll| match (name, poll) {
+            ("m1", 40..=44) => Reply::Text("75"),
+            ("m1", _) => Reply::Text("10"),
+            (_, 20..=29) => Reply::Fail(ErrorKind::ConnectionRefused),
+            _ => Reply::Text("2.5"),
+        });
+        let collector = spawn_collector(test_config(servers), fetcher);
+        let mut data = collector.data;
+        data.notes = "smoke".to_string();
+        let started = current_timestamp_ms();
+        headless::run(data, collector.updates, collector.run, Some(Duration::from_millis(59_500)), &dir).await;
+
+        // headless::run забирает отсчёты в свою копию, здесь они нужны ещё раз — берём из файлов
+        let csv = std::fs::read_to_string(dir.join(CSV_PATH)).unwrap();
+        let excel = export::load_excel(&dir.join(EXCEL_PATH)).unwrap();
+        let json = session::load(&dir.join(JSON_PATH)).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // CSV: 60 строк по секунде, пропуски m2 — пустые ячейки, событие порога со временем возврата
+        let mut sections = csv.split("\n\n");
+        let rows: Vec<&str> = sections.next().unwrap().lines().collect();
+        assert_eq!(rows[0], "time,m1,m2,sampled,channels");
+        assert_eq!(rows.len(), 61);
+        assert_eq!(rows[1], "0,10,2.5,2,2");
+        assert_eq!(rows[21], "20,10,,1,2");
+        assert_eq!(rows[30], "29,10,,1,2");
+        assert_eq!(rows[31], "30,10,2.5,2,2");
+        assert_eq!(rows[41], "40,75,2.5,2,2");
+        assert_eq!(rows[60], "59,10,2.5,2,2");
+        let quality: Vec<&str> = sections.next().unwrap().lines().collect();
+        assert_eq!(quality[21], "20,OK,ERROR");
+        let events = sections.find(|s| s.starts_with("time,channel")).unwrap();
+        assert_eq!(events.lines().collect::<Vec<_>>(), ["time,channel,value,bound,limit,resolved", "40,m1,75,above,50,45"]);
+
+        // xlsx: те же отсчёты, пропуски пустыми ячейками, метаданные сбора
+        assert_eq!(excel.results.len(), 60);
+        assert_eq!(excel.results[25].flow, [Some(10.0), None]);
+        assert_eq!(excel.results[59].timestamp, 59_000);
+        assert_eq!(excel.metadata.notes, "smoke");
+        assert_eq!(excel.metadata.poll_interval_ms, 1000);
+        assert_eq!(excel.metadata.app_version, env!("CARGO_PKG_VERSION"));
+        let start = excel.start_time.unwrap();
+        assert!(start >= started && start <= current_timestamp_ms(), "{} not in [{}, now]", start, started);
+
+        // JSON: сессия с раскладкой, качеством, событиями простоя и метаданными
+        assert_eq!(json.start_time, Some(start));
+        assert_eq!(json.results.len(), 60);
+        assert_eq!(json.results[25].flow, [Some(10.0), None]);
+        assert_eq!(json.results[25].quality, [SampleQuality::Good, SampleQuality::Error]);
+        assert_eq!(json.columns.len(), 2);
+        assert_eq!(json.metadata.notes, "smoke");
+        let outages: Vec<_> = json.availability.iter().map(|a| (a.server.as_str(), a.outages.len())).collect();
+        assert_eq!(outages, [("m1", 0), ("m2", 1)]);
+    }
+
+    #[test]
+    fn parse_value_accepts_decimal_comma() {
+        assert_eq!(parse_value("1.5"), Some(1.5));
+        assert_eq!(parse_value("1,5"), Some(1.5));
+        assert_eq!(parse_value("-12,345"), Some(-12.345));
+        assert_eq!(parse_value("1e3"), Some(1000.0));
+        assert_eq!(parse_value("2,5E-1"), Some(0.25));
+        assert_eq!(parse_value(" 2.5 \r\n"), Some(2.5));
+        assert_eq!(parse_value("\t7\n"), Some(7.0));
+    }
+
+    #[test]
+    fn parse_value_rejects_ambiguous_and_empty() {
+        for text in ["", "  \n", "1,234,5", "1,234.5", "1.2.3", "12 kg", ","] {
+            assert_eq!(parse_value(text), None, "{:?}", text);
+        }
+    }
+
+    // Сервер m2 отвечает через раз: его колонка получает пропуски, а не нули и не прошлое значение
+    #[test]
+    fn flaky_server_leaves_gaps() {
+        let data = ServerData::new(test_config(vec![ServerInfo::new("m1", "a:1"), ServerInfo::new("m2", "b:1")]));
+        let flows: Vec<_> = (0..10)
+            .map(|tick| {
+                let m2 = if tick % 2 == 0 { Err(io_error(ErrorKind::ConnectionRefused)) } else { Ok(format!("{}", tick)) };
+                parse_responses(&data.columns, &data.servers, &[Ok("1.5".to_string()), m2])
+            })
+            .collect();
+        for (tick, (flow, quality)) in flows.iter().enumerate() {
+            if tick % 2 == 0 {
+                assert_eq!(flow, &[Some(1.5), None]);
+                assert_eq!(quality, &[SampleQuality::Good, SampleQuality::Error]);
+            } else {
+                assert_eq!(flow, &[Some(1.5), Some(tick as f64)]);
+                assert_eq!(quality, &[SampleQuality::Good, SampleQuality::Good]);
+            }
+        }
+    }
+
+    #[test]
+    fn gaps_name_their_cause() {
+        let mut data = ServerData::new(test_config(vec![ServerInfo::new("m1", "a:1"), ServerInfo::new("m2", "b:1")]));
+        let timeout = || Err(io_error(ErrorKind::TimedOut));
+        let (flow, quality) = parse_responses(&data.columns, &data.servers, &[Ok("x".to_string()), timeout()]);
+        assert_eq!(flow, [None, None]);
+        assert_eq!(quality, [SampleQuality::Parse, SampleQuality::Timeout]);
+
+        // Удалённый посреди сессии сервер оставляет свою колонку пустой
+        data.servers.remove(1);
+        let (flow, quality) = parse_responses(&data.columns, &data.servers, &[Ok("3".to_string())]);
+        assert_eq!(flow, [Some(3.0), None]);
+        assert_eq!(quality, [SampleQuality::Good, SampleQuality::Missing]);
+    }
+
+    // Время отсчётов идёт от монотонного начала сессии: шаг настенных часов назад его не трогает,
+    // а совпавшие или запоздавшие моменты всё равно дают строго возрастающее время
+    #[test]
+    fn timestamps_ignore_wall_clock_steps() {
+        let t0 = Instant::now();
+        let mut data = ServerData::new(test_config(Vec::new()));
+        // Часы отошли на час назад после старта: начало сессии оказалось «в будущем»
+        let start_time = current_timestamp_ms() + 3_600_000;
+        data.start_time = Some(start_time);
+        data.started = Some(t0);
+
+        let mut timestamps = Vec::new();
+        for offset in [0, 500, 500, 400, 1500] {
+            let (start, _, result) = sample(&data, t0 + Duration::from_millis(offset), false);
+            assert_eq!(start, start_time);
+            timestamps.push(result.timestamp);
+            data.computed_results.push(result);
+        }
+        assert_eq!(timestamps, [0, 500, 501, 502, 1500]);
+    }
+
+    // Прибор из src/bin/mock_server.rs, отвечающий по сценарию. Порт 0 — свободный от системы
+    async fn mock_instrument(port: u16, script: &str) -> (SocketAddr, tokio::task::JoinHandle<()>) {
+        let port = port.to_string();
+        let args = mock_server::Args::parse_from(["mock_server", "--port", &port, "--wave", "script", "--script", script]);
+        let script = mock_server::parse_script(script).unwrap();
+        mock_server::start(args, script).await.unwrap().pop().unwrap()
+    }
+
+    // Настоящий опрос по TCP: m1 идёт по сценарию с обрывом и мусором, m2 отвечает постоянным
+    // значением, его выключают на один тик и поднимают на том же порту
+    #[tokio::test]
+    async fn collects_from_mock_instruments() {
+        let (m1, _m1_task) = mock_instrument(0, "1,2,err,garbage,5").await;
+        let (m2, m2_task) = mock_instrument(0, "7").await;
+        let servers = vec![ServerInfo::new("m1", &m1.to_string()), ServerInfo::new("m2", &m2.to_string())];
+        let mut collector = spawn_collector(test_config(servers), fetcher::Network);
+
+        // Стартовый опрос забирает первый шаг сценария m1
+        collector.until("startup probe", |data| data.servers.iter().all(|s| s.status == ServerStatus::Online)).await;
+        collector.run.try_transition(RunCommand::Start).unwrap();
+        collector.samples(2).await;
+
+        m2_task.abort();
+        let _ = m2_task.await;
+        collector.samples(3).await;
+        let m2_status = &collector.data.servers[1];
+        assert_eq!((m2_status.status, m2_status.failure), (ServerStatus::Offline, Some(FetchFailure::Refused)));
+
+        let (_, _m2_task) = mock_instrument(m2.port(), "7").await;
+        collector.samples(5).await;
+        assert_eq!(collector.data.servers[1].status, ServerStatus::Online);
+
+        let results = &collector.data.computed_results[..5];
+        let flows: Vec<_> = results.iter().map(|r| r.flow.clone()).collect();
+        assert_eq!(flows, [
+            [Some(2.0), Some(7.0)],
+            [None, Some(7.0)],
+            [None, None],
+            [Some(5.0), Some(7.0)],
+            [Some(1.0), Some(7.0)],
+        ]);
+        let quality: Vec<_> = results.iter().map(|r| r.quality.clone()).collect();
+        use SampleQuality::*;
+        assert_eq!(quality, [[Good, Good], [Parse, Good], [Parse, Error], [Good, Good], [Good, Good]]);
+        let sampled: Vec<_> = results.iter().map(|r| r.sampled).collect();
+        assert_eq!(sampled, [2, 1, 0, 2, 2]);
+    }
+
+    fn three_servers() -> config::Config {
+        test_config(["m1", "m2", "m3"].iter().map(|name| ServerInfo::new(name, &format!("{}:9000", name))).collect())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn all_servers_healthy() {
+        let mut collector = spawn_collector(three_servers(), Scripted::new(|name, _| match name {
+            "m1" => Reply::Text("1.5"),
+            "m2" => Reply::Text("2,5"),
+            _ => Reply::Text("3e1"),
+        }));
+        collector.run.try_transition(RunCommand::Start).unwrap();
+        collector.samples(3).await;
+
+        for result in &collector.data.computed_results {
+            assert_eq!(result.flow, [Some(1.5), Some(2.5), Some(30.0)]);
+            assert_eq!((result.sampled, result.channels), (3, 3));
+        }
+        assert!(collector.data.servers.iter().all(|s| s.status == ServerStatus::Online && s.failure.is_none()));
+    }
+
+    // Молчащий m2 держит тик не дольше своего таймаута, остальные каналы пишутся
+    #[tokio::test(start_paused = true)]
+    async fn one_server_timing_out() {
+        let mut collector = spawn_collector(three_servers(), Scripted::new(|name, _| match name {
+            "m2" => Reply::Silent,
+            _ => Reply::Text("1"),
+        }));
+        collector.run.try_transition(RunCommand::Start).unwrap();
+        collector.samples(3).await;
+
+        for result in &collector.data.computed_results {
+            assert_eq!(result.flow, [Some(1.0), None, Some(1.0)]);
+            assert_eq!(result.quality[1], SampleQuality::Timeout);
+            assert_eq!(result.sampled, 2);
+        }
+        // Таймаут укладывается в тик: после стартового опроса с коротким таймаутом
+        // отсчёты идут раз в секунду, а не реже
+        let timestamps: Vec<_> = collector.data.computed_results.iter().map(|r| r.timestamp).collect();
+        assert!(timestamps[1..].windows(2).all(|w| w[1] - w[0] == 1000), "{:?}", timestamps);
+        let m2 = &collector.data.servers[1];
+        assert_eq!((m2.status, m2.failure), (ServerStatus::Offline, Some(FetchFailure::TimedOut)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn server_returning_text() {
+        let mut collector = spawn_collector(three_servers(), Scripted::new(|name, _| match name {
+            "m3" => Reply::Text("ERR overload"),
+            _ => Reply::Text("4"),
+        }));
+        collector.run.try_transition(RunCommand::Start).unwrap();
+        collector.samples(2).await;
+
+        let result = &collector.data.computed_results[1];
+        assert_eq!(result.flow, [Some(4.0), Some(4.0), None]);
+        assert_eq!(result.quality[2], SampleQuality::Parse);
+        // Прибор на связи, но значение не разобрано
+        let m3 = &collector.data.servers[2];
+        assert_eq!((m3.status, m3.failure), (ServerStatus::Online, Some(FetchFailure::BadValue)));
+    }
+
+    // Пауза посреди сбора оставляет отсчёты и время начала, стоп очищает сессию
+    #[tokio::test(start_paused = true)]
+    async fn start_pause_stop_mid_run() {
+        let mut collector = spawn_collector(three_servers(), Scripted::new(|_, _| Reply::Text("1")));
+        collector.until("startup probe", |data| data.servers.iter().all(|s| s.status == ServerStatus::Online)).await;
+        assert!(collector.data.computed_results.is_empty(), "samples before start");
+
+        collector.run.try_transition(RunCommand::Start).unwrap();
+        collector.samples(3).await;
+        collector.run.try_transition(RunCommand::Pause).unwrap();
+        let start_time = collector.data.start_time;
+        // Отсчёт тика, начатого до паузы, ещё мог прийти; дальше новых нет
+        time::sleep(TICK_INTERVAL * 2).await;
+        collector.until("updates", |_| true).await;
+        let paused = collector.data.computed_results.len();
+        time::sleep(TICK_INTERVAL * 5).await;
+        collector.until("updates", |_| true).await;
+        assert_eq!(collector.data.computed_results.len(), paused);
+
+        collector.run.try_transition(RunCommand::Resume).unwrap();
+        collector.samples(paused + 1).await;
+        let resumed = &collector.data.computed_results[paused];
+        assert!(resumed.after_pause);
+        assert!(resumed.timestamp >= 5000, "{}", resumed.timestamp);
+        assert_eq!(collector.data.start_time, start_time);
+
+        collector.run.try_transition(RunCommand::Stop).unwrap();
+        let mut state = collector.run.subscribe();
+        let _ = time::timeout(Duration::from_secs(5), state.wait_for(|s| s.is_idle())).await.unwrap();
+        collector.until("cleared session", |data| data.computed_results.is_empty() && data.start_time.is_none()).await;
+    }
+
+    const QUALITIES: [(SampleQuality, &str, &str); 8] = [
+        (SampleQuality::Good,     "OK",       "\"good\""),
+        (SampleQuality::Retried,  "RETRIED",  "\"retried\""),
+        (SampleQuality::Timeout,  "TIMEOUT",  "\"timeout\""),
+        (SampleQuality::Parse,    "PARSE",    "\"parse\""),
+        (SampleQuality::Error,    "ERROR",    "\"error\""),
+        (SampleQuality::Disabled, "DISABLED", "\"disabled\""),
+        (SampleQuality::Skipped,  "SKIPPED",  "\"skipped\""),
+        (SampleQuality::Missing,  "MISSING",  "\"missing\""),
+    ];
+
+    // Коды в файлах экспорта и имена в JSON сессии — формат файлов, менять их нельзя
+    #[test]
+    fn sample_quality_codes_and_serialization() {
+        for (quality, code, json) in QUALITIES {
+            assert_eq!(quality.code(), code);
+            assert_eq!(serde_json::to_string(&quality).unwrap(), json);
+            assert_eq!(serde_json::from_str::<SampleQuality>(json).unwrap(), quality);
+        }
+        assert!(serde_json::from_str::<SampleQuality>("\"bogus\"").is_err());
+    }
+
+    #[test]
+    fn quality_round_trips_with_results() {
+        let result = ComputationResults {
+            timestamp: 1000,
+            flow:      vec![Some(1.0), None],
+            quality:   vec![SampleQuality::Retried, SampleQuality::Timeout],
+            ..Default::default()
+        };
+        let text = serde_json::to_string(&result).unwrap();
+        assert!(text.contains(r#""quality":["retried","timeout"]"#), "{}", text);
+        assert_eq!(serde_json::from_str::<ComputationResults>(&text).unwrap().quality, result.quality);
+        // Отсчёты из сессий до появления поля читаются с пустым качеством
+        let old: ComputationResults = serde_json::from_str(r#"{"timestamp":0,"flow":[1.0],"sampled":1,"channels":1}"#).unwrap();
+        assert!(old.quality.is_empty());
+    }
+
+    #[test]
+    fn retried_fetch_is_flagged() {
+        let mut data = ServerData::new(test_config(vec![ServerInfo::new("m1", "a:1")]));
+        data.servers[0].retries = 2;
+        let (flow, quality) = parse_responses(&data.columns, &data.servers, &[Ok("5".to_string())]);
+        assert_eq!((flow, quality), (vec![Some(5.0)], vec![SampleQuality::Retried]));
+        data.servers[0].enabled = false;
+        let (flow, quality) = parse_responses(&data.columns, &data.servers, &[Err(io_error(ErrorKind::Other))]);
+        assert_eq!((flow, quality), (vec![None], vec![SampleQuality::Disabled]));
+    }
+
+    // Мёртвый m1 после двух ошибок пропускается на паузу: опросов меньше, чем тиков,
+    // отсчёты помечены Skipped, а после восстановления цепь замыкается
+    #[tokio::test(start_paused = true)]
+    async fn breaker_skips_a_dead_server() {
+        let mut config = test_config(vec![ServerInfo::new("m1", "a:1"), ServerInfo::new("m2", "b:1")]);
+        config.breaker = BreakerSettings { enabled: true, failures: 2, cooldown_secs: 3, max_cooldown_secs: 6 };
+        let fetcher = Scripted::new(|name, poll| match (name, poll) {
+            ("m1", 0..=3) => Reply::Fail(ErrorKind::ConnectionRefused),
+            _ => Reply::Text("1"),
+        });
+        let mut collector = spawn_collector(config, fetcher.clone());
+        collector.run.try_transition(RunCommand::Start).unwrap();
+        collector.samples(20).await;
+
+        let polls = fetcher.polls.lock().unwrap().clone();
+        let (m1, m2) = (collector.data.servers[0].id, collector.data.servers[1].id);
+        assert!(polls[&m1] < polls[&m2], "m1 polled {} times of {}", polls[&m1], polls[&m2]);
+        let flags: Vec<_> = collector.data.computed_results.iter().map(|r| r.quality[0]).collect();
+        assert!(flags.contains(&SampleQuality::Skipped), "{:?}", flags);
+        assert_eq!(flags.last(), Some(&SampleQuality::Good));
+        // Ошибки и пропуски идут одним куском до восстановления
+        let recovered = flags.iter().position(|&f| f == SampleQuality::Good).unwrap();
+        assert!(flags[recovered..].iter().all(|&f| f == SampleQuality::Good), "{:?}", flags);
+        let breaker = collector.data.servers[0].breaker;
+        assert_eq!((breaker.failures, breaker.open_until), (0, None));
+    }
+
+    // Десять серверов по 200 мс: с ограничением в три соединения одновременно идут ровно три опроса,
+    // и все успевают до конца тика; без ограничения — все десять
+    #[tokio::test(start_paused = true)]
+    async fn connection_limit_caps_concurrent_fetches() {
+        for (limit, expected) in [(3, 3), (0, 10)] {
+            let servers = (1..=10).map(|i| ServerInfo::new(&format!("m{}", i), &format!("10.0.0.{}:9000", i))).collect();
+            let config = config::Config { max_connections: limit, ..test_config(servers) };
+            let fetcher = Scripted::new(|_, _| Reply::Late(Duration::from_millis(200), "1"));
+            let mut collector = spawn_collector(config, fetcher.clone());
+            collector.run.try_transition(RunCommand::Start).unwrap();
+            collector.samples(3).await;
+
+            assert_eq!(fetcher.peak.load(Ordering::SeqCst), expected, "limit {}", limit);
+            for result in &collector.data.computed_results {
+                assert_eq!(result.sampled, 10, "limit {}", limit);
+            }
+        }
+    }
+
+    #[test]
+    fn format_seconds_rolls_over_into_days() {
+        assert_eq!(format_seconds(0, false), "00:00:00");
+        assert_eq!(format_seconds(59_000, false), "00:00:59");
+        assert_eq!(format_seconds(3_601_000, false), "01:00:01");
+        assert_eq!(format_seconds(86_400_000, false), "1d 00:00:00");
+        assert_eq!(format_seconds(90_061_000, false), "1d 01:01:01");
+        assert_eq!(format_seconds(200_000_000, false), "2d 07:33:20");
+        assert_eq!(format_seconds(90_061_042, true), "1d 01:01:01.042");
+    }
+
+    // Часы и дата зависят от часового пояса машины, поэтому дату берём у chrono,
+    // а часы сверяем с отметкой ровно на сутки раньше (в январе переходов на летнее время нет)
+    #[test]
+    fn format_time_adds_date_after_a_day() {
+        let start = 1_705_320_000_000;
+        let origin = Some(start);
+        let early = format_time(3_661_000, false, origin);
+        assert_eq!(early.len(), "ЧЧ:ММ:СС".chars().count());
+        let date = chrono::DateTime::from_timestamp_millis(start as i64 + 90_061_000).unwrap().with_timezone(&chrono::Local);
+        assert_eq!(format_time(90_061_000, false, origin), format!("{} {}", date.format("%d.%m"), early));
+        assert!(format_time(90_061_042, true, origin).ends_with(".042"));
+        // Без начала сессии — время от старта сбора
+        assert_eq!(format_time(90_061_000, false, None), "1d 01:01:01");
+    }
+
+    #[test]
+    fn grid_steps_nest_on_hours_and_days() {
+        let steps = |base_step_size| time_grid_steps(egui_plot::GridInput { bounds: (0.0, 1.0e6), base_step_size });
+        assert_eq!(steps(0.3), [0.5, 1.0, 2.0]);
+        assert_eq!(steps(2000.0), [3600.0, 7200.0, 21_600.0]);
+        assert_eq!(steps(50_000.0), [86_400.0, 172_800.0, 1_728_000.0]);
+        // Шаг крупнее таблицы остаётся как есть
+        assert_eq!(steps(1.0e6), [1.0e6, 1.0e7, 1.0e8]);
+    }
+
+    #[test]
+    fn unescape_command_expands_known_sequences() {
+        assert_eq!(unescape_command("READ?\\r\\n"), b"READ?\r\n");
+        assert_eq!(unescape_command("a\\tb\\\\c"), b"a\tb\\c");
+        assert_eq!(unescape_command("\\x02MEAS\\x03"), b"\x02MEAS\x03");
+        assert_eq!(unescape_command("\\xFf"), [0xff]);
+        // Не-ASCII уходит в UTF-8
+        assert_eq!(unescape_command("Т"), "Т".as_bytes());
+    }
+
+    // Нераспознанное и неполное экранирование отправляется как есть
+    #[test]
+    fn unescape_command_keeps_unknown_sequences() {
+        assert_eq!(unescape_command("\\q"), b"\\q");
+        assert_eq!(unescape_command("\\x4"), b"\\x4");
+        assert_eq!(unescape_command("\\xZZ"), b"\\xZZ");
+        assert_eq!(unescape_command("\\x+1"), b"\\x+1");
+        assert_eq!(unescape_command("end\\"), b"end\\");
+    }
+
+    fn at(timestamps: &[u64]) -> Vec<ComputationResults> {
+        timestamps.iter().map(|&timestamp| ComputationResults { timestamp, ..Default::default() }).collect()
+    }
+
+    #[test]
+    fn window_counts_back_from_the_latest_sample() {
+        let results = at(&[0, 1000, 2000, 5000, 9000, 10_000]);
+        let stamps = |window| window_results(&results, &window).iter().map(|r| r.timestamp).collect::<Vec<_>>();
+        // Граница окна включается
+        assert_eq!(stamps(TimeWindow { secs: 5, show_all: false }), [5000, 9000, 10_000]);
+        assert_eq!(stamps(TimeWindow { secs: 1, show_all: false }), [9000, 10_000]);
+        // Окно длиннее сессии и «всё» отдают всё
+        assert_eq!(stamps(TimeWindow { secs: 60, show_all: false }).len(), 6);
+        assert_eq!(stamps(TimeWindow { secs: 1, show_all: true }).len(), 6);
+        assert!(window_results(&[], &TimeWindow { secs: 5, show_all: false }).is_empty());
+    }
+
+    // Первые n-1 точек без среднего, дальше — среднее по последним n
+    #[test]
+    fn moving_average_needs_a_full_window() {
+        let run = [[0.0, 1.0], [1.0, 2.0], [2.0, 3.0], [3.0, 10.0], [4.0, 4.0]];
+        assert_eq!(moving_average(&run, 3), [[2.0, 2.0], [3.0, 5.0], [4.0, 17.0 / 3.0]]);
+        assert_eq!(moving_average(&run, 1), run);
+        assert_eq!(moving_average(&run, 5), [[4.0, 4.0]]);
+        assert!(moving_average(&run, 6).is_empty());
+    }
+
+    #[test]
+    fn nearest_sample_picks_the_closer_neighbour() {
+        let results = at(&[1000, 2000, 4000]);
+        let nearest = |ms| nearest_sample(&results, ms).map(|r| r.timestamp);
+        assert_eq!(nearest(0), Some(1000));
+        assert_eq!(nearest(2000), Some(2000));
+        assert_eq!(nearest(2900), Some(2000));
+        assert_eq!(nearest(3100), Some(4000));
+        // Посередине — более ранний
+        assert_eq!(nearest(3000), Some(2000));
+        assert_eq!(nearest(60_000), Some(4000));
+        assert_eq!(nearest_sample(&[], 1000).map(|r| r.timestamp), None);
+    }
+}
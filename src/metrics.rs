@@ -0,0 +1,154 @@
+//! HTTP-эндпоинт `/metrics` в текстовом формате Prometheus.
+//!
+//! Сборщик после каждого тика пересобирает текст целиком, HTTP-задача отдаёт последнюю версию.
+//! Серии помечены и именем, и адресом сервера: переименование меняет метку `server`,
+//! но запрос по `address` продолжает находить ту же серию.
+//!
+//! ```text
+//! enlil_flow{server="m1",address="192.168.1.10:5000",channel="m1"} 23.45
+//! enlil_fetch_errors_total{server="m1",address="192.168.1.10:5000",reason="TIMEOUT"} 3
+//! enlil_collecting 1
+//! ```
+
+use std::collections::HashMap;
+use axum::{extract::State, http::header, routing::get, Router};
+use tokio::{net::TcpListener, sync::watch, task::JoinHandle};
+use crate::{channel, MetricsSettings, ServerData};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+pub struct Exporter {
+    text:   watch::Sender<String>,
+    // Адрес, на котором сейчас слушает HTTP-задача
+    server: Option<(String, JoinHandle<()>)>,
+    // (адрес сервера, код ошибки) -> число неудачных тиков с начала работы
+    errors: HashMap<(String, &'static str), u64>,
+}
+
+impl Exporter {
+    pub fn new() -> Self {
+        Self {
+            text:   watch::channel(String::new()).0,
+            server: None,
+            errors: HashMap::new(),
+        }
+    }
+
+    // HTTP-задача перезапускается только при смене адреса или выключении
+    pub fn configure(&mut self, settings: &MetricsSettings) {
+        let listen = settings.enabled.then_some(settings.listen.as_str());
+        if self.server.as_ref().map(|(addr, _)| addr.as_str()) == listen {
+            return;
+        }
+        if let Some((_, task)) = self.server.take() {
+            task.abort();
+        }
+        if let Some(listen) = listen {
+            let task = tokio::spawn(serve(listen.to_string(), self.text.subscribe()));
+            self.server = Some((listen.to_string(), task));
+        }
+    }
+
+    // Вызывается раз за тик: счётчики ошибок растут, даже если эндпоинт выключен
+    pub fn publish(&mut self, data: &ServerData, flow: &[Option<f64>], collecting: bool) {
+        for server in &data.servers {
+            if let Some(failure) = server.failure {
+                *self.errors.entry((server.address.clone(), failure.code())).or_insert(0) += 1;
+            }
+        }
+        if self.server.is_some() {
+            self.text.send_replace(self.render(data, flow, collecting));
+        }
+    }
+
+    fn render(&self, data: &ServerData, flow: &[Option<f64>], collecting: bool) -> String {
+        let mut text = String::new();
+        text.push_str("# HELP enlil_flow Last value of a server channel\n# TYPE enlil_flow gauge\n");
+        let channels = channel::resolved(&data.columns, &data.servers).zip(flow);
+        for ((server, def), value) in channels.filter_map(|(channel, value)| channel.map(|c| (c, value))) {
+            // Канал без свежего отсчёта пропускается, Prometheus сам пометит серию устаревшей
+            let Some(value) = value.filter(|_| server.has_good_sample()) else { continue };
+            text.push_str(&format!(
+                "enlil_flow{{server=\"{}\",address=\"{}\",channel=\"{}\"}} {}\n",
+                escape(&server.name),
+                escape(&server.address),
+                escape(&channel::label(server, def)),
+                value,
+            ));
+        }
+
+        text.push_str("# HELP enlil_fetch_errors_total Failed polls by reason\n# TYPE enlil_fetch_errors_total counter\n");
+        for server in &data.servers {
+            let mut reasons: Vec<_> = self.errors
+                .iter()
+                .filter(|((address, _), _)| *address == server.address)
+                .map(|((_, reason), count)| (*reason, *count))
+                .collect();
+            reasons.sort_unstable();
+            for (reason, count) in reasons {
+                text.push_str(&format!(
+                    "enlil_fetch_errors_total{{server=\"{}\",address=\"{}\",reason=\"{}\"}} {}\n",
+                    escape(&server.name),
+                    escape(&server.address),
+                    reason,
+                    count,
+                ));
+            }
+        }
+
+        text.push_str("# HELP enlil_collecting 1 while samples are being recorded\n# TYPE enlil_collecting gauge\n");
+        text.push_str(&format!("enlil_collecting {}\n", u8::from(collecting)));
+        text
+    }
+}
+
+// Экранирование значения метки по правилам текстового формата
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+async fn serve(listen: String, text: watch::Receiver<String>) {
+    let listener = match TcpListener::bind(&listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!(%listen, error = %e, "metrics listen failed");
+            return;
+        }
+    };
+    let app = Router::new().route("/metrics", get(metrics)).with_state(text);
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!(%listen, error = %e, "metrics server failed");
+    }
+}
+
+async fn metrics(State(text): State<watch::Receiver<String>>) -> ([(header::HeaderName, &'static str); 1], String) {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], text.borrow().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel::ChannelDef, ServerInfo, ServerStatus};
+
+    // Метка channel совпадает с подписью в легенде: безымянный канал — имя сервера, именованный — «сервер.канал»
+    #[test]
+    fn channel_label_matches_legend() {
+        let mut servers = vec![ServerInfo::new("m1", "192.168.1.10:5000"), ServerInfo::new("m2", "192.168.1.11:5000")];
+        servers[1].channels = vec![
+            ChannelDef { name: "t".to_string(), ..channel::default_channels()[0].clone() },
+            ChannelDef { name: "p".to_string(), index: 1, ..channel::default_channels()[0].clone() },
+        ];
+        for server in &mut servers {
+            server.status = ServerStatus::Online;
+        }
+        channel::assign_ids(&mut servers);
+        let data = ServerData { columns: channel::layout(&servers), servers, ..Default::default() };
+
+        let text = Exporter::new().render(&data, &[Some(23.45), Some(1.0), None], true);
+        assert!(text.contains("enlil_flow{server=\"m1\",address=\"192.168.1.10:5000\",channel=\"m1\"} 23.45\n"));
+        assert!(text.contains("enlil_flow{server=\"m2\",address=\"192.168.1.11:5000\",channel=\"m2.t\"} 1\n"));
+        // Канал без отсчёта не выводится
+        assert!(!text.contains("channel=\"m2.p\""));
+        assert!(text.ends_with("enlil_collecting 1\n"));
+    }
+}
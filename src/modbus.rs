@@ -0,0 +1,213 @@
+use std::{
+    fmt,
+    io,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const EXCEPTION_FLAG:         u8 = 0x80;
+// Ограничение протокола на одно чтение
+pub const MAX_REGISTERS: u16 = 125;
+
+// Как превращать регистры в значения. f32 занимает два регистра подряд
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Decode {
+    #[default]
+    U16,
+    I16,
+    // Старшее слово первым (ABCD)
+    F32Be,
+    // Младшее слово первым (CDAB)
+    F32Le,
+}
+
+impl Decode {
+    pub const ALL: [Decode; 4] = [Decode::U16, Decode::I16, Decode::F32Be, Decode::F32Le];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Decode::U16   => "u16",
+            Decode::I16   => "i16",
+            Decode::F32Be => "f32 BE",
+            Decode::F32Le => "f32 LE",
+        }
+    }
+}
+
+// Ответ-исключение устройства или нарушенный кадр
+#[derive(Debug)]
+pub struct ModbusError(pub String);
+
+impl fmt::Display for ModbusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Modbus: {}", self.0)
+    }
+}
+
+impl std::error::Error for ModbusError {}
+
+pub fn is_modbus_error(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<ModbusError>())
+}
+
+fn modbus_error(message: impl Into<String>) -> io::Error {
+    io::Error::other(ModbusError(message.into()))
+}
+
+// Чтение holding-регистров (функция 0x03). Значения возвращаются текстом через пробел,
+// как многозначный ответ TCP-прибора, чтобы дальше работали каналы
+pub async fn read_values(stream: &mut TcpStream, unit: u8, register: u16, count: u16, decode: Decode) -> io::Result<String> {
+    let registers = read_holding(stream, unit, register, count).await?;
+    Ok(decode_registers(&registers, decode)
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+async fn read_holding(stream: &mut TcpStream, unit: u8, register: u16, count: u16) -> io::Result<Vec<u16>> {
+    if count == 0 || count > MAX_REGISTERS {
+        return Err(modbus_error(format!("число регистров должно быть 1..={}", MAX_REGISTERS)));
+    }
+    let transaction: u16 = 1;
+    let mut request = Vec::with_capacity(12);
+    request.extend_from_slice(&transaction.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes()); // протокол
+    request.extend_from_slice(&6u16.to_be_bytes()); // длина: unit + PDU
+    request.push(unit);
+    request.push(READ_HOLDING_REGISTERS);
+    request.extend_from_slice(&register.to_be_bytes());
+    request.extend_from_slice(&count.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut header = [0u8; 7];
+    stream.read_exact(&mut header).await?;
+    if u16::from_be_bytes([header[0], header[1]]) != transaction {
+        return Err(modbus_error("чужой номер транзакции"));
+    }
+    let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    if length < 2 {
+        return Err(modbus_error("слишком короткий кадр"));
+    }
+    let mut pdu = vec![0u8; length - 1];
+    stream.read_exact(&mut pdu).await?;
+
+    let function = pdu[0];
+    if function == READ_HOLDING_REGISTERS | EXCEPTION_FLAG {
+        let code = pdu.get(1).copied().unwrap_or(0);
+        return Err(modbus_error(format!("исключение {}: {}", code, exception_text(code))));
+    }
+    if function != READ_HOLDING_REGISTERS {
+        return Err(modbus_error(format!("неожиданная функция 0x{:02X}", function)));
+    }
+    let data = pdu.get(2..).unwrap_or_default();
+    if pdu.get(1).copied() != Some(count as u8 * 2) || data.len() != count as usize * 2 {
+        return Err(modbus_error("длина данных не совпадает с числом регистров"));
+    }
+    Ok(data.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect())
+}
+
+fn decode_registers(registers: &[u16], decode: Decode) -> Vec<f64> {
+    match decode {
+        Decode::U16 => registers.iter().map(|&r| r as f64).collect(),
+        Decode::I16 => registers.iter().map(|&r| r as i16 as f64).collect(),
+        Decode::F32Be => registers
+            .chunks_exact(2)
+            .map(|w| f32::from_bits((w[0] as u32) << 16 | w[1] as u32) as f64)
+            .collect(),
+        Decode::F32Le => registers
+            .chunks_exact(2)
+            .map(|w| f32::from_bits((w[1] as u32) << 16 | w[0] as u32) as f64)
+            .collect(),
+    }
+}
+
+fn exception_text(code: u8) -> &'static str {
+    match code {
+        0x01 => "функция не поддерживается",
+        0x02 => "недопустимый адрес регистра",
+        0x03 => "недопустимое значение",
+        0x04 => "сбой устройства",
+        0x05 => "запрос принят, выполняется",
+        0x06 => "устройство занято",
+        0x0A => "шлюз: нет пути",
+        0x0B => "шлюз: устройство не ответило",
+        _    => "неизвестный код",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    // Устройство в том же процессе: holding-регистры с адреса 0, за их пределами — исключение 0x02
+    async fn responder(registers: Vec<u16>) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let registers = registers.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 12];
+                    while stream.read_exact(&mut request).await.is_ok() {
+                        assert_eq!(request[7], READ_HOLDING_REGISTERS);
+                        let start = u16::from_be_bytes([request[8], request[9]]) as usize;
+                        let count = u16::from_be_bytes([request[10], request[11]]) as usize;
+                        let pdu = match registers.get(start..start + count) {
+                            Some(values) => {
+                                let mut pdu = vec![READ_HOLDING_REGISTERS, count as u8 * 2];
+                                pdu.extend(values.iter().flat_map(|r| r.to_be_bytes()));
+                                pdu
+                            }
+                            None => vec![READ_HOLDING_REGISTERS | EXCEPTION_FLAG, 0x02],
+                        };
+                        // Заголовок MBAP: транзакция и unit из запроса, длина — unit + PDU
+                        let mut frame = request[..4].to_vec();
+                        frame.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+                        frame.push(request[6]);
+                        frame.extend(pdu);
+                        stream.write_all(&frame).await.unwrap();
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    fn f32_words(value: f32) -> [u16; 2] {
+        let bits = value.to_bits();
+        [(bits >> 16) as u16, bits as u16]
+    }
+
+    #[tokio::test]
+    async fn reads_and_decodes_holding_registers() {
+        let [hi, lo] = f32_words(-2.5);
+        let addr = responder(vec![7, 65535, hi, lo, lo, hi]).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+
+        assert_eq!(read_values(&mut stream, 1, 0, 2, Decode::U16).await.unwrap(), "7 65535");
+        assert_eq!(read_values(&mut stream, 1, 1, 1, Decode::I16).await.unwrap(), "-1");
+        assert_eq!(read_values(&mut stream, 1, 2, 2, Decode::F32Be).await.unwrap(), "-2.5");
+        assert_eq!(read_values(&mut stream, 1, 4, 2, Decode::F32Le).await.unwrap(), "-2.5");
+    }
+
+    #[tokio::test]
+    async fn exceptions_and_bad_counts_are_modbus_errors() {
+        let addr = responder(vec![1, 2]).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+
+        let err = read_values(&mut stream, 1, 1, 5, Decode::U16).await.unwrap_err();
+        assert!(is_modbus_error(&err));
+        assert!(err.to_string().contains("недопустимый адрес регистра"), "{}", err);
+        // Проверка числа регистров до отправки: соединение остаётся пригодным
+        for count in [0, MAX_REGISTERS + 1] {
+            assert!(is_modbus_error(&read_values(&mut stream, 1, 0, count, Decode::U16).await.unwrap_err()));
+        }
+        assert_eq!(read_values(&mut stream, 1, 0, 2, Decode::U16).await.unwrap(), "1 2");
+    }
+}
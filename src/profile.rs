@@ -0,0 +1,84 @@
+use std::{
+    fs,
+    io,
+    path::PathBuf,
+};
+use serde::{Deserialize, Serialize};
+use crate::{config, ServerInfo, TimeWindow, YAxis};
+
+// Именованный набор настроек стенда: серверы с калибровкой и вид графика.
+// Каждый профиль — отдельный файл <имя>.json в каталоге profiles рядом с конфигурацией
+#[derive(Serialize, Deserialize)]
+pub struct Profile {
+    pub servers:   Vec<ServerInfo>,
+    pub window:    TimeWindow,
+    pub y_axis:    YAxis,
+    // Нет в профилях до появления правой оси
+    #[serde(default)]
+    pub y_right:   YAxis,
+    pub smoothing: usize,
+}
+
+fn dir() -> io::Result<PathBuf> {
+    config::config_dir()
+        .map(|dir| dir.join("profiles"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))
+}
+
+fn path(name: &str) -> io::Result<PathBuf> {
+    Ok(dir()?.join(format!("{}.json", name)))
+}
+
+// Имя становится именем файла, поэтому разделители путей и служебные символы запрещены
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Пустое имя профиля".to_string());
+    }
+    if name != name.trim() || name.starts_with('.') {
+        return Err("Имя не может начинаться с точки или пробела и заканчиваться пробелом".to_string());
+    }
+    if let Some(c) = name.chars().find(|c| c.is_control() || r#"/\:*?"<>|"#.contains(*c)) {
+        return Err(format!("Недопустимый символ в имени: {:?}", c));
+    }
+    Ok(())
+}
+
+// Имена профилей по алфавиту. Нет каталога — нет профилей
+pub fn list() -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(dir()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    Ok(names)
+}
+
+pub fn load(name: &str) -> io::Result<Profile> {
+    let text = fs::read_to_string(path(name)?)?;
+    serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn save(name: &str, profile: &Profile) -> io::Result<()> {
+    fs::create_dir_all(dir()?)?;
+    fs::write(path(name)?, serde_json::to_string_pretty(profile)?)
+}
+
+pub fn delete(name: &str) -> io::Result<()> {
+    fs::remove_file(path(name)?)
+}
+
+// Существующий профиль с новым именем не перезаписывается
+pub fn rename(from: &str, to: &str) -> io::Result<()> {
+    let target = path(to)?;
+    if target.exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("Профиль {} уже есть", to)));
+    }
+    fs::rename(path(from)?, target)
+}
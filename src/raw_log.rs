@@ -0,0 +1,108 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    io,
+    path::Path,
+    string::FromUtf8Error,
+};
+
+// Память на сервер ограничена: не больше DEPTH ответов по MAX_BYTES байт
+const DEPTH:     usize = 64;
+const MAX_BYTES: usize = 4096;
+
+// Сырой ответ прибора для отладки: байты как пришли, до разбора на каналы
+#[derive(Clone)]
+pub struct RawResponse {
+    // Unix-время получения, мс
+    pub at:        u64,
+    pub bytes:     Vec<u8>,
+    // Ответ был длиннее MAX_BYTES и обрезан
+    pub truncated: bool,
+    pub error:     Option<String>,
+}
+
+impl RawResponse {
+    // Ответ не в UTF-8 приходит ошибкой, но его байты сохраняются в ней и тоже записываются
+    pub fn capture(resp: &Result<String, io::Error>, at: u64) -> Self {
+        let (bytes, error) = match resp {
+            Ok(text) => (text.as_bytes(), None),
+            Err(e) => {
+                let bytes = e.get_ref()
+                    .and_then(|inner| inner.downcast_ref::<FromUtf8Error>())
+                    .map_or(&[][..], |inner| inner.as_bytes());
+                (bytes, Some(e.to_string()))
+            }
+        };
+        Self {
+            at,
+            bytes:     bytes[..bytes.len().min(MAX_BYTES)].to_vec(),
+            truncated: bytes.len() > MAX_BYTES,
+            error,
+        }
+    }
+
+    pub fn hex(&self) -> String {
+        let mut out = String::with_capacity(self.bytes.len() * 3);
+        for (i, byte) in self.bytes.iter().enumerate() {
+            if i > 0 {
+                out.push(if i % 16 == 0 { '\n' } else { ' ' });
+            }
+            let _ = write!(out, "{:02x}", byte);
+        }
+        out
+    }
+
+    // Управляющие символы показываются escape-последовательностями, чтобы были видны \r и \0
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).escape_debug().to_string()
+    }
+}
+
+// Кольцевой буфер последних ответов сервера. Пишется, только пока включена отладка
+#[derive(Clone, Default)]
+pub struct RawLog {
+    entries:  VecDeque<RawResponse>,
+    // Итог последней выгрузки в файл
+    pub note: Option<String>,
+}
+
+impl RawLog {
+    pub fn push(&mut self, entry: RawResponse) {
+        if self.entries.len() == DEPTH {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Новые сверху
+    pub fn iter(&self) -> impl Iterator<Item = &RawResponse> {
+        self.entries.iter().rev()
+    }
+
+    // Текстовая выгрузка: заголовок записи, строка текста и hex-дамп
+    pub fn dump(&self, server: &str, path: &Path) -> io::Result<()> {
+        let mut out = format!("# {}: {} ответов\n", server, self.len());
+        for entry in self.entries.iter() {
+            let _ = writeln!(out, "\n@ {} мс, {} байт{}", entry.at, entry.bytes.len(), if entry.truncated { " (обрезано)" } else { "" });
+            if let Some(error) = &entry.error {
+                let _ = writeln!(out, "ошибка: {}", error);
+            }
+            let _ = writeln!(out, "текст: {}", entry.text());
+            let _ = writeln!(out, "{}", entry.hex());
+        }
+        fs::write(path, out)
+    }
+}
@@ -0,0 +1,195 @@
+use tokio::sync::watch;
+
+// Жизненный цикл сбора. Любая смена состояния идёт через RunControl::try_transition
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunState {
+    Idle,
+    Collecting,
+    // Пауза: данные и время начала сохраняются, отсчёты не пишутся
+    Paused,
+    // Переходное: сборщик завершает запуск и сбрасывает данные
+    Stopping,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunCommand {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+    // Сборщик закончил остановку
+    Finish,
+}
+
+impl RunState {
+    // Таблица переходов. Всё, что не перечислено, запрещено
+    pub fn next(self, command: RunCommand) -> Result<RunState, String> {
+        match (self, command) {
+            (RunState::Idle,       RunCommand::Start)  => Ok(RunState::Collecting),
+            (RunState::Collecting, RunCommand::Pause)  => Ok(RunState::Paused),
+            (RunState::Paused,     RunCommand::Resume) => Ok(RunState::Collecting),
+            (RunState::Collecting, RunCommand::Stop)   => Ok(RunState::Stopping),
+            (RunState::Paused,     RunCommand::Stop)   => Ok(RunState::Stopping),
+            (RunState::Stopping,   RunCommand::Finish) => Ok(RunState::Idle),
+            (state, command) => Err(format!(
+                "«{}» невозможно в состоянии «{}»",
+                command.label(),
+                state.label(),
+            )),
+        }
+    }
+
+    pub fn is_collecting(&self) -> bool {
+        *self == RunState::Collecting
+    }
+
+    // Список серверов и настройки сбора меняются только в простое
+    pub fn is_idle(&self) -> bool {
+        *self == RunState::Idle
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunState::Idle       => "Ожидание",
+            RunState::Collecting => "Идёт сбор",
+            RunState::Paused     => "Пауза",
+            RunState::Stopping   => "Остановка (сброс данных)",
+        }
+    }
+}
+
+impl RunCommand {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunCommand::Start  => "Старт",
+            RunCommand::Pause  => "Пауза",
+            RunCommand::Resume => "Продолжить",
+            RunCommand::Stop   => "Стоп",
+            RunCommand::Finish => "Завершение",
+        }
+    }
+}
+
+// Владелец состояния. Сборщик и GUI наблюдают за ним через watch-канал
+pub struct RunControl {
+    tx: watch::Sender<RunState>,
+}
+
+impl RunControl {
+    pub fn new() -> Self {
+        Self { tx: watch::Sender::new(RunState::Idle) }
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<RunState> {
+        self.tx.subscribe()
+    }
+
+    pub fn try_transition(&self, command: RunCommand) -> Result<RunState, String> {
+        let mut result = Err(String::new());
+        self.tx.send_if_modified(|state| {
+            result = state.next(command);
+            match result {
+                Ok(next) => {
+                    *state = next;
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+        if let Err(reason) = &result {
+            tracing::warn!(%reason, "run state transition rejected");
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATES: [RunState; 4] = [RunState::Idle, RunState::Collecting, RunState::Paused, RunState::Stopping];
+    const COMMANDS: [RunCommand; 5] = [
+        RunCommand::Start,
+        RunCommand::Pause,
+        RunCommand::Resume,
+        RunCommand::Stop,
+        RunCommand::Finish,
+    ];
+
+    // Ожидаемая таблица, записанная отдельно от RunState::next
+    fn expected(state: RunState, command: RunCommand) -> Option<RunState> {
+        use RunCommand::*;
+        use RunState::*;
+        match (state, command) {
+            (Idle, Start) => Some(Collecting),
+            (Collecting, Pause) => Some(Paused),
+            (Collecting, Stop) => Some(Stopping),
+            (Paused, Resume) => Some(Collecting),
+            (Paused, Stop) => Some(Stopping),
+            (Stopping, Finish) => Some(Idle),
+            _ => None,
+        }
+    }
+
+    // RunControl в заданном состоянии, приведённый туда разрешёнными переходами
+    fn control_in(state: RunState) -> RunControl {
+        let control = RunControl::new();
+        let path: &[RunCommand] = match state {
+            RunState::Idle => &[],
+            RunState::Collecting => &[RunCommand::Start],
+            RunState::Paused => &[RunCommand::Start, RunCommand::Pause],
+            RunState::Stopping => &[RunCommand::Start, RunCommand::Stop],
+        };
+        for command in path {
+            control.try_transition(*command).unwrap();
+        }
+        assert_eq!(*control.subscribe().borrow(), state);
+        control
+    }
+
+    #[test]
+    fn transition_table_is_exhaustive() {
+        for state in STATES {
+            for command in COMMANDS {
+                match (state.next(command), expected(state, command)) {
+                    (Ok(next), Some(want)) => assert_eq!(next, want, "{:?} + {:?}", state, command),
+                    (Err(reason), None) => {
+                        assert!(reason.contains(command.label()) && reason.contains(state.label()), "{}", reason)
+                    }
+                    (got, want) => panic!("{:?} + {:?}: got {:?}, want {:?}", state, command, got, want),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn try_transition_applies_allowed_and_keeps_rejected() {
+        for state in STATES {
+            for command in COMMANDS {
+                let control = control_in(state);
+                let mut rx = control.subscribe();
+                rx.mark_unchanged();
+                let result = control.try_transition(command);
+                match expected(state, command) {
+                    Some(want) => {
+                        assert_eq!(result, Ok(want));
+                        assert!(rx.has_changed().unwrap(), "{:?} + {:?} not published", state, command);
+                        assert_eq!(*rx.borrow_and_update(), want);
+                    }
+                    None => {
+                        assert!(result.is_err());
+                        // Запрещённый переход не будит наблюдателей и не меняет значение
+                        assert!(!rx.has_changed().unwrap(), "{:?} + {:?} woke receivers", state, command);
+                        assert_eq!(*rx.borrow(), state);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn labels_are_distinct() {
+        let labels: std::collections::HashSet<_> = STATES.iter().map(RunState::label).collect();
+        assert_eq!(labels.len(), STATES.len());
+    }
+}
@@ -0,0 +1,44 @@
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_serial::SerialPortBuilderExt;
+use crate::{source, unescape_command};
+
+// Ответ длиннее этого без терминатора считаем мусором, а не ждём до таймаута
+const MAX_RESPONSE: usize = 4096;
+
+// Порт открывается на время одного опроса (по умолчанию 8N1) и закрывается при выходе,
+// поэтому занятый или пропавший порт даёт ошибку ОС только этому серверу и не держится после закрытия приложения
+pub async fn fetch(path: &str, baud: u32, request: &str, terminator: &str) -> io::Result<String> {
+    let mut port = tokio_serial::new(path, baud).open_native_async().map_err(io::Error::from)?;
+    let request = unescape_command(request);
+    if !request.is_empty() {
+        port.write_all(&request).await?;
+    }
+
+    let terminator = unescape_command(terminator);
+    let mut response = Vec::new();
+    let mut buf = [0u8; 256];
+    while !response.ends_with(&terminator) {
+        let n = port.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+        if response.len() > MAX_RESPONSE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Нет терминатора строки в ответе"));
+        }
+    }
+    // Порт закрылся посреди ответа: без терминатора конец строки мог потеряться, отсчёт не берём
+    if !response.ends_with(&terminator) {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Порт закрыт до терминатора строки"));
+    }
+    response.truncate(response.len() - terminator.len());
+    source::decode(response)
+}
+
+// Имена портов для выпадающего списка. Ошибка перечисления — просто пустой список
+pub fn available_ports() -> Vec<String> {
+    tokio_serial::available_ports()
+        .map(|ports| ports.into_iter().map(|p| p.port_name).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs,
+    io,
+    path::Path,
+    time::Duration,
+};
+use serde::{Deserialize, Serialize};
+use crate::{availability::{self, ServerAvailability}, channel::{self, Column}, export, ComputationResults, Marker, ServerData, ServerInfo, TICK_INTERVAL};
+
+// Повышается при несовместимом изменении схемы, чтобы загрузка могла отказаться от чужого файла
+// 2 — пропуски отсчётов записываются как null
+// 3 — время в миллисекундах (start_time и timestamp), раньше было в секундах
+// 4 — id серверов и раскладка колонок flow
+pub const FORMAT_VERSION: u32 = 4;
+const MILLIS_VERSION: u32 = 3;
+
+// Сохранённая сессия сбора.
+// flow в каждом отсчёте — значения по колонкам columns: колонка называет id сервера и номер канала.
+// null — канал не дал значения на этом тике. Если сервер добавили посреди сессии,
+// ранние отсчёты короче полного списка колонок
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub format_version: u32,
+    // Unix-время первого отсчёта в мс, None для пустой сессии
+    pub start_time:     Option<u64>,
+    pub servers:        Vec<ServerInfo>,
+    // Нет в файлах до версии 4: тогда flow шёл по каналам servers подряд
+    #[serde(default)]
+    pub columns:        Vec<Column>,
+    pub results:        Vec<ComputationResults>,
+    // Нет в файлах до появления метаданных
+    #[serde(default)]
+    pub metadata:       Metadata,
+    // Простои серверов для отчёта (см. availability.rs). Пишется при сохранении,
+    // при загрузке не нужен: пересчитывается из флагов качества
+    #[serde(default)]
+    pub availability:   Vec<ServerAvailability>,
+    // Отметки оператора на шкале времени. Нет в файлах до их появления
+    #[serde(default)]
+    pub markers:        Vec<Marker>,
+}
+
+// Что нужно, чтобы через месяцы понять, откуда взялись числа. Адреса и калибровка
+// серверов лежат в servers, здесь — остальное
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    // Версия enlil, записавшая файл
+    pub app_version:      String,
+    pub poll_interval_ms: u64,
+    // Заметки оператора: стенд, геометрия, условия опыта
+    pub notes:            String,
+    // Значения сгенерированы демо-режимом, а не сняты с приборов
+    pub demo:             bool,
+}
+
+impl Metadata {
+    pub fn from_data(data: &ServerData) -> Self {
+        Self {
+            app_version:      env!("CARGO_PKG_VERSION").to_string(),
+            poll_interval_ms: TICK_INTERVAL.as_millis() as u64,
+            notes:            data.notes.clone(),
+            demo:             data.demo,
+        }
+    }
+}
+
+impl Session {
+    pub fn from_data(data: &ServerData) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            start_time:     data.start_time,
+            servers:        data.servers.clone(),
+            columns:        data.columns.clone(),
+            results:        data.computed_results.clone(),
+            metadata:       Metadata::from_data(data),
+            availability:   availability::report(&data.computed_results, &data.columns, &data.servers),
+            markers:        data.markers.clone(),
+        }
+    }
+
+    // Данные только для просмотра: статусы серверов не проверены, конфигурация не меняется
+    pub fn into_data(self) -> ServerData {
+        ServerData {
+            servers:          self.servers,
+            columns:          self.columns,
+            computed_results: self.results,
+            start_time:       self.start_time,
+            notes:            self.metadata.notes,
+            markers:          self.markers,
+            ..Default::default()
+        }
+    }
+}
+
+// Срез сессии для выгрузки: отсчёты, события и отметки в [from, to] мс. Время остаётся от начала
+// всей сессии, чтобы срез сверялся с полной выгрузкой. Границы вне данных прижимаются к первому
+// и последнему отсчёту, а не дают ошибку
+pub fn slice(data: &ServerData, from: u64, to: u64) -> ServerData {
+    let results = &data.computed_results;
+    let (first, last) = match (results.first(), results.last()) {
+        (Some(first), Some(last)) => (first.timestamp, last.timestamp),
+        _ => (0, 0),
+    };
+    let (from, to) = (from.clamp(first, last), to.clamp(first, last));
+    let range = results.partition_point(|r| r.timestamp < from)..results.partition_point(|r| r.timestamp <= to);
+    ServerData {
+        servers:          data.servers.clone(),
+        columns:          data.columns.clone(),
+        computed_results: results[range].to_vec(),
+        start_time:       data.start_time,
+        alerts:           data.alerts
+            .iter()
+            .filter(|alert| alert.timestamp <= to && alert.resolved.is_none_or(|resolved| resolved >= from))
+            .cloned()
+            .collect(),
+        markers:          data.markers.iter().filter(|m| (from..=to).contains(&m.timestamp)).cloned().collect(),
+        notes:            data.notes.clone(),
+        demo:             data.demo,
+        ..Default::default()
+    }
+}
+
+// Отбрасывает отсчёты раньше before мс. Время оставшихся отсчётов, событий и отметок сдвигается
+// так, чтобы первый оставшийся отсчёт пришёлся на ноль; начало сессии сдвигается вперёд на столько же.
+// before за последним отсчётом прижимается к нему: хотя бы один отсчёт остаётся
+pub fn trim(data: &mut ServerData, before: u64) {
+    let Some(last) = data.computed_results.last().map(|r| r.timestamp) else { return };
+    let cut = data.computed_results.partition_point(|r| r.timestamp < before.min(last));
+    data.computed_results.drain(..cut);
+    let offset = data.computed_results[0].timestamp;
+    for result in &mut data.computed_results {
+        result.timestamp -= offset;
+    }
+    if let Some(first) = data.computed_results.first_mut() {
+        first.after_pause = false;
+    }
+    data.start_time = data.start_time.map(|t| t + offset);
+    data.started = data.started.map(|t| t + Duration::from_millis(offset));
+    data.missed = data.computed_results.iter().map(|r| r.channels.saturating_sub(r.sampled) as u64).sum();
+
+    // Событие, начавшееся до обрезки и ещё не закрытое к ней, начинается с нуля
+    data.alerts.retain(|alert| alert.resolved.is_none_or(|resolved| resolved >= offset));
+    for alert in &mut data.alerts {
+        alert.timestamp = alert.timestamp.saturating_sub(offset);
+        alert.resolved = alert.resolved.map(|t| t - offset);
+    }
+    data.markers.retain(|marker| marker.timestamp >= offset);
+    for marker in &mut data.markers {
+        marker.timestamp -= offset;
+    }
+    data.unsaved = true;
+}
+
+pub fn save_json(data: &ServerData, path: &Path) -> io::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(&Session::from_data(data))?)
+}
+
+// Формат выбирается по расширению: .xlsx — лист из save_to_excel, остальное — JSON
+pub fn load(path: &Path) -> Result<Session, String> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xlsx")) {
+        return export::load_excel(path).map_err(|e| e.to_string());
+    }
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut session: Session = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    if session.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "Файл сохранён более новой версией (формат {}, поддерживается до {})",
+            session.format_version, FORMAT_VERSION
+        ));
+    }
+    // Старые файлы хранили время в секундах
+    if session.format_version < MILLIS_VERSION {
+        session.start_time = session.start_time.map(|t| t * 1000);
+        for result in &mut session.results {
+            result.timestamp *= 1000;
+        }
+    }
+    channel::assign_ids(&mut session.servers);
+    if session.columns.is_empty() {
+        session.columns = channel::layout(&session.servers);
+    }
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::alert::{AlertEvent, Bound};
+
+    // Пять отсчётов через секунду, у третьего один канал из двух пропущен
+    fn recorded() -> ServerData {
+        let result = |timestamp, sampled| ComputationResults { timestamp, sampled, channels: 2, after_pause: true, ..Default::default() };
+        let alert = |timestamp, resolved| AlertEvent {
+            timestamp, channel: "m1".to_string(), value: 80.0, bound: Bound::Above, limit: 50.0, resolved, acknowledged: false,
+        };
+        let marker = |timestamp| Marker { timestamp, label: format!("at {}", timestamp) };
+        ServerData {
+            start_time:       Some(1_715_689_800_000),
+            started:          Some(Instant::now()),
+            computed_results: vec![result(0, 2), result(1000, 2), result(2000, 1), result(3000, 2), result(4000, 2)],
+            missed:           1,
+            alerts:           vec![alert(500, Some(1500)), alert(1500, Some(3500)), alert(2500, None)],
+            markers:          vec![marker(1000), marker(2500)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn trim_rebases_everything_on_the_cut() {
+        let mut data = recorded();
+        let started = data.started.unwrap();
+        trim(&mut data, 1800);
+
+        // Первый оставшийся отсчёт — 2000 мс, от него и считается новое начало
+        let timestamps: Vec<_> = data.computed_results.iter().map(|r| r.timestamp).collect();
+        assert_eq!(timestamps, [0, 1000, 2000]);
+        assert!(!data.computed_results[0].after_pause);
+        assert!(data.computed_results[1].after_pause);
+        assert_eq!(data.start_time, Some(1_715_689_802_000));
+        assert_eq!(data.started, Some(started + Duration::from_millis(2000)));
+        assert_eq!(data.missed, 1);
+
+        // Закрытое до обрезки событие уходит, идущее через неё начинается с нуля
+        let alerts: Vec<_> = data.alerts.iter().map(|a| (a.timestamp, a.resolved)).collect();
+        assert_eq!(alerts, [(0, Some(1500)), (500, None)]);
+        let markers: Vec<_> = data.markers.iter().map(|m| (m.timestamp, m.label.as_str())).collect();
+        assert_eq!(markers, [(500, "at 2500")]);
+        assert!(data.unsaved);
+    }
+
+    #[test]
+    fn trim_past_the_end_keeps_the_last_sample() {
+        let mut data = recorded();
+        trim(&mut data, 60_000);
+
+        assert_eq!(data.computed_results.len(), 1);
+        assert_eq!(data.computed_results[0].timestamp, 0);
+        assert_eq!(data.start_time, Some(1_715_689_804_000));
+        // Пропуск был в отброшенном отсчёте
+        assert_eq!(data.missed, 0);
+        let alerts: Vec<_> = data.alerts.iter().map(|a| (a.timestamp, a.resolved)).collect();
+        assert_eq!(alerts, [(0, None)]);
+        assert!(data.markers.is_empty());
+    }
+
+    #[test]
+    fn trim_of_empty_session_changes_nothing() {
+        let mut data = ServerData::default();
+        trim(&mut data, 1000);
+        assert!(data.computed_results.is_empty());
+        assert!(!data.unsaved);
+    }
+}
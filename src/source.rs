@@ -0,0 +1,131 @@
+use std::{
+    future::Future,
+    io::{self, ErrorKind},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    time,
+};
+use crate::{
+    connect, http, modbus, resolve, serial, stream, tls, udp, unescape_command,
+    ServerInfo, SourceKind, TICK_INTERVAL,
+};
+
+// Источник значений: один запрос — один ответ текстом, который разбирается на каналы.
+// Таймаут покрывает весь обмен, включая подключение
+pub trait DataSource {
+    fn fetch(&self, timeout: Duration) -> impl Future<Output = io::Result<String>> + Send;
+    // Короткое описание для журнала и подсказок: протокол и куда он обращается
+    fn describe(&self) -> String;
+}
+
+// Запрос-ответ по TCP, при необходимости поверх TLS
+pub struct TcpSource<'a> {
+    pub address: &'a str,
+    pub command: &'a str,
+    pub tls:     &'a tls::TlsSettings,
+}
+
+impl DataSource for TcpSource<'_> {
+    async fn fetch(&self, timeout: Duration) -> io::Result<String> {
+        let command = unescape_command(self.command);
+        let request = async {
+            let stream = connect(self.address).await?;
+            if self.tls.enabled {
+                request_response(tls::wrap(stream, self.address, self.tls).await?, &command).await
+            } else {
+                request_response(stream, &command).await
+            }
+        };
+        let result = within(timeout, request).await;
+        if let Err(e) = &result {
+            tracing::debug!(address = self.address, tls = self.tls.enabled, error = %e, "TCP request failed");
+        }
+        result
+    }
+
+    fn describe(&self) -> String {
+        let tls = if self.tls.enabled { " (TLS)" } else { "" };
+        format!("TCP {}{}", self.address, tls)
+    }
+}
+
+async fn request_response<S>(mut stream: S, command: &[u8]) -> io::Result<String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(command).await?;
+
+    let mut response = Vec::new();
+    match stream.read_to_end(&mut response).await {
+        Ok(_) => {}
+        // Приборы часто закрывают TLS-сессию без close_notify; полученный ответ при этом целый
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof && !response.is_empty() => {}
+        Err(e) => return Err(e),
+    }
+    decode(response)
+}
+
+// Ответ прибора как текст. Неверную кодировку классификатор отличает от прочих ошибок кадра
+// с тем же видом InvalidData по вложенной ошибке (см. FetchFailure::InvalidUtf8)
+pub fn decode(response: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(response).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+pub fn is_utf8_error(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<std::string::FromUtf8Error>())
+}
+
+// Сервер выбирает реализацию по своему SourceKind
+impl DataSource for ServerInfo {
+    async fn fetch(&self, timeout: Duration) -> io::Result<String> {
+        match &self.source {
+            // Строка считается свежей, если пришла за последний тик с запасом на таймаут
+            SourceKind::Tcp { streaming: true, .. } => stream::latest(&self.address, TICK_INTERVAL + timeout),
+            SourceKind::Tcp { command, tls, .. } => {
+                TcpSource { address: &self.address, command, tls }.fetch(timeout).await
+            }
+            SourceKind::Http { url, json_pointer } => within(timeout, http::fetch(url, json_pointer)).await,
+            SourceKind::Modbus { unit_id, register, count, decode } => {
+                within(timeout, async {
+                    let mut stream = connect(&self.address).await?;
+                    modbus::read_values(&mut stream, *unit_id, *register, *count, *decode).await
+                }).await
+            }
+            SourceKind::Serial { port, baud, request, terminator } => {
+                within(timeout, serial::fetch(port, *baud, request, terminator)).await
+            }
+            // Значение подставляет evaluate_derived после опроса остальных
+            SourceKind::Derived { .. } => Ok(String::new()),
+            SourceKind::Udp { local_port, trigger } => {
+                within(timeout, async {
+                    let peer = resolve(&self.address).await?[0];
+                    udp::fetch(*local_port, peer, &unescape_command(trigger)).await
+                }).await
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.source {
+            SourceKind::Tcp { streaming: true, .. } => format!("TCP {} (поток)", self.address),
+            SourceKind::Tcp { command, tls, .. } => TcpSource { address: &self.address, command, tls }.describe(),
+            SourceKind::Http { url, .. } => format!("HTTP {}", url),
+            SourceKind::Modbus { unit_id, register, .. } => format!("Modbus {} unit {} reg {}", self.address, unit_id, register),
+            SourceKind::Serial { port, baud, .. } => format!("Serial {} {} бод", port, baud),
+            SourceKind::Derived { expression } => format!("= {}", expression),
+            SourceKind::Udp { local_port, .. } => format!("UDP {} → :{}", self.address, local_port),
+        }
+    }
+}
+
+async fn within<F>(timeout: Duration, request: F) -> io::Result<String>
+where
+    F: Future<Output = io::Result<String>>,
+{
+    match time::timeout(timeout, request).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(ErrorKind::TimedOut, "Response timeout")),
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    task::JoinHandle,
+    time,
+};
+
+const BACKOFF_START: Duration = Duration::from_millis(500);
+const BACKOFF_MAX:   Duration = Duration::from_secs(30);
+
+// Соединение открыто, но свежей строки нет. Сервер при этом считается доступным
+#[derive(Debug)]
+pub struct NoRecentData;
+
+impl fmt::Display for NoRecentData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Соединение открыто, новых данных нет")
+    }
+}
+
+impl std::error::Error for NoRecentData {}
+
+pub fn is_no_recent_data(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<NoRecentData>())
+}
+
+#[derive(Default)]
+struct StreamState {
+    connected:  bool,
+    last_line:  Option<(String, Instant)>,
+    last_error: Option<(io::ErrorKind, String)>,
+}
+
+struct Stream {
+    state: Arc<Mutex<StreamState>>,
+    task:  JoinHandle<()>,
+}
+
+fn streams() -> &'static Mutex<HashMap<String, Stream>> {
+    static STREAMS: OnceLock<Mutex<HashMap<String, Stream>>> = OnceLock::new();
+    STREAMS.get_or_init(Default::default)
+}
+
+// Последняя строка, пришедшая не раньше max_age назад. При первом обращении к адресу
+// запускается фоновое чтение; отключение сообщается последней ошибкой соединения
+pub fn latest(address: &str, max_age: Duration) -> io::Result<String> {
+    let state = {
+        let mut streams = streams().lock().unwrap_or_else(|e| e.into_inner());
+        let stream = streams.entry(address.to_string()).or_insert_with(|| {
+            let state = Arc::new(Mutex::new(StreamState::default()));
+            let task = tokio::spawn(run(address.to_string(), state.clone()));
+            Stream { state, task }
+        });
+        stream.state.clone()
+    };
+
+    let state = state.lock().unwrap_or_else(|e| e.into_inner());
+    if !state.connected {
+        return Err(match &state.last_error {
+            Some((kind, message)) => io::Error::new(*kind, message.clone()),
+            None => io::Error::new(io::ErrorKind::NotConnected, "Подключение…"),
+        });
+    }
+    match &state.last_line {
+        Some((line, at)) if at.elapsed() <= max_age => Ok(line.clone()),
+        _ => Err(io::Error::other(NoRecentData)),
+    }
+}
+
+// Закрывает соединения адресов, которые больше не работают в потоковом режиме
+pub fn retain(addresses: &HashSet<&str>) {
+    streams().lock().unwrap_or_else(|e| e.into_inner()).retain(|address, stream| {
+        let keep = addresses.contains(address.as_str());
+        if !keep {
+            stream.task.abort();
+        }
+        keep
+    });
+}
+
+// Держит одно соединение и читает строки; при обрыве переподключается с растущей паузой
+async fn run(address: String, state: Arc<Mutex<StreamState>>) {
+    let mut backoff = BACKOFF_START;
+    loop {
+        match crate::connect(&address).await {
+            Ok(stream) => {
+                backoff = BACKOFF_START;
+                update(&state, |s| s.connected = true);
+                let error = read_lines(stream, &state).await;
+                update(&state, |s| {
+                    s.connected = false;
+                    s.last_error = Some((error.kind(), error.to_string()));
+                });
+            }
+            Err(e) => update(&state, |s| s.last_error = Some((e.kind(), e.to_string()))),
+        }
+        time::sleep(backoff).await;
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
+
+// Возвращает причину, по которой чтение прекратилось
+async fn read_lines(stream: tokio::net::TcpStream, state: &Mutex<StreamState>) -> io::Error {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(0) => return io::Error::new(io::ErrorKind::ConnectionReset, "Соединение закрыто прибором"),
+            Ok(_) => {
+                let text = line.trim_end().to_string();
+                update(state, |s| s.last_line = Some((text, Instant::now())));
+            }
+            Err(e) => return e,
+        }
+    }
+}
+
+fn update(state: &Mutex<StreamState>, f: impl FnOnce(&mut StreamState)) {
+    f(&mut state.lock().unwrap_or_else(|e| e.into_inner()));
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io,
+    sync::{Arc, Mutex, OnceLock},
+};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{ring, CryptoProvider},
+        pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    },
+    TlsConnector,
+};
+use crate::address;
+
+// TLS поверх TCP-источника. Пустой ca_path — системные корневые сертификаты
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsSettings {
+    pub enabled:  bool,
+    pub ca_path:  String,
+    // Без проверки сертификата — только для стенда
+    pub insecure: bool,
+}
+
+// Не удалось собрать конфигурацию: нет файла CA, нет ни одного сертификата, неверное имя узла
+#[derive(Debug)]
+pub struct TlsConfigError(pub String);
+
+impl fmt::Display for TlsConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TLS: {}", self.0)
+    }
+}
+
+impl std::error::Error for TlsConfigError {}
+
+fn config_error(message: impl Into<String>) -> io::Error {
+    io::Error::other(TlsConfigError(message.into()))
+}
+
+// tokio-rustls отдаёт ошибки рукопожатия и проверки сертификата как io::Error с rustls::Error внутри
+pub fn is_tls_error(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<rustls::Error>() || inner.is::<TlsConfigError>())
+}
+
+pub async fn wrap(stream: TcpStream, address: &str, settings: &TlsSettings) -> io::Result<TlsStream<TcpStream>> {
+    let connector = TlsConnector::from(client_config(settings)?);
+    connector.connect(server_name(address)?, stream).await
+}
+
+// Имя для SNI и проверки сертификата берётся из адреса сервера
+fn server_name(address: &str) -> io::Result<ServerName<'static>> {
+    match address::parse(address).map_err(config_error)? {
+        address::Target::Socket(addr) => Ok(ServerName::from(addr.ip())),
+        address::Target::Host(host, _) => ServerName::try_from(host).map_err(|e| config_error(e.to_string())),
+    }
+}
+
+// Ключ — (путь к CA, insecure)
+type ConfigCache = Mutex<HashMap<(String, bool), Arc<ClientConfig>>>;
+
+// Конфигурация собирается один раз на пару (CA, insecure): системные корни читаются с диска
+fn client_config(settings: &TlsSettings) -> io::Result<Arc<ClientConfig>> {
+    static CONFIGS: OnceLock<ConfigCache> = OnceLock::new();
+    let key = (settings.ca_path.clone(), settings.insecure);
+    let mut configs = CONFIGS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(config) = configs.get(&key) {
+        return Ok(config.clone());
+    }
+
+    let provider = Arc::new(ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| config_error(e.to_string()))?;
+    let config = if settings.insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+            .with_no_client_auth()
+    } else {
+        builder.with_root_certificates(root_store(&settings.ca_path)?).with_no_client_auth()
+    };
+    let config = Arc::new(config);
+    configs.insert(key, config.clone());
+    Ok(config)
+}
+
+fn root_store(ca_path: &str) -> io::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    if ca_path.is_empty() {
+        roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    } else {
+        let certs = CertificateDer::pem_file_iter(ca_path)
+            .map_err(|e| config_error(format!("{}: {}", ca_path, e)))?;
+        for cert in certs {
+            let cert = cert.map_err(|e| config_error(format!("{}: {}", ca_path, e)))?;
+            roots.add(cert).map_err(|e| config_error(format!("{}: {}", ca_path, e)))?;
+        }
+    }
+    if roots.is_empty() {
+        return Err(config_error("нет ни одного корневого сертификата"));
+    }
+    Ok(roots)
+}
+
+// Принимает любой сертификат. Подписи рукопожатия всё равно проверяются, чтобы соединение было целым
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
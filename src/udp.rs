@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, OnceLock},
+};
+use tokio::{net::UdpSocket, sync::oneshot};
+use crate::source;
+
+// Один сокет на локальный порт. Несколько серверов на одном порту делят его,
+// а пришедший пакет отдаётся тем, кто ждёт ответа от этого IP
+struct SharedSocket {
+    socket:  Arc<UdpSocket>,
+    waiters: Mutex<HashMap<IpAddr, Vec<oneshot::Sender<Vec<u8>>>>>,
+}
+
+fn sockets() -> &'static Mutex<HashMap<u16, Arc<SharedSocket>>> {
+    static SOCKETS: OnceLock<Mutex<HashMap<u16, Arc<SharedSocket>>>> = OnceLock::new();
+    SOCKETS.get_or_init(Default::default)
+}
+
+// Порт привязывается при первом обращении и держится до конца работы приложения
+fn socket_for(local_port: u16) -> io::Result<Arc<SharedSocket>> {
+    let mut sockets = sockets().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(shared) = sockets.get(&local_port) {
+        return Ok(shared.clone());
+    }
+    let std_socket = std::net::UdpSocket::bind(("0.0.0.0", local_port))?;
+    std_socket.set_nonblocking(true)?;
+    let shared = Arc::new(SharedSocket {
+        socket:  Arc::new(UdpSocket::from_std(std_socket)?),
+        waiters: Mutex::new(HashMap::new()),
+    });
+    tokio::spawn(dispatch(shared.clone()));
+    sockets.insert(local_port, shared.clone());
+    Ok(shared)
+}
+
+// Раздаёт пакеты ждущим по IP отправителя. Пакеты от неизвестных узлов отбрасываются
+async fn dispatch(shared: Arc<SharedSocket>) {
+    let mut buf = vec![0u8; 65_536];
+    loop {
+        let (len, from) = match shared.socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                tracing::warn!(error = %e, "UDP receive failed");
+                continue;
+            }
+        };
+        let waiters = shared.waiters.lock().unwrap_or_else(|e| e.into_inner()).remove(&from.ip());
+        for waiter in waiters.into_iter().flatten() {
+            let _ = waiter.send(buf[..len].to_vec());
+        }
+    }
+}
+
+// Ждёт следующий пакет от peer, перед этим отправив trigger, если он задан.
+// Совпадение идёт по IP: логгеры часто шлют с произвольного исходного порта.
+// Таймаут накладывает вызывающий
+pub async fn fetch(local_port: u16, peer: SocketAddr, trigger: &[u8]) -> io::Result<String> {
+    let shared = socket_for(local_port)?;
+    let (tx, rx) = oneshot::channel();
+    {
+        let mut waiters = shared.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        let queue = waiters.entry(peer.ip()).or_default();
+        // Ожидания, брошенные по таймауту, иначе копились бы, пока узел молчит
+        queue.retain(|waiter| !waiter.is_closed());
+        queue.push(tx);
+    }
+    if !trigger.is_empty() {
+        shared.socket.send_to(trigger, peer).await?;
+    }
+    let packet = rx.await.map_err(|_| io::Error::other("UDP socket closed"))?;
+    source::decode(packet)
+}